@@ -0,0 +1,186 @@
+//! Values which change over time, given at a few keyframes and interpolated in between, and the
+//! timing of the frames of an animation.
+
+use crate::{prelude::*, transform::Transform};
+
+/// How values are interpolated between keyframes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    /// Straight from one key to the next, with a sudden change of speed at each key.
+    Linear,
+    /// A Catmull-Rom spline, passing through the keys with a speed at each given by its two
+    /// neighbours, so that motion is smooth.
+    CatmullRom,
+}
+
+/// Anything which can be keyframed, having weighted averages.
+pub trait Animatable: Copy {
+    /// The average of `values`, whose weights add up to one but may be negative.
+    fn blend(values: &[(Float, Self)]) -> Self;
+}
+
+impl Animatable for Float {
+    fn blend(values: &[(Float, Self)]) -> Self {
+        values.iter().map(|(w, v)| w * v).sum()
+    }
+}
+
+impl Animatable for Dir {
+    fn blend(values: &[(Float, Self)]) -> Self {
+        values.iter().map(|&(w, v)| w * v).sum()
+    }
+}
+
+impl Animatable for Pos {
+    fn blend(values: &[(Float, Self)]) -> Self {
+        let origin = values[0].1;
+        origin + values.iter().map(|&(w, p)| w * (p - origin)).sum()
+    }
+}
+
+impl Animatable for Transform {
+    fn blend(values: &[(Float, Self)]) -> Self {
+        Transform::blend(values)
+    }
+}
+
+/// A value given at times `keys`, held before the first and after the last.
+#[derive(Clone, Debug)]
+pub struct Keyframes<T> {
+    keys: Vec<(Float, T)>,
+    interpolation: Interpolation,
+}
+
+impl<T: Animatable> Keyframes<T> {
+    /// The keys are pairs of a time and a value, in order of time.
+    pub fn new(interpolation: Interpolation, keys: Vec<(Float, T)>) -> Self {
+        assert!(!keys.is_empty(), "Keyframes need at least one key");
+        assert!(
+            keys.windows(2).all(|k| k[0].0 < k[1].0),
+            "Keyframes must be in order of time"
+        );
+        Self {
+            keys,
+            interpolation,
+        }
+    }
+
+    /// A value which doesn't change.
+    pub fn constant(value: T) -> Self {
+        Self::new(Interpolation::Linear, vec![(0., value)])
+    }
+
+    /// Times of the keys.
+    pub fn times(&self) -> impl Iterator<Item = Float> + '_ {
+        self.keys.iter().map(|&(t, _)| t)
+    }
+
+    pub fn at(&self, time: Float) -> T {
+        let keys = &self.keys;
+        let n = keys.len();
+        // The key ending the segment `time` is in
+        let i = match keys.iter().position(|&(t, _)| t > time) {
+            Some(0) => return keys[0].1,
+            Some(i) => i,
+            None => return keys[n - 1].1,
+        };
+        let ((t1, p1), (t2, p2)) = (keys[i - 1], keys[i]);
+        let s = (time - t1) / (t2 - t1);
+        match self.interpolation {
+            Interpolation::Linear => T::blend(&[(1. - s, p1), (s, p2)]),
+            Interpolation::CatmullRom => {
+                // Ends are repeated, so that the spline starts and stops with the straight line
+                let (t0, p0) = if i >= 2 { keys[i - 2] } else { (t1, p1) };
+                let (t3, p3) = if i + 1 < n { keys[i + 1] } else { (t2, p2) };
+                // Tangents from the neighbours, scaled to the length of the segment
+                let a = if t2 > t0 { (t2 - t1) / (t2 - t0) } else { 0. };
+                let b = if t3 > t1 { (t2 - t1) / (t3 - t1) } else { 0. };
+                let (s2, s3) = (s * s, s * s * s);
+                let h00 = 2. * s3 - 3. * s2 + 1.;
+                let h10 = s3 - 2. * s2 + s;
+                let h01 = -2. * s3 + 3. * s2;
+                let h11 = s3 - s2;
+                T::blend(&[
+                    (-h10 * a, p0),
+                    (h00 - h11 * b, p1),
+                    (h01 + h10 * a, p2),
+                    (h11 * b, p3),
+                ])
+            }
+        }
+    }
+}
+
+/// The path of a camera, keyframing where it is and where it looks.
+#[derive(Clone, Debug)]
+pub struct CameraPath {
+    pub look_from: Keyframes<Pos>,
+    pub look_at: Keyframes<Pos>,
+}
+
+/// Timing of the frames of an animation, all rendered with the same settings.
+#[derive(Clone, Copy, Debug)]
+pub struct Animation {
+    pub frames: usize,
+    /// Frames per second, on the scene's clock.
+    pub fps: Float,
+    /// Fraction of each frame the shutter is open for, 0.5 for a 180 degree shutter.
+    pub shutter: Float,
+}
+
+impl Animation {
+    /// When the shutter opens and closes for `frame`, counted from zero.
+    pub fn shutter_interval(&self, frame: usize) -> (Float, Float) {
+        let open = frame as Float / self.fps;
+        (open, open + self.shutter / self.fps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_keyframes_pass_through_keys_and_hold_at_the_ends() {
+        for &interpolation in &[Interpolation::Linear, Interpolation::CatmullRom] {
+            let k = Keyframes::new(interpolation, vec![(0., 1.), (1., 3.), (3., 2.), (4., 5.)]);
+            assert_approx_eq!(1., k.at(-1.));
+            assert_approx_eq!(3., k.at(1.));
+            assert_approx_eq!(2., k.at(3.));
+            assert_approx_eq!(5., k.at(10.));
+        }
+    }
+
+    #[test]
+    fn test_catmull_rom_is_smooth_at_keys() {
+        let k = Keyframes::new(
+            Interpolation::CatmullRom,
+            vec![(0., 0.), (1., 2.), (2., 1.), (4., 3.)],
+        );
+        let h = 1e-3;
+        for &t in &[1., 2.] {
+            let before = (k.at(t) - k.at(t - h)) / h;
+            let after = (k.at(t + h) - k.at(t)) / h;
+            assert_approx_eq!(before, after, 1e-2);
+        }
+        // Straight lines stay straight, at constant speed
+        let k = Keyframes::new(
+            Interpolation::CatmullRom,
+            vec![(0., 0.), (1., 1.), (2., 2.), (3., 3.)],
+        );
+        assert_approx_eq!(1.25, k.at(1.25));
+    }
+
+    #[test]
+    fn test_shutter_interval() {
+        let animation = Animation {
+            frames: 10,
+            fps: 25.,
+            shutter: 0.5,
+        };
+        let (open, close) = animation.shutter_interval(2);
+        assert_approx_eq!(0.08, open);
+        assert_approx_eq!(0.1, close);
+    }
+}
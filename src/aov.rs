@@ -0,0 +1,204 @@
+//! Arbitrary output variables: images of what the camera rays first hit, and of the light split
+//! by the number of bounces it took, written alongside the rendered image for compositing.
+
+use crate::{integrator::Lighting, prelude::*, scene::Scene};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Channel {
+    /// Distance to the first hit, from white close to the camera to black at the farthest. Also
+    /// saved as floats.
+    Depth,
+    /// Position of the first hit, scaled to the bounds of the positions seen. Also saved as
+    /// floats.
+    Position,
+    /// Shading normal, mapped from [-1, 1] to [0, 1].
+    Normal,
+    /// Colour of the surface hit, or of the light or background seen.
+    Albedo,
+    /// A random colour for each material.
+    MaterialId,
+    /// A random colour for each primitive.
+    ObjectId,
+    /// Light seen directly, or scattered once towards the camera.
+    Direct,
+    /// Light scattered more than once.
+    Indirect,
+}
+
+pub const CHANNELS: [Channel; 8] = [
+    Channel::Depth,
+    Channel::Position,
+    Channel::Normal,
+    Channel::Albedo,
+    Channel::MaterialId,
+    Channel::ObjectId,
+    Channel::Direct,
+    Channel::Indirect,
+];
+
+impl Channel {
+    /// Used to name the file the channel is saved to.
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Depth => "depth",
+            Channel::Position => "position",
+            Channel::Normal => "normal",
+            Channel::Albedo => "albedo",
+            Channel::MaterialId => "material_id",
+            Channel::ObjectId => "object_id",
+            Channel::Direct => "direct",
+            Channel::Indirect => "indirect",
+        }
+    }
+
+    /// Whether the channel holds measurements, which are also saved unscaled, as floats.
+    pub fn floats(self) -> bool {
+        matches!(self, Channel::Depth | Channel::Position)
+    }
+}
+
+/// Sums of the output variables over the samples of a pixel, or their values for one sample.
+#[derive(Clone, Copy, Debug)]
+pub struct Aovs {
+    /// Number of samples whose camera ray hit something. Depth, position and normal are only
+    /// summed over those.
+    pub hits: usize,
+    pub depth: Float,
+    pub position: Pos,
+    /// Zero for hits in participating media.
+    pub normal: Dir,
+    pub albedo: Col,
+    /// Identifiers of the material and primitive hit by the first sample which hit anything.
+    pub material: Option<usize>,
+    pub object: Option<usize>,
+    pub lighting: Lighting,
+}
+
+impl Aovs {
+    pub fn zero() -> Self {
+        Self {
+            hits: 0,
+            depth: 0.,
+            position: Pos::zero(),
+            normal: Dir::zero(),
+            albedo: Col::zero(),
+            material: None,
+            object: None,
+            lighting: Lighting::zero(),
+        }
+    }
+}
+
+impl std::ops::AddAssign for Aovs {
+    fn add_assign(&mut self, o: Self) {
+        self.hits += o.hits;
+        self.depth += o.depth;
+        self.position += o.position - Pos::zero();
+        self.normal += o.normal;
+        self.albedo += o.albedo;
+        // Identifiers can't be averaged
+        self.material = self.material.or(o.material);
+        self.object = self.object.or(o.object);
+        self.lighting += o.lighting;
+    }
+}
+
+fn clamp(c: Col) -> Col {
+    col(c.r().min(1.), c.g().min(1.), c.b().min(1.))
+}
+
+/// The variables for a camera ray `r` which hit nothing. Its lighting is left for the
+/// integrator to fill in.
+pub fn missed(scene: &Scene, r: &Ray) -> Aovs {
+    Aovs {
+        albedo: clamp((scene.background)(r)),
+        ..Aovs::zero()
+    }
+}
+
+/// The variables describing `rec`, the first hit of camera ray `r`, where the integrator's
+/// sampling of the material gave `scatter`. Its lighting is left for the integrator to fill in.
+pub fn first_hit(r: &Ray, rec: &HitRecord, scatter: Option<&Scatter>) -> Aovs {
+    let albedo = match scatter {
+        Some(scatter) => scatter.attenuation,
+        None => rec.mat.emitted(r, rec),
+    };
+    Aovs {
+        hits: 1,
+        depth: rec.t * r.direction().length(),
+        position: rec.p,
+        normal: rec.normal,
+        albedo: clamp(albedo),
+        material: rec.mat.id(),
+        object: Some(rec.object),
+        lighting: Lighting::zero(),
+    }
+}
+
+/// A bright colour picked at random for `id`, the same every time.
+pub fn id_colour(id: usize) -> Col {
+    let h = crate::sampler::hash(&[id as u64]);
+    let channel = |shift: u64| 0.2 + 0.8 * ((h >> shift) & 0xff) as Float / 255.;
+    col(channel(0), channel(8), channel(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::{Perspective, Placement},
+        hitable,
+        material::lambertian,
+        sampler::Independent,
+        texture::constant_texture,
+    };
+    use assert_approx_eq::assert_approx_eq;
+
+    fn sky(_r: &Ray) -> Col {
+        col(0.5, 0.7, 2.)
+    }
+
+    #[test]
+    fn test_first_hit_and_miss() {
+        let scene = Scene {
+            camera: Box::new(Perspective::new(
+                Placement {
+                    look_from: Pos::zero(),
+                    look_at: pos(0., 0., -1.),
+                    up: dir(0., 1., 0.),
+                },
+                90.,
+                2.,
+                0.,
+                1.,
+                (0., 0.),
+            )),
+            world: hitable::sphere(
+                pos(0., 0., -3.),
+                1.,
+                lambertian(constant_texture(col(0.2, 0.4, 0.6))),
+            ),
+            lights: vec![],
+            background: sky,
+        };
+        let mut sampler = Independent::new();
+
+        let r = Ray::new(Pos::zero(), dir(0., 0., -2.), 0.);
+        let rec = scene.world.hit(&r, 0.001, Float::MAX).unwrap();
+        let scatter = rec.mat.scatter(&r, &rec, &mut sampler);
+        let aovs = first_hit(&r, &rec, scatter.as_ref());
+        assert_eq!(1, aovs.hits);
+        assert_approx_eq!(2., aovs.depth);
+        assert_eq!(pos(0., 0., -2.), aovs.position);
+        assert_eq!(dir(0., 0., 1.), aovs.normal);
+        assert_eq!(col(0.2, 0.4, 0.6), aovs.albedo);
+        assert!(aovs.material.is_some());
+        assert!(aovs.object.is_some());
+
+        let r = Ray::new(Pos::zero(), dir(0., 1., 0.), 0.);
+        let aovs = missed(&scene, &r);
+        assert_eq!(0, aovs.hits);
+        assert_eq!(col(0.5, 0.7, 1.), aovs.albedo);
+        assert_eq!(None, aovs.object);
+    }
+}
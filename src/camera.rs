@@ -1,97 +1,369 @@
+mod aperture;
+mod equirectangular;
+mod fisheye;
+mod moving;
+mod orthographic;
+mod perspective;
+mod realistic;
+mod rolling_shutter;
+mod stereo;
+
 use crate::prelude::*;
-use rand::prelude::*;
-
-pub struct Camera {
-    lower_left_corner: Pos,
-    horizontal: Dir,
-    vertical: Dir,
-    origin: Pos,
-    u: Dir,
-    v: Dir,
-    lens_radius: Float,
-    time0: Float,
-    time1: Float,
+
+pub use aperture::{Aperture, ApertureMask};
+pub use equirectangular::Equirectangular;
+pub use fisheye::{Fisheye, FisheyeMapping};
+pub use moving::Moving;
+pub use orthographic::Orthographic;
+pub use perspective::Perspective;
+pub use realistic::{Lens, Realistic};
+pub use rolling_shutter::RollingShutter;
+pub use stereo::Stereo;
+
+pub trait Camera: Send + Sync {
+    /// The ray through (`u`, `v`) on the image, from (0, 0) at the bottom left to (1, 1) at the
+    /// top right. `None` where the projection covers nothing, such as outside a fisheye's circle,
+    /// or where the light is blocked, as by vignetting.
+    fn get_ray(&self, u: Float, v: Float, sampler: &mut dyn Sampler) -> Option<Ray>;
+
+    /// What the light reaching the image is multiplied by.
+    fn exposure(&self) -> Float {
+        1.
+    }
+
+    /// Moves the plane in focus to whatever is seen through (`u`, `v`), where `hit` gives the
+    /// parameter along a ray of the first thing it hits. Cameras which are in focus everywhere
+    /// ignore it.
+    fn autofocus(&mut self, _hit: &mut dyn FnMut(&Ray) -> Option<Float>, _u: Float, _v: Float) {}
 }
 
-fn random_in_unit_disk() -> Dir {
-    let mut rng = thread_rng();
-    loop {
-        let p = 2. * dir(rng.gen(), rng.gen(), 0.) - dir(1., 1., 0.);
-        if p.squared_length() < 1. {
-            return p;
-        }
+pub type CameraBox = Box<dyn Camera>;
+
+/// How the scene is projected onto the image.
+#[derive(Clone, Copy, Debug)]
+pub enum Projection {
+    /// Through a thin lens, with the scene's field of view, aperture and focus distance.
+    Perspective,
+    /// Parallel rays, covering `height` in scene units from bottom to top of the image.
+    Orthographic { height: Float },
+    /// A circular image of the hemisphere or more in front of the camera, `fov` degrees across.
+    Fisheye { fov: Float, mapping: FisheyeMapping },
+    /// The whole sphere around the camera, longitude across and latitude up the image.
+    Equirectangular,
+    /// Through the lens described by the prescription file at `path` (see `Lens::parse` for the
+    /// format), onto a sensor `sensor` millimetres wide and tall, focused at the scene's focus
+    /// distance. One scene unit is `unit` millimetres long.
+    Realistic {
+        path: &'static str,
+        sensor: (Float, Float),
+        unit: Float,
+    },
+}
+
+/// Settings of a real camera, to make the perspective camera from. Lengths are in millimetres
+/// and times in seconds.
+#[derive(Clone, Copy, Debug)]
+pub struct Physical {
+    /// Width and height of the sensor, 36 by 24 for full frame. The image is the largest part
+    /// of it with the image's aspect ratio.
+    pub sensor: (Float, Float),
+    pub focal_length: Float,
+    /// Focal length divided by the diameter of the aperture.
+    pub f_number: Float,
+    /// When the shutter opens and closes, on the scene's clock.
+    pub shutter: (Float, Float),
+    pub iso: Float,
+    /// Length of one scene unit.
+    pub unit: Float,
+}
+
+impl Physical {
+    /// Top to bottom, in degrees, for an image of the given aspect ratio.
+    pub fn vfov(&self, aspect: Float) -> Float {
+        let (width, height) = self.sensor;
+        let height = height.min(width / aspect);
+        2. * (height / (2. * self.focal_length)).atan() * 180. / PI
+    }
+
+    /// In scene units.
+    pub fn lens_radius(&self) -> Float {
+        self.focal_length / self.f_number / 2. / self.unit
     }
+
+    /// Proportional to the light reaching the sensor, and to its sensitivity. One at f/16 with
+    /// the shutter open for 1/ISO seconds, the sunny 16 rule, which suits a sky as bright as
+    /// `scene::sky`.
+    pub fn exposure(&self) -> Float {
+        let (open, close) = self.shutter;
+        256. * (close - open) * self.iso / (self.f_number * self.f_number)
+    }
+}
+
+/// The shape of the aperture of the perspective and realistic cameras, and ways their lens
+/// departs from one facing the image squarely.
+#[derive(Clone, Debug)]
+pub struct Bokeh {
+    pub aperture: Aperture,
+    /// Cat's eye vignetting: how far off centre, for a point at the top of the image, the
+    /// barrel of the lens cuts the aperture, in radii of the aperture. Out of focus highlights
+    /// narrow into lemons towards the corners, which darken. The realistic camera's lens does
+    /// this by itself.
+    pub cat_eye: Float,
+    /// Angles in degrees the plane in focus is turned by, about the horizontal then vertical
+    /// axis of the image, as with a tilting lens.
+    pub tilt: (Float, Float),
+    /// How far the image is moved across and up, in widths and heights of the image, as with a
+    /// shifting lens, without turning the camera.
+    pub shift: (Float, Float),
 }
 
-impl Camera {
-    /// vfov is top to bottom in degrees
-    pub fn new(
-        look_from: Pos,
-        look_at: Pos,
-        up: Dir,
-        vfov: Float,
-        aspect: Float,
-        aperture: Float,
-        focus_dist: Float,
-        t0: Float,
-        t1: Float,
-    ) -> Camera {
-        let theta = vfov * PI / 180. / 2.;
-        let half_height = theta.tan();
-        let half_width = aspect * half_height;
-        let w = (look_from - look_at).unit_vector();
-        let u = up.cross(w).unit_vector();
-        let v = w.cross(u);
-
-        Camera {
-            lower_left_corner: look_from - focus_dist * (half_width * u + half_height * v + w),
-            horizontal: 2. * half_width * focus_dist * u,
-            vertical: 2. * half_height * focus_dist * v,
-            origin: look_from,
-            u,
-            v,
-            lens_radius: aperture / 2.,
-            time0: t0,
-            time1: t1,
+impl Default for Bokeh {
+    fn default() -> Self {
+        Self {
+            aperture: Aperture::Circle,
+            cat_eye: 0.,
+            tilt: (0., 0.),
+            shift: (0., 0.),
         }
     }
+}
 
-    #[inline]
-    pub fn get_ray(&self, u: Float, v: Float) -> Ray {
-        let mut rng = thread_rng();
-
-        let rd = self.lens_radius * random_in_unit_disk();
-        let offset = self.u * rd.x() + self.v * rd.y();
-        let time = self.time0 + rng.gen::<Float>() * (self.time1 - self.time0);
+/// Where a camera is and which way it faces: at `look_from`, looking at `look_at`, with `up`
+/// towards the top of the image.
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub look_from: Pos,
+    pub look_at: Pos,
+    pub up: Dir,
+}
 
-        Ray::new(
-            self.origin + offset,
-            self.lower_left_corner - self.origin + u * self.horizontal + v * self.vertical - offset,
-            time,
-        )
+impl Placement {
+    /// The camera's frame: right, up, and backwards.
+    pub fn frame(&self) -> (Dir, Dir, Dir) {
+        frame(self.look_from, self.look_at, self.up)
     }
 }
 
+/// The right-handed frame of a camera at `look_from` looking at `look_at`: right, up, and
+/// backwards.
+pub fn frame(look_from: Pos, look_at: Pos, up: Dir) -> (Dir, Dir, Dir) {
+    let w = (look_from - look_at).unit_vector();
+    let u = up.cross(w).unit_vector();
+    let v = w.cross(u);
+    (u, v, w)
+}
+
+/// A random time within the shutter interval.
+fn time(time0: Float, time1: Float, sampler: &mut dyn Sampler) -> Float {
+    time0 + sampler.next_1d() * (time1 - time0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        animation::{CameraPath, Interpolation, Keyframes},
+        sampler::Independent,
+    };
+    use assert_approx_eq::assert_approx_eq;
+
+    fn assert_dir_eq(expected: Dir, actual: Dir) {
+        let actual = actual.unit_vector();
+        assert_approx_eq!(expected.x(), actual.x());
+        assert_approx_eq!(expected.y(), actual.y());
+        assert_approx_eq!(expected.z(), actual.z());
+    }
 
     #[test]
-    fn test_camera_creation() {
-        let cam = Camera::new(
-            Pos::zero(),
-            pos(0., 0., -1.),
-            dir(0., 1., 0.),
-            90.,
-            2.,
+    fn test_cameras_look_at_the_target_through_the_centre() {
+        let placement = Placement {
+            look_from: pos(1., 2., 3.),
+            look_at: pos(1., 2., 0.),
+            up: dir(0., 1., 0.),
+        };
+        let cameras: Vec<CameraBox> = vec![
+            Box::new(Perspective::new(placement, 40., 2., 0., 1., (0., 0.))),
+            Box::new(Orthographic::new(placement, 4., 2., (0., 0.))),
+            Box::new(Fisheye::new(
+                placement,
+                180.,
+                FisheyeMapping::Equisolid,
+                1.,
+                (0., 0.),
+            )),
+            Box::new(Equirectangular::new(placement, 0., (0., 0.))),
+        ];
+        for camera in cameras {
+            let r = camera.get_ray(0.5, 0.5, &mut Independent::new()).unwrap();
+            assert_dir_eq(dir(0., 0., -1.), r.direction());
+        }
+    }
+
+    #[test]
+    fn test_physical_settings() {
+        let lens = Physical {
+            sensor: (36., 24.),
+            focal_length: 50.,
+            f_number: 16.,
+            shutter: (0., 0.01),
+            iso: 100.,
+            unit: 1000.,
+        };
+        assert_approx_eq!(26.99, lens.vfov(1.5), 1e-2);
+        // A wider image is cropped off the top and bottom of the sensor
+        assert_approx_eq!(20.41, lens.vfov(2.), 1e-2);
+        assert_approx_eq!(50. / 16. / 2. / 1000., lens.lens_radius());
+        assert_approx_eq!(1., lens.exposure());
+        let lens = Physical {
+            f_number: 8.,
+            ..lens
+        };
+        assert_approx_eq!(4., lens.exposure());
+    }
+
+    #[test]
+    fn test_moving_camera_follows_its_path() {
+        let path = CameraPath {
+            look_from: Keyframes::new(
+                Interpolation::Linear,
+                vec![(0., pos(0., 0., 0.)), (1., pos(2., 0., 0.))],
+            ),
+            look_at: Keyframes::new(
+                Interpolation::Linear,
+                vec![(0., pos(0., 0., -1.)), (1., pos(4., 0., 1.))],
+            ),
+        };
+        let up = dir(0., 1., 0.);
+        let eye = |t0, t1| -> CameraBox {
+            Box::new(Orthographic::new(
+                Placement {
+                    look_from: path.look_from.at(0.),
+                    look_at: path.look_at.at(0.),
+                    up,
+                },
+                2.,
+                1.,
+                (t0, t1),
+            ))
+        };
+        let mut sampler = Independent::new();
+        // Halfway, the camera is at (1, 0, 0) looking along x
+        let camera = Moving::along(eye(0.5, 0.5), &path, up, 0., 1.);
+        let r = camera.get_ray(0.5, 0.5, &mut sampler).unwrap();
+        assert_eq!(0.5, r.time());
+        assert_approx_eq!(1., r.origin().x());
+        assert_approx_eq!(0., r.origin().z());
+        assert_dir_eq(dir(1., 0., 0.), r.direction());
+    }
+
+    #[test]
+    fn test_rolling_shutter_exposes_the_top_first() {
+        let camera = RollingShutter::new(
+            Box::new(Orthographic::new(
+                Placement {
+                    look_from: Pos::zero(),
+                    look_at: pos(0., 0., -1.),
+                    up: dir(0., 1., 0.),
+                },
+                2.,
+                1.,
+                (0., 1.),
+            )),
             0.,
             1.,
+            0.75,
+        );
+        let mut sampler = Independent::new();
+        for _ in 0..10 {
+            let top = camera.get_ray(0.5, 1., &mut sampler).unwrap().time();
+            assert!((0. ..=0.25).contains(&top));
+            let bottom = camera.get_ray(0.5, 0., &mut sampler).unwrap().time();
+            assert!((0.75..=1.).contains(&bottom));
+        }
+    }
+
+    #[test]
+    fn test_orthographic_rays_are_parallel() {
+        let camera = Orthographic::new(
+            Placement {
+                look_from: Pos::zero(),
+                look_at: pos(0., 0., -1.),
+                up: dir(0., 1., 0.),
+            },
+            4.,
+            2.,
+            (0., 0.),
+        );
+        let mut sampler = Independent::new();
+        let r = camera.get_ray(0., 0., &mut sampler).unwrap();
+        assert_eq!(pos(-4., -2., 0.), r.origin());
+        assert_dir_eq(dir(0., 0., -1.), r.direction());
+        let r = camera.get_ray(1., 1., &mut sampler).unwrap();
+        assert_eq!(pos(4., 2., 0.), r.origin());
+        assert_dir_eq(dir(0., 0., -1.), r.direction());
+    }
+
+    #[test]
+    fn test_fisheye_covers_its_field_of_view_within_a_circle() {
+        for &mapping in &[FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            let camera = Fisheye::new(
+                Placement {
+                    look_from: Pos::zero(),
+                    look_at: pos(0., 0., -1.),
+                    up: dir(0., 1., 0.),
+                },
+                180.,
+                mapping,
+                2.,
+                (0., 0.),
+            );
+            let mut sampler = Independent::new();
+            // The top of the circle looks straight up, the corners see nothing
+            let r = camera.get_ray(0.5, 1., &mut sampler).unwrap();
+            assert_dir_eq(dir(0., 1., 0.), r.direction());
+            assert!(camera.get_ray(1., 1., &mut sampler).is_none());
+        }
+    }
+
+    #[test]
+    fn test_equirectangular_wraps_around() {
+        let camera = Equirectangular::new(
+            Placement {
+                look_from: Pos::zero(),
+                look_at: pos(0., 0., -1.),
+                up: dir(0., 1., 0.),
+            },
             0.,
-            0.,
+            (0., 0.),
         );
-        assert_eq!(Pos::zero(), cam.origin);
-        assert_eq!(pos(-2., -1., -1.), cam.lower_left_corner);
-        assert_eq!(dir(4., 0., 0.), cam.horizontal);
-        assert_eq!(dir(0., 2., 0.), cam.vertical);
+        let mut sampler = Independent::new();
+        let r = camera.get_ray(0.75, 0.5, &mut sampler).unwrap();
+        assert_dir_eq(dir(1., 0., 0.), r.direction());
+        let r = camera.get_ray(0., 0.5, &mut sampler).unwrap();
+        assert_dir_eq(dir(0., 0., 1.), r.direction());
+        let r = camera.get_ray(0.3, 1., &mut sampler).unwrap();
+        assert_dir_eq(dir(0., 1., 0.), r.direction());
+    }
+
+    #[test]
+    fn test_stereo_puts_the_left_eye_on_top() {
+        let eye = |x| -> CameraBox {
+            Box::new(Orthographic::new(
+                Placement {
+                    look_from: pos(x, 0., 0.),
+                    look_at: pos(x, 0., -1.),
+                    up: dir(0., 1., 0.),
+                },
+                2.,
+                1.,
+                (0., 0.),
+            ))
+        };
+        let camera = Stereo::new(eye(-1.), eye(1.));
+        let mut sampler = Independent::new();
+        let r = camera.get_ray(0.5, 0.75, &mut sampler).unwrap();
+        assert_eq!(pos(-1., 0., 0.), r.origin());
+        let r = camera.get_ray(0.5, 0.25, &mut sampler).unwrap();
+        assert_eq!(pos(1., 0., 0.), r.origin());
     }
 }
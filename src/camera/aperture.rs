@@ -0,0 +1,256 @@
+use crate::prelude::*;
+use std::{path::Path, sync::Arc};
+
+/// Shape of the opening of a lens, which out of focus highlights take.
+#[derive(Clone, Debug)]
+pub enum Aperture {
+    Circle,
+    /// A regular polygon inscribed in the circle, as made by `blades` straight diaphragm blades,
+    /// turned by `rotation` degrees.
+    Polygon {
+        blades: usize,
+        rotation: Float,
+    },
+    /// Any shape, letting light through where the mask is bright.
+    Mask(Arc<ApertureMask>),
+}
+
+impl Aperture {
+    /// Panics unless the aperture is a shape: polygons need at least 3 blades.
+    pub fn check(&self) {
+        if let Aperture::Polygon { blades, .. } = self {
+            assert!(
+                *blades >= 3,
+                "A polygonal aperture needs at least 3 blades, not {}",
+                blades
+            );
+        }
+    }
+
+    /// A point of the aperture, in the square from (-1, -1) to (1, 1) the unit circle fits in,
+    /// with as many points where the aperture lets more light through.
+    pub fn sample(&self, (a, b): (Float, Float)) -> (Float, Float) {
+        match self {
+            Aperture::Circle => concentric_disk((a, b)),
+            Aperture::Polygon { blades, rotation } => {
+                // Pick a triangle between the centre and one side, then a point in it
+                let n = *blades as Float;
+                let k = (a * n).floor().min(n - 1.);
+                let a = a * n - k;
+                let vertex = |k: Float| {
+                    let phi = rotation * PI / 180. + 2. * PI * k / n + FRAC_PI_2;
+                    (phi.cos(), phi.sin())
+                };
+                let ((x0, y0), (x1, y1)) = (vertex(k), vertex(k + 1.));
+                let s = a.sqrt();
+                (s * ((1. - b) * x0 + b * x1), s * ((1. - b) * y0 + b * y1))
+            }
+            Aperture::Mask(mask) => mask.sample((a, b)),
+        }
+    }
+
+    /// Whether the aperture lets light through at (`x`, `y`), in the square from (-1, -1) to
+    /// (1, 1) the unit circle fits in. Masks count as open where they let through at least half
+    /// the light.
+    pub fn contains(&self, (x, y): (Float, Float)) -> bool {
+        match self {
+            Aperture::Circle => x * x + y * y <= 1.,
+            Aperture::Polygon { blades, rotation } => {
+                // Measured along the normal of the side the point is towards
+                let n = *blades as Float;
+                let side = 2. * PI / n;
+                let phi = y.atan2(x) - rotation * PI / 180. - FRAC_PI_2;
+                let from_middle = phi - side * ((phi / side).floor() + 0.5);
+                (x * x + y * y).sqrt() * from_middle.cos() <= (PI / n).cos()
+            }
+            Aperture::Mask(mask) => mask.transmittance((x, y)) >= 0.5,
+        }
+    }
+}
+
+/// Maps a point of the unit square onto the unit disk, keeping strata together (Shirley and
+/// Chiu's concentric mapping).
+fn concentric_disk((a, b): (Float, Float)) -> (Float, Float) {
+    let (a, b) = (2. * a - 1., 2. * b - 1.);
+    if a == 0. && b == 0. {
+        return (0., 0.);
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// An image of the aperture, sampled in proportion to its brightness through the cumulative
+/// distributions of its rows, and of the pixels within each row.
+#[derive(Debug)]
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    /// Fraction of the light let through by the rows up to and including each one, from the top.
+    rows: Vec<Float>,
+    /// Fraction of the light of its row let through by the pixels up to and including each one.
+    columns: Vec<Float>,
+    transmittance: Vec<Float>,
+}
+
+/// Running sums of `values`, divided by their total.
+fn cdf(values: &[Float]) -> Vec<Float> {
+    let mut sum = 0.;
+    let mut cdf: Vec<Float> = values
+        .iter()
+        .map(|v| {
+            sum += v;
+            sum
+        })
+        .collect();
+    if sum > 0. {
+        for c in &mut cdf {
+            *c /= sum;
+        }
+    }
+    cdf
+}
+
+/// The index of the first step of `cdf` above `a`, and how far into it `a` is.
+fn invert(cdf: &[Float], a: Float) -> (usize, Float) {
+    // Rounding may leave the last step just below one, and past any empty ones
+    let last = cdf[cdf.len() - 1];
+    let i = cdf.iter().position(|&c| c > a || c >= last).unwrap();
+    let lo = if i == 0 { 0. } else { cdf[i - 1] };
+    (i, ((a - lo) / (cdf[i] - lo)).min(1.))
+}
+
+impl ApertureMask {
+    /// A mask `width` by `height` pixels, letting through `transmittance` of the light at each,
+    /// row by row from the top. It is stretched to the square around the unit circle.
+    pub fn new(width: usize, height: usize, transmittance: &[Float]) -> Self {
+        assert_eq!(width * height, transmittance.len());
+        let totals: Vec<Float> = transmittance
+            .chunks(width)
+            .map(|row| row.iter().sum())
+            .collect();
+        assert!(
+            totals.iter().any(|&t| t > 0.),
+            "The aperture mask lets no light through"
+        );
+        Self {
+            width,
+            height,
+            rows: cdf(&totals),
+            columns: transmittance.chunks(width).flat_map(cdf).collect(),
+            transmittance: transmittance.to_vec(),
+        }
+    }
+
+    /// Loads a mask from an image, letting light through in proportion to its luminance.
+    pub fn open<P: AsRef<Path>>(path: P) -> image::ImageResult<Self> {
+        let image = image::open(path)?.to_luma();
+        let transmittance: Vec<Float> = image.pixels().map(|p| p[0] as Float / 255.).collect();
+        Ok(Self::new(
+            image.width() as usize,
+            image.height() as usize,
+            &transmittance,
+        ))
+    }
+
+    /// The transmittance of the pixel at (`x`, `y`), in the square the mask is stretched to.
+    fn transmittance(&self, (x, y): (Float, Float)) -> Float {
+        let i = (x + 1.) / 2. * self.width as Float;
+        let j = (1. - y) / 2. * self.height as Float;
+        if i < 0. || j < 0. || i >= self.width as Float || j >= self.height as Float {
+            return 0.;
+        }
+        self.transmittance[i as usize + self.width * j as usize]
+    }
+
+    fn sample(&self, (a, b): (Float, Float)) -> (Float, Float) {
+        let (j, dy) = invert(&self.rows, b);
+        let row = &self.columns[j * self.width..(j + 1) * self.width];
+        let (i, dx) = invert(row, a);
+        let x = (i as Float + dx) / self.width as Float;
+        let y = (j as Float + dy) / self.height as Float;
+        (2. * x - 1., 1. - 2. * y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_polygon_samples_stay_inside() {
+        let aperture = Aperture::Polygon {
+            blades: 6,
+            rotation: 10.,
+        };
+        // The inscribed circle of a hexagon
+        let inner = (PI / 6.).cos();
+        let mut rng = thread_rng();
+        let mut outside_inner = 0;
+        for _ in 0..1000 {
+            let (x, y) = aperture.sample(rng.gen());
+            let r = (x * x + y * y).sqrt();
+            assert!(r <= 1. + 1e-5);
+            if r > inner {
+                outside_inner += 1;
+            }
+        }
+        assert!(outside_inner > 0);
+    }
+
+    #[test]
+    fn test_apertures_contain_their_samples() {
+        let apertures = vec![
+            Aperture::Circle,
+            Aperture::Polygon {
+                blades: 5,
+                rotation: 20.,
+            },
+            Aperture::Mask(Arc::new(ApertureMask::new(2, 2, &[0., 1., 1., 0.]))),
+        ];
+        let mut rng = StdRng::seed_from_u64(43);
+        for aperture in apertures {
+            for _ in 0..100 {
+                // Nudged towards the centre, off the edges
+                let (x, y) = aperture.sample(rng.gen());
+                assert!(aperture.contains((0.99 * x, 0.99 * y)), "({}, {})", x, y);
+            }
+        }
+        let pentagon = Aperture::Polygon {
+            blades: 5,
+            rotation: 0.,
+        };
+        // A vertex points straight up, a side faces straight down
+        assert!(pentagon.contains((0., 0.99)));
+        assert!(!pentagon.contains((0., -0.9)));
+        assert!(pentagon.contains((0., -0.8)));
+    }
+
+    #[test]
+    fn test_mask_samples_where_light_gets_through() {
+        // Only the top right quarter is open
+        let mask = ApertureMask::new(2, 2, &[0., 1., 0., 0.]);
+        let aperture = Aperture::Mask(Arc::new(mask));
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let (x, y) = aperture.sample(rng.gen());
+            assert!((0. ..=1.).contains(&x), "{}", x);
+            assert!((0. ..=1.).contains(&y), "{}", y);
+        }
+    }
+
+    #[test]
+    fn test_star_mask_is_open_in_the_middle_only() {
+        let mask = ApertureMask::open("apertures/star.png").unwrap();
+        let aperture = Aperture::Mask(Arc::new(mask));
+        let mut rng = StdRng::seed_from_u64(43);
+        for _ in 0..100 {
+            let (x, y) = aperture.sample(rng.gen());
+            assert!(x * x + y * y < 1., "({}, {})", x, y);
+        }
+    }
+}
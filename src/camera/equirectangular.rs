@@ -0,0 +1,49 @@
+use super::{time, Camera, Placement};
+use crate::prelude::*;
+
+/// A 360° panorama: longitude goes across the image, with the view towards `look_at` in the
+/// middle, and latitude from straight down at the bottom to straight up at the top.
+///
+/// For stereo panoramas, each eye sits `eye_offset` to the right (or left, if negative) of
+/// `look_from` relative to the direction it looks in, which turns with it around the vertical
+/// axis (omni-directional stereo).
+pub struct Equirectangular {
+    origin: Pos,
+    u: Dir,
+    v: Dir,
+    w: Dir,
+    eye_offset: Float,
+    time0: Float,
+    time1: Float,
+}
+
+impl Equirectangular {
+    pub fn new(placement: Placement, eye_offset: Float, shutter: (Float, Float)) -> Self {
+        let (u, v, w) = placement.frame();
+        let (time0, time1) = shutter;
+        Self {
+            origin: placement.look_from,
+            u,
+            v,
+            w,
+            eye_offset,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl Camera for Equirectangular {
+    fn get_ray(&self, u: Float, v: Float, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let phi = (2. * u - 1.) * PI;
+        let latitude = (v - 0.5) * PI;
+        let forward = phi.sin() * self.u - phi.cos() * self.w;
+        let right = phi.cos() * self.u + phi.sin() * self.w;
+        let direction = latitude.cos() * forward + latitude.sin() * self.v;
+        Some(Ray::new(
+            self.origin + self.eye_offset * right,
+            direction,
+            time(self.time0, self.time1, sampler),
+        ))
+    }
+}
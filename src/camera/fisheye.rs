@@ -0,0 +1,75 @@
+use super::{time, Camera, Placement};
+use crate::prelude::*;
+
+/// How the angle from the axis of a fisheye lens maps to the distance from the centre of the
+/// image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FisheyeMapping {
+    /// Distance proportional to the angle, keeping angles between directions.
+    Equidistant,
+    /// Distance proportional to the sine of half the angle, keeping solid angles, so that
+    /// everything keeps its share of the image.
+    Equisolid,
+}
+
+/// A fisheye lens, showing a circle `fov` degrees across as tall as the image.
+pub struct Fisheye {
+    origin: Pos,
+    u: Dir,
+    v: Dir,
+    w: Dir,
+    /// Half the field of view, in radians.
+    theta_max: Float,
+    mapping: FisheyeMapping,
+    aspect: Float,
+    time0: Float,
+    time1: Float,
+}
+
+impl Fisheye {
+    pub fn new(
+        placement: Placement,
+        fov: Float,
+        mapping: FisheyeMapping,
+        aspect: Float,
+        shutter: (Float, Float),
+    ) -> Self {
+        let (u, v, w) = placement.frame();
+        let (time0, time1) = shutter;
+        Self {
+            origin: placement.look_from,
+            u,
+            v,
+            w,
+            theta_max: fov * PI / 180. / 2.,
+            mapping,
+            aspect,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl Camera for Fisheye {
+    fn get_ray(&self, u: Float, v: Float, sampler: &mut dyn Sampler) -> Option<Ray> {
+        // Coordinates scaled so that the circle has radius one
+        let x = (2. * u - 1.) * self.aspect;
+        let y = 2. * v - 1.;
+        let r = (x * x + y * y).sqrt();
+        if r > 1. {
+            return None;
+        }
+        let theta = match self.mapping {
+            FisheyeMapping::Equidistant => r * self.theta_max,
+            FisheyeMapping::Equisolid => 2. * (r * (self.theta_max / 2.).sin()).asin(),
+        };
+        let phi = y.atan2(x);
+        let direction =
+            theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w;
+        Some(Ray::new(
+            self.origin,
+            direction,
+            time(self.time0, self.time1, sampler),
+        ))
+    }
+}
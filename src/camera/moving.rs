@@ -0,0 +1,87 @@
+use super::{frame, Camera, CameraBox};
+use crate::{
+    animation::{CameraPath, Interpolation, Keyframes},
+    prelude::*,
+    transform::{Rotation, Transform},
+};
+
+/// Steps each stretch between the keys of a camera path is cut into when following it.
+const PATH_STEPS: usize = 8;
+
+/// A camera carried along while the shutter is open, for shots from the hand or a vehicle. Its
+/// rays are moved by the transform at their time.
+pub struct Moving {
+    camera: CameraBox,
+    motion: Keyframes<Transform>,
+}
+
+impl Moving {
+    pub fn new(camera: CameraBox, motion: Keyframes<Transform>) -> Self {
+        Self { camera, motion }
+    }
+
+    /// A camera following `path` from `t0` to `t1`, made from `camera` where it is at `t0`.
+    pub fn along(camera: CameraBox, path: &CameraPath, up: Dir, t0: Float, t1: Float) -> Self {
+        let pose = |t: Float| {
+            let (u, v, w) = frame(path.look_from.at(t), path.look_at.at(t), up);
+            (path.look_from.at(t), Rotation::from_basis(u, v, w))
+        };
+        let (origin0, rotation0) = pose(t0);
+        let mut stops = vec![t0];
+        stops.extend(
+            path.look_from
+                .times()
+                .chain(path.look_at.times())
+                .filter(|&t| t0 < t && t < t1),
+        );
+        stops.push(t1);
+        stops.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        stops.dedup();
+
+        let mut keys = vec![(t0, Transform::identity())];
+        for stop in stops.windows(2) {
+            let (from, to) = (stop[0], stop[1]);
+            for i in 1..=PATH_STEPS {
+                let t = from + (to - from) * i as Float / PATH_STEPS as Float;
+                let (origin, rotation) = pose(t);
+                let rotation = rotation * rotation0.inverse();
+                let transform = Transform {
+                    rotation,
+                    ..Transform::identity()
+                };
+                // Turn about the first position, then move to the new one
+                let translation = origin - transform.point(origin0);
+                keys.push((
+                    t,
+                    Transform {
+                        translation,
+                        ..transform
+                    },
+                ));
+            }
+        }
+        keys.dedup_by(|a, b| a.0 == b.0);
+        Self::new(camera, Keyframes::new(Interpolation::Linear, keys))
+    }
+}
+
+fn moved(transform: &Transform, r: &Ray) -> Ray {
+    r.spawn(transform.point(r.origin()), transform.vector(r.direction()))
+}
+
+impl Camera for Moving {
+    fn get_ray(&self, u: Float, v: Float, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let r = self.camera.get_ray(u, v, sampler)?;
+        Some(moved(&self.motion.at(r.time()), &r))
+    }
+
+    fn exposure(&self) -> Float {
+        self.camera.exposure()
+    }
+
+    fn autofocus(&mut self, hit: &mut dyn FnMut(&Ray) -> Option<Float>, u: Float, v: Float) {
+        let motion = &self.motion;
+        self.camera
+            .autofocus(&mut |r| hit(&moved(&motion.at(r.time()), r)), u, v);
+    }
+}
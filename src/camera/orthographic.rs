@@ -0,0 +1,45 @@
+use super::{time, Camera, Placement};
+use crate::prelude::*;
+
+/// Parallel rays leaving a rectangle centred on `look_from`, for elevations and plans without
+/// perspective.
+pub struct Orthographic {
+    lower_left_corner: Pos,
+    horizontal: Dir,
+    vertical: Dir,
+    direction: Dir,
+    time0: Float,
+    time1: Float,
+}
+
+impl Orthographic {
+    /// `height` is top to bottom in scene units.
+    pub fn new(
+        placement: Placement,
+        height: Float,
+        aspect: Float,
+        shutter: (Float, Float),
+    ) -> Self {
+        let (u, v, w) = placement.frame();
+        let (look_from, (time0, time1)) = (placement.look_from, shutter);
+        let (half_width, half_height) = (aspect * height / 2., height / 2.);
+        Self {
+            lower_left_corner: look_from - half_width * u - half_height * v,
+            horizontal: 2. * half_width * u,
+            vertical: 2. * half_height * v,
+            direction: -w,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl Camera for Orthographic {
+    fn get_ray(&self, u: Float, v: Float, sampler: &mut dyn Sampler) -> Option<Ray> {
+        Some(Ray::new(
+            self.lower_left_corner + u * self.horizontal + v * self.vertical,
+            self.direction,
+            time(self.time0, self.time1, sampler),
+        ))
+    }
+}
@@ -0,0 +1,268 @@
+use super::{time, Aperture, Bokeh, Camera, Physical, Placement};
+use crate::prelude::*;
+
+/// A pinhole camera, or a thin lens focused at `focus_dist` when the aperture is open.
+pub struct Perspective {
+    lower_left_corner: Pos,
+    horizontal: Dir,
+    vertical: Dir,
+    origin: Pos,
+    u: Dir,
+    v: Dir,
+    lens_radius: Float,
+    aperture: Aperture,
+    cat_eye: Float,
+    /// Normal of the plane in focus, which goes through the middle of the image.
+    focus_normal: Dir,
+    time0: Float,
+    time1: Float,
+    exposure: Float,
+}
+
+impl Perspective {
+    /// vfov is top to bottom in degrees
+    pub fn new(
+        placement: Placement,
+        vfov: Float,
+        aspect: Float,
+        aperture: Float,
+        focus_dist: Float,
+        shutter: (Float, Float),
+    ) -> Self {
+        let theta = vfov * PI / 180. / 2.;
+        let half_height = theta.tan();
+        let half_width = aspect * half_height;
+        let (u, v, w) = placement.frame();
+        let (look_from, (time0, time1)) = (placement.look_from, shutter);
+
+        Self {
+            lower_left_corner: look_from - focus_dist * (half_width * u + half_height * v + w),
+            horizontal: 2. * half_width * focus_dist * u,
+            vertical: 2. * half_height * focus_dist * v,
+            origin: look_from,
+            u,
+            v,
+            lens_radius: aperture / 2.,
+            aperture: Aperture::Circle,
+            cat_eye: 0.,
+            focus_normal: w,
+            time0,
+            time1,
+            exposure: 1.,
+        }
+    }
+
+    /// A camera set up as `lens`, focused at `focus_dist`.
+    pub fn physical(
+        placement: Placement,
+        aspect: Float,
+        lens: &Physical,
+        focus_dist: Float,
+    ) -> Self {
+        Self {
+            exposure: lens.exposure(),
+            ..Self::new(
+                placement,
+                lens.vfov(aspect),
+                aspect,
+                2. * lens.lens_radius(),
+                focus_dist,
+                lens.shutter,
+            )
+        }
+    }
+
+    /// The same camera with the lens described by `bokeh`. Panics if its aperture is a polygon
+    /// of fewer than 3 blades.
+    pub fn with_bokeh(self, bokeh: &Bokeh) -> Self {
+        bokeh.aperture.check();
+        let (tilt, swing) = bokeh.tilt;
+        let (tilt, swing) = (tilt * PI / 180., swing * PI / 180.);
+        let w = self.u.cross(self.v);
+        let (shift_x, shift_y) = bokeh.shift;
+        Self {
+            lower_left_corner: self.lower_left_corner
+                + shift_x * self.horizontal
+                + shift_y * self.vertical,
+            aperture: bokeh.aperture.clone(),
+            cat_eye: bokeh.cat_eye,
+            focus_normal: (swing.cos() * (tilt.cos() * w + tilt.sin() * self.v)
+                + swing.sin() * self.u)
+                .unit_vector(),
+            ..self
+        }
+    }
+
+    /// The centre of the image, on the plane in focus.
+    fn centre(&self) -> Pos {
+        self.lower_left_corner + 0.5 * self.horizontal + 0.5 * self.vertical
+    }
+}
+
+impl Camera for Perspective {
+    fn get_ray(&self, u: Float, v: Float, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let (x, y) = self.aperture.sample(sampler.next_2d());
+        if self.cat_eye > 0. {
+            // Off axis, the light is also cut by the rear of the lens, seen as a circle shifted
+            // away from the middle of the aperture
+            let aspect = self.horizontal.length() / self.vertical.length();
+            let (cx, cy) = (
+                self.cat_eye * (2. * u - 1.) * aspect,
+                self.cat_eye * (2. * v - 1.),
+            );
+            if (x - cx).powi(2) + (y - cy).powi(2) > 1. {
+                return None;
+            }
+        }
+        let offset = self.lens_radius * (x * self.u + y * self.v);
+
+        // Where the ray through the middle of the lens meets the plane in focus, which only
+        // differs from the point on the image when the plane is tilted
+        let d = self.lower_left_corner - self.origin + u * self.horizontal + v * self.vertical;
+        let t = (self.centre() - self.origin).dot(self.focus_normal) / d.dot(self.focus_normal);
+
+        Some(Ray::new(
+            self.origin + offset,
+            t * d - offset,
+            time(self.time0, self.time1, sampler),
+        ))
+    }
+
+    fn exposure(&self) -> Float {
+        self.exposure
+    }
+
+    fn autofocus(&mut self, hit: &mut dyn FnMut(&Ray) -> Option<Float>, u: Float, v: Float) {
+        let d = self.lower_left_corner - self.origin + u * self.horizontal + v * self.vertical;
+        let r = Ray::new(self.origin, d, self.time0);
+        if let Some(t) = hit(&r) {
+            // Scale the image about the camera until the plane in focus goes through the hit
+            let n = self.focus_normal;
+            let s = t * d.dot(n) / (self.centre() - self.origin).dot(n);
+            self.lower_left_corner = self.origin + s * (self.lower_left_corner - self.origin);
+            self.horizontal = s * self.horizontal;
+            self.vertical = s * self.vertical;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Independent;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_camera_creation() {
+        let cam = Perspective::new(
+            Placement {
+                look_from: Pos::zero(),
+                look_at: pos(0., 0., -1.),
+                up: dir(0., 1., 0.),
+            },
+            90.,
+            2.,
+            0.,
+            1.,
+            (0., 0.),
+        );
+        assert_eq!(Pos::zero(), cam.origin);
+        assert_eq!(pos(-2., -1., -1.), cam.lower_left_corner);
+        assert_eq!(dir(4., 0., 0.), cam.horizontal);
+        assert_eq!(dir(0., 2., 0.), cam.vertical);
+    }
+
+    #[test]
+    fn test_autofocus_brings_what_is_seen_into_focus() {
+        let lens = Physical {
+            sensor: (36., 24.),
+            focal_length: 50.,
+            f_number: 1.4,
+            shutter: (0., 0.01),
+            iso: 100.,
+            unit: 1000.,
+        };
+        let mut cam = Perspective::physical(
+            Placement {
+                look_from: Pos::zero(),
+                look_at: pos(0., 0., -1.),
+                up: dir(0., 1., 0.),
+            },
+            1.5,
+            &lens,
+            1.,
+        );
+        // A wall 5 units away, seen off centre
+        let (u, v) = (0.7, 0.4);
+        cam.autofocus(
+            &mut |r| Some((-5. - r.origin().z()) / r.direction().z()),
+            u,
+            v,
+        );
+
+        let mut sampler = Independent::new();
+        let target = cam.get_ray(u, v, &mut sampler).unwrap().point_at(1.);
+        for _ in 0..10 {
+            let r = cam.get_ray(u, v, &mut sampler).unwrap();
+            assert_approx_eq!(-5., target.z());
+            assert_approx_eq!(0., (r.point_at(1.) - target).length());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 3 blades")]
+    fn test_aperture_needs_three_blades() {
+        let bokeh = Bokeh {
+            aperture: Aperture::Polygon {
+                blades: 2,
+                rotation: 0.,
+            },
+            ..Bokeh::default()
+        };
+        Perspective::new(
+            Placement {
+                look_from: Pos::zero(),
+                look_at: pos(0., 0., -1.),
+                up: dir(0., 1., 0.),
+            },
+            90.,
+            1.,
+            1.,
+            2.,
+            (0., 0.),
+        )
+        .with_bokeh(&bokeh);
+    }
+
+    #[test]
+    fn test_tilted_plane_in_focus() {
+        let bokeh = Bokeh {
+            tilt: (45., 0.),
+            ..Bokeh::default()
+        };
+        let cam = Perspective::new(
+            Placement {
+                look_from: Pos::zero(),
+                look_at: pos(0., 0., -1.),
+                up: dir(0., 1., 0.),
+            },
+            90.,
+            1.,
+            1.,
+            2.,
+            (0., 0.),
+        )
+        .with_bokeh(&bokeh);
+        let mut sampler = Independent::new();
+        // The plane in focus leans back, so the top of the image is in focus further away
+        for &(v, z) in &[(0.5, -2.), (0.75, -4.), (0.25, -4. / 3.)] {
+            let r = cam.get_ray(0.5, v, &mut sampler).unwrap();
+            let target = r.point_at(1.);
+            assert_approx_eq!(z, target.z());
+            for _ in 0..10 {
+                let r = cam.get_ray(0.5, v, &mut sampler).unwrap();
+                assert_approx_eq!(0., (r.point_at(1.) - target).length());
+            }
+        }
+    }
+}
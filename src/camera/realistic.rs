@@ -0,0 +1,681 @@
+use super::{time, Aperture, Bokeh, Camera, Physical, Placement};
+use crate::prelude::*;
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs,
+    num::ParseFloatError,
+    path::Path,
+};
+
+/// One surface of a lens, and the gap behind it. Lengths are in millimetres.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LensSurface {
+    /// Radius of curvature, positive when the centre is behind the surface, on the sensor side.
+    /// Zero for the aperture stop.
+    pub radius: Float,
+    /// Distance along the axis to the next surface, or to the sensor for the last one.
+    pub thickness: Float,
+    /// Index of refraction of what fills the gap behind the surface.
+    pub ior: Float,
+    /// Diameter, beyond which light is blocked.
+    pub aperture: Float,
+}
+
+#[derive(Debug)]
+pub enum LoadLensError {
+    Io(std::io::Error),
+    ParseFloatError(ParseFloatError),
+    WrongColumnCount {
+        line: usize,
+        found: usize,
+    },
+    NotPositive {
+        line: usize,
+        measurement: &'static str,
+        value: Float,
+    },
+    NoSurfaces,
+}
+
+impl Display for LoadLensError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadLensError::Io(e) => write!(f, "couldn't read the lens prescription: {}", e),
+            LoadLensError::ParseFloatError(e) => write!(f, "bad lens measurement: {}", e),
+            LoadLensError::WrongColumnCount { line, found } => write!(
+                f,
+                "expected 4 columns on line {} of the lens prescription, found {}",
+                line, found
+            ),
+            LoadLensError::NotPositive {
+                line,
+                measurement,
+                value,
+            } => write!(
+                f,
+                "the {} on line {} of the lens prescription should be positive, not {}",
+                measurement, line, value
+            ),
+            LoadLensError::NoSurfaces => write!(f, "the lens prescription has no surfaces"),
+        }
+    }
+}
+
+impl Error for LoadLensError {}
+
+impl From<std::io::Error> for LoadLensError {
+    fn from(e: std::io::Error) -> LoadLensError {
+        LoadLensError::Io(e)
+    }
+}
+
+impl From<ParseFloatError> for LoadLensError {
+    fn from(e: ParseFloatError) -> LoadLensError {
+        LoadLensError::ParseFloatError(e)
+    }
+}
+
+/// The surfaces of a lens, from the front to the back.
+#[derive(Clone, Debug)]
+pub struct Lens {
+    pub surfaces: Vec<LensSurface>,
+}
+
+impl Lens {
+    /// Parses a lens prescription: one line per surface from the front, each with the radius,
+    /// thickness, index of refraction and aperture diameter of the surface in millimetres,
+    /// separated by whitespace. An index of zero stands for air. Apertures and indices must
+    /// otherwise be positive. Lines starting with `#` are comments.
+    pub fn parse(s: &str) -> Result<Self, LoadLensError> {
+        let mut surfaces = vec![];
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let columns = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<Float>, _>>()?;
+            if let [radius, thickness, ior, aperture] = columns[..] {
+                let ior = if ior == 0. { 1. } else { ior };
+                for &(measurement, value) in &[("index of refraction", ior), ("aperture", aperture)]
+                {
+                    if value.is_nan() || value <= 0. {
+                        return Err(LoadLensError::NotPositive {
+                            line: i + 1,
+                            measurement,
+                            value,
+                        });
+                    }
+                }
+                surfaces.push(LensSurface {
+                    radius,
+                    thickness,
+                    ior,
+                    aperture,
+                });
+            } else {
+                return Err(LoadLensError::WrongColumnCount {
+                    line: i + 1,
+                    found: columns.len(),
+                });
+            }
+        }
+        if surfaces.is_empty() {
+            return Err(LoadLensError::NoSurfaces);
+        }
+        Ok(Self { surfaces })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LoadLensError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+/// A ray in the lens's own space, in millimetres, with the sensor centred on the origin and the
+/// axis of the lens along z, looking towards negative z.
+#[derive(Clone, Copy, Debug)]
+struct LensRay {
+    origin: Pos,
+    direction: Dir,
+}
+
+/// Bends `d` into a medium `eta` times as refractive, at a surface of normal `n` facing `d`.
+fn refract(d: Dir, n: Dir, eta: Float) -> Option<Dir> {
+    let wi = -d.unit_vector();
+    let cos_i = wi.dot(n);
+    let sin2_t = (1. - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1. {
+        // Total internal reflection
+        return None;
+    }
+    let cos_t = (1. - sin2_t).sqrt();
+    Some(-wi / eta + (cos_i / eta - cos_t) * n)
+}
+
+/// A camera looking through a lens made of spherical surfaces, traced one by one, so that the
+/// image shows the lens's distortion, vignetting, and change of field of view with focus.
+/// The light reaching the sensor isn't weighted by its angle, so corners which see the whole
+/// exit pupil are as bright as the middle.
+pub struct Realistic {
+    lens: Lens,
+    /// Position of each surface along the axis, in front of the sensor.
+    z: Vec<Float>,
+    /// Size of the part of the sensor the image covers, in millimetres.
+    width: Float,
+    height: Float,
+    origin: Pos,
+    u: Dir,
+    v: Dir,
+    w: Dir,
+    /// Length of one scene unit, in millimetres.
+    unit: Float,
+    time0: Float,
+    time1: Float,
+    /// Shape of the aperture stop.
+    aperture: Aperture,
+    /// How far the sensor is moved across and up, in widths and heights of the image.
+    shift: (Float, Float),
+    /// Tangents of the angles the plane in focus is turned by, about the horizontal then
+    /// vertical axis of the image.
+    tilt: (Float, Float),
+    /// Size of the image on the sensor relative to what is in focus.
+    magnification: Float,
+    exposure: Float,
+}
+
+impl Realistic {
+    /// The lens is moved away from the sensor, at `look_from`, to focus at `focus_dist`, or as
+    /// close to it as it can.
+    pub fn new(
+        placement: Placement,
+        lens: Lens,
+        sensor: (Float, Float),
+        aspect: Float,
+        focus_dist: Float,
+        unit: Float,
+        shutter: (Float, Float),
+    ) -> Self {
+        let (u, v, w) = placement.frame();
+        let (time0, time1) = shutter;
+        let (sensor_width, sensor_height) = sensor;
+        let width = sensor_width.min(sensor_height * aspect);
+        let mut camera = Self {
+            z: vec![],
+            lens,
+            width,
+            height: width / aspect,
+            origin: placement.look_from,
+            u,
+            v,
+            w,
+            unit,
+            time0,
+            time1,
+            aperture: Aperture::Circle,
+            shift: (0., 0.),
+            tilt: (0., 0.),
+            magnification: 0.,
+            exposure: 1.,
+        };
+        camera.place_surfaces();
+        camera.focus(focus_dist * unit);
+        camera
+    }
+
+    /// The same camera with the aperture stop shaped, and the sensor shifted and turned, as
+    /// `bokeh` says. The barrel of the lens already cuts the aperture off axis, so `cat_eye` is
+    /// left out.
+    pub fn with_bokeh(self, bokeh: &Bokeh) -> Self {
+        bokeh.aperture.check();
+        let (tilt, swing) = bokeh.tilt;
+        Self {
+            aperture: bokeh.aperture.clone(),
+            shift: bokeh.shift,
+            tilt: ((tilt * PI / 180.).tan(), (swing * PI / 180.).tan()),
+            ..self
+        }
+    }
+
+    /// The same camera stopped down to the f-number of `physical`, unless the lens doesn't open
+    /// that wide, with its shutter interval, and exposed for its ISO. Its sensor, focal length
+    /// and unit are left to the lens and the projection.
+    pub fn with_physical(mut self, physical: &Physical) -> Self {
+        let (f, _, _) = self.thick_lens();
+        let f_number = match self.stop() {
+            Some((i, pupil_magnification)) => {
+                let stop = &mut self.lens.surfaces[i];
+                let widest = f / (pupil_magnification * stop.aperture);
+                let f_number = physical.f_number.max(widest);
+                stop.aperture = f / (pupil_magnification * f_number);
+                f_number
+            }
+            None => physical.f_number,
+        };
+        let (time0, time1) = physical.shutter;
+        Self {
+            exposure: Physical {
+                f_number,
+                ..*physical
+            }
+            .exposure(),
+            time0,
+            time1,
+            ..self
+        }
+    }
+
+    fn place_surfaces(&mut self) {
+        let mut z = 0.;
+        self.z = self
+            .lens
+            .surfaces
+            .iter()
+            .rev()
+            .map(|s| {
+                z -= s.thickness;
+                z
+            })
+            .collect();
+        self.z.reverse();
+    }
+
+    /// Traces `r` through the surfaces in `order`, or returns `None` if it gets blocked.
+    fn trace<I>(&self, mut r: LensRay, order: I) -> Option<LensRay>
+    where
+        I: Iterator<Item = usize>,
+    {
+        let surfaces = &self.lens.surfaces;
+        let towards_sensor = r.direction.z() > 0.;
+        for i in order {
+            let s = &surfaces[i];
+            let (o, d) = (r.origin, r.direction);
+            let (t, normal) = if s.radius == 0. {
+                ((self.z[i] - o.z()) / d.z(), dir(0., 0., 1.))
+            } else {
+                let centre = pos(0., 0., self.z[i] + s.radius);
+                let oc = o - centre;
+                let a = d.squared_length();
+                let b = oc.dot(d);
+                let c = oc.squared_length() - s.radius * s.radius;
+                let discriminant = b * b - a * c;
+                if discriminant < 0. {
+                    return None;
+                }
+                // Of the two crossings of the sphere, the surface is on the side of the vertex
+                let (t0, t1) = (
+                    (-b - discriminant.sqrt()) / a,
+                    (-b + discriminant.sqrt()) / a,
+                );
+                let t = if towards_sensor != (s.radius < 0.) {
+                    t0
+                } else {
+                    t1
+                };
+                (t, (o + t * d - centre).unit_vector())
+            };
+            if t <= 0. {
+                return None;
+            }
+            let p = o + t * d;
+            if p.x() * p.x() + p.y() * p.y() > s.aperture * s.aperture / 4. {
+                return None;
+            }
+            let radius = s.aperture / 2.;
+            if s.radius == 0. && !self.aperture.contains((p.x() / radius, p.y() / radius)) {
+                return None;
+            }
+            let direction = if s.radius == 0. {
+                d
+            } else {
+                // The media on either side of the surface, in the direction of travel
+                let before = if i == 0 { 1. } else { surfaces[i - 1].ior };
+                let (eta_i, eta_t) = if towards_sensor {
+                    (before, s.ior)
+                } else {
+                    (s.ior, before)
+                };
+                let normal = if normal.dot(d) > 0. { -normal } else { normal };
+                refract(d, normal, eta_t / eta_i)?
+            };
+            r = LensRay {
+                origin: p,
+                direction,
+            };
+        }
+        Some(r)
+    }
+
+    fn trace_from_sensor(&self, r: LensRay) -> Option<LensRay> {
+        self.trace(r, (0..self.lens.surfaces.len()).rev())
+    }
+
+    fn trace_from_scene(&self, r: LensRay) -> Option<LensRay> {
+        self.trace(r, 0..self.lens.surfaces.len())
+    }
+
+    /// Where a ray parallel to the axis at height `x` crosses the axis after going through the
+    /// lens, and where it would have had to bend to get there in one go: a focal point and
+    /// principal plane of the lens.
+    fn cardinal_points(&self, r: LensRay, x: Float) -> (Float, Float) {
+        let out = if r.direction.z() > 0. {
+            self.trace_from_scene(r)
+        } else {
+            self.trace_from_sensor(r)
+        }
+        .expect("A ray along the axis of the lens was blocked");
+        let (o, d) = (out.origin, out.direction);
+        let focal = o.z() - o.x() * d.z() / d.x();
+        let principal = o.z() + (x - o.x()) * d.z() / d.x();
+        (focal, principal)
+    }
+
+    /// The index of the aperture stop, if the lens has one, and how much larger it looks from the
+    /// front of the lens, as the entrance pupil.
+    fn stop(&self) -> Option<(usize, Float)> {
+        let i = self.lens.surfaces.iter().position(|s| s.radius == 0.)?;
+        let x = 0.01 * self.lens.surfaces[i].aperture / 2.;
+        let from_scene = LensRay {
+            origin: pos(x, 0., self.z[0] - 1.),
+            direction: dir(0., 0., 1.),
+        };
+        let at_stop = self
+            .trace(from_scene, 0..=i)
+            .expect("A ray along the axis of the lens was blocked");
+        Some((i, x / at_stop.origin.x()))
+    }
+
+    /// The focal length of the lens and where its object and image principal planes are,
+    /// treating it as a thick lens.
+    fn thick_lens(&self) -> (Float, Float, Float) {
+        let x = 0.01 * self.lens.surfaces[0].aperture / 2.;
+        let front = self.z[0];
+        let from_scene = LensRay {
+            origin: pos(x, 0., front - 1.),
+            direction: dir(0., 0., 1.),
+        };
+        let (image_focal, image_principal) = self.cardinal_points(from_scene, x);
+        let from_sensor = LensRay {
+            origin: pos(x, 0., 1.),
+            direction: dir(0., 0., -1.),
+        };
+        let (_, object_principal) = self.cardinal_points(from_sensor, x);
+        (
+            image_focal - image_principal,
+            object_principal,
+            image_principal,
+        )
+    }
+
+    /// Moves the lens so that what is `distance` millimetres in front of the sensor is in focus,
+    /// treating it as a thick lens, or as close to it as the lens can focus.
+    fn focus(&mut self, distance: Float) {
+        let (f, object_principal, image_principal) = self.thick_lens();
+
+        // With the lens moved by delta, the object is s = object_principal + delta + distance
+        // in front of the lens and its image is f s / (s - f) behind, which should land on the
+        // sensor. That makes s a root of s^2 - k s + f k, where k is the distance from the
+        // object to the sensor as seen by the principal planes. There is no root for k < 4 f,
+        // closer than the lens can focus.
+        let distance = distance.max(4. * f - object_principal + image_principal);
+        let k = distance + object_principal - image_principal;
+        let s = (k + (k * k - 4. * f * k).max(0.).sqrt()) / 2.;
+        let delta = s - object_principal - distance;
+        let last = self.lens.surfaces.len() - 1;
+        self.lens.surfaces[last].thickness -= delta;
+        self.place_surfaces();
+        self.magnification = f / (s - f);
+    }
+
+    /// The point of the sensor (`u`, `v`) of the image falls on.
+    fn on_sensor(&self, u: Float, v: Float) -> Pos {
+        // The lens turns the image upside down
+        let (shift_x, shift_y) = self.shift;
+        let x = (0.5 - u - shift_x) * self.width;
+        let y = (0.5 - v - shift_y) * self.height;
+        // By the Scheimpflug principle, the sensor turns less than the plane in focus, in
+        // proportion to the magnification
+        let (tilt, swing) = self.tilt;
+        pos(x, y, self.magnification * (y * tilt + x * swing))
+    }
+
+    /// The ray leaving the front of the lens as `r`, in the scene.
+    fn to_scene(&self, r: LensRay, time: Float) -> Ray {
+        let to_scene = |p: Dir| p.x() * self.u + p.y() * self.v + p.z() * self.w;
+        Ray::new(
+            self.origin + to_scene(r.origin - Pos::zero()) / self.unit,
+            to_scene(r.direction),
+            time,
+        )
+    }
+}
+
+impl Camera for Realistic {
+    fn get_ray(&self, u: Float, v: Float, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let on_sensor = self.on_sensor(u, v);
+        let rear = self.lens.surfaces.len() - 1;
+        let (x, y) = Aperture::Circle.sample(sampler.next_2d());
+        let radius = self.lens.surfaces[rear].aperture / 2.;
+        let on_rear = pos(radius * x, radius * y, self.z[rear]);
+        let r = self.trace_from_sensor(LensRay {
+            origin: on_sensor,
+            direction: on_rear - on_sensor,
+        })?;
+        Some(self.to_scene(r, time(self.time0, self.time1, sampler)))
+    }
+
+    fn exposure(&self) -> Float {
+        self.exposure
+    }
+
+    /// Focuses on what the ray through the middle of the rear of the lens hits, or as close as
+    /// the lens can.
+    fn autofocus(&mut self, hit: &mut dyn FnMut(&Ray) -> Option<Float>, u: Float, v: Float) {
+        let on_sensor = self.on_sensor(u, v);
+        let rear = pos(0., 0., self.z[self.lens.surfaces.len() - 1]);
+        let r = match self.trace_from_sensor(LensRay {
+            origin: on_sensor,
+            direction: rear - on_sensor,
+        }) {
+            Some(r) => self.to_scene(r, self.time0),
+            None => return,
+        };
+        if let Some(t) = hit(&r) {
+            let distance = (r.point_at(t) - self.origin).dot(-self.w) * self.unit;
+            self.focus(distance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Independent;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn double_gauss() -> Lens {
+        Lens::parse(include_str!("../../lenses/dgauss.50mm.dat")).unwrap()
+    }
+
+    #[test]
+    fn test_parse_lens() {
+        let lens = double_gauss();
+        assert_eq!(11, lens.surfaces.len());
+        assert_eq!(
+            LensSurface {
+                radius: 0.,
+                thickness: 4.5,
+                ior: 1.,
+                aperture: 17.1
+            },
+            lens.surfaces[5]
+        );
+        match Lens::parse("# radius thickness\n1 2") {
+            Err(e @ LoadLensError::WrongColumnCount { line: 2, found: 2 }) => assert_eq!(
+                "expected 4 columns on line 2 of the lens prescription, found 2",
+                e.to_string()
+            ),
+            _ => panic!("expected a column count error"),
+        }
+        match Lens::parse("10 2 1.5 20\n-10 5 0 -1") {
+            Err(e @ LoadLensError::NotPositive { line: 2, .. }) => assert_eq!(
+                "the aperture on line 2 of the lens prescription should be positive, not -1",
+                e.to_string()
+            ),
+            _ => panic!("expected an aperture error"),
+        }
+        match Lens::parse("10 2 -1.5 20") {
+            Err(LoadLensError::NotPositive {
+                measurement: "index of refraction",
+                ..
+            }) => (),
+            _ => panic!("expected an index of refraction error"),
+        }
+    }
+
+    /// How close to the sensor, in millimetres, the lens can focus.
+    fn closest_focus(camera: &Realistic) -> Float {
+        let (f, object_principal, image_principal) = camera.thick_lens();
+        4. * f - object_principal + image_principal
+    }
+
+    fn focused_at_2m() -> Realistic {
+        Realistic::new(
+            Placement {
+                look_from: Pos::zero(),
+                look_at: pos(0., 0., -1.),
+                up: dir(0., 1., 0.),
+            },
+            double_gauss(),
+            (36., 24.),
+            1.5,
+            2.,
+            1000.,
+            (0., 0.),
+        )
+    }
+
+    /// Where, in millimetres along the axis, two rays from the point of the sensor for (`u`,
+    /// `v`) through either side of the middle of the rear of the lens cross.
+    fn in_focus(camera: &Realistic, u: Float, v: Float) -> Float {
+        let on_sensor = camera.on_sensor(u, v);
+        let rear = camera.z[10];
+        let through = |x: Float| {
+            camera
+                .trace_from_sensor(LensRay {
+                    origin: on_sensor,
+                    direction: pos(x, 0., rear) - on_sensor,
+                })
+                .unwrap()
+        };
+        let (a, b) = (through(-0.5), through(0.5));
+        let slope = |r: &LensRay| r.direction.x() / r.direction.z();
+        let (xa, xb) = (
+            a.origin.x() - a.origin.z() * slope(&a),
+            b.origin.x() - b.origin.z() * slope(&b),
+        );
+        (xb - xa) / (slope(&a) - slope(&b))
+    }
+
+    #[test]
+    fn test_autofocus_brings_what_is_seen_into_focus() {
+        let mut camera = focused_at_2m();
+        // A wall 5 units away
+        camera.autofocus(
+            &mut |r| Some((-5. - r.origin().z()) / r.direction().z()),
+            0.5,
+            0.5,
+        );
+        assert_approx_eq!(-5000., in_focus(&camera, 0.5, 0.5), 25.);
+        // Closer than the lens can focus, it gets as close as it can
+        camera.autofocus(&mut |_| Some(0.01), 0.5, 0.5);
+        assert_approx_eq!(-closest_focus(&camera), in_focus(&camera, 0.5, 0.5), 5.);
+    }
+
+    #[test]
+    fn test_tilted_plane_in_focus() {
+        let bokeh = Bokeh {
+            tilt: (30., 0.),
+            ..Bokeh::default()
+        };
+        let camera = focused_at_2m().with_bokeh(&bokeh);
+        let untilted = focused_at_2m();
+        assert_approx_eq!(-2000., in_focus(&camera, 0.5, 0.5), 10.);
+        // The plane in focus leans back, so the top of the image is in focus further away than
+        // with the lens untilted, whose field curvature already bends the plane towards it
+        assert!(in_focus(&camera, 0.5, 0.75) < in_focus(&untilted, 0.5, 0.75) - 100.);
+        assert!(in_focus(&camera, 0.5, 0.25) > in_focus(&untilted, 0.5, 0.25) + 100.);
+    }
+
+    #[test]
+    fn test_stopping_down() {
+        let lens = Physical {
+            sensor: (36., 24.),
+            focal_length: 50.,
+            f_number: 16.,
+            shutter: (0., 0.01),
+            iso: 100.,
+            unit: 1000.,
+        };
+        let camera = focused_at_2m().with_physical(&lens);
+        assert_approx_eq!(1., camera.exposure());
+        assert_eq!((0., 0.01), (camera.time0, camera.time1));
+
+        // The lens doesn't open as wide as f/1
+        let lens = Physical {
+            f_number: 1.,
+            ..lens
+        };
+        let exposure = focused_at_2m().with_physical(&lens).exposure();
+        assert!(
+            1. < exposure && exposure < 256. / (1.5 * 1.5),
+            "{}",
+            exposure
+        );
+    }
+
+    #[test]
+    fn test_focusing_too_close() {
+        let camera = Realistic::new(
+            Placement {
+                look_from: Pos::zero(),
+                look_at: pos(0., 0., -1.),
+                up: dir(0., 1., 0.),
+            },
+            double_gauss(),
+            (36., 24.),
+            1.5,
+            0.1,
+            1000.,
+            (0., 0.),
+        );
+        assert_approx_eq!(-closest_focus(&camera), in_focus(&camera, 0.5, 0.5), 5.);
+    }
+
+    #[test]
+    fn test_rays_from_the_centre_of_the_sensor_meet_at_the_focus_distance() {
+        let camera = focused_at_2m();
+        // Just behind the lens, as the rear surface's thickness is its distance to the sensor
+        assert!(camera.lens.surfaces[10].thickness > 30.);
+
+        // Close to the axis, where spherical aberration doesn't move the focus
+        let rear = pos(0.5, 0., camera.z[10]);
+        let r = camera
+            .trace_from_sensor(LensRay {
+                origin: Pos::zero(),
+                direction: rear - Pos::zero(),
+            })
+            .unwrap();
+        let t = -r.origin.x() / r.direction.x();
+        assert_approx_eq!(-2000., (r.origin + t * r.direction).z(), 10.);
+
+        // Through the whole lens, some rays are blocked
+        let mut sampler = Independent::new();
+        let seen = (0..100)
+            .filter(|_| camera.get_ray(0.9, 0.9, &mut sampler).is_some())
+            .count();
+        assert!(seen > 10 && seen < 100, "{}", seen);
+    }
+}
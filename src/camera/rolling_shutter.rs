@@ -0,0 +1,42 @@
+use super::{Camera, CameraBox};
+use crate::prelude::*;
+
+/// Exposes the rows of the image one after the other from the top, as sensors read out line by
+/// line do, so that things moving across the image lean.
+pub struct RollingShutter {
+    camera: CameraBox,
+    time0: Float,
+    time1: Float,
+    /// Fraction of the shutter interval between the top row starting its exposure and the
+    /// bottom one starting. Each row is exposed for the rest.
+    readout: Float,
+}
+
+impl RollingShutter {
+    pub fn new(camera: CameraBox, time0: Float, time1: Float, readout: Float) -> Self {
+        Self {
+            camera,
+            time0,
+            time1,
+            readout,
+        }
+    }
+}
+
+impl Camera for RollingShutter {
+    fn get_ray(&self, u: Float, v: Float, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let r = self.camera.get_ray(u, v, sampler)?;
+        let interval = self.time1 - self.time0;
+        let open = self.time0 + self.readout * (1. - v) * interval;
+        let time = open + sampler.next_1d() * (1. - self.readout) * interval;
+        Some(r.with_time(time))
+    }
+
+    fn exposure(&self) -> Float {
+        self.camera.exposure()
+    }
+
+    fn autofocus(&mut self, hit: &mut dyn FnMut(&Ray) -> Option<Float>, u: Float, v: Float) {
+        self.camera.autofocus(hit, u, v);
+    }
+}
@@ -0,0 +1,37 @@
+use super::{Camera, CameraBox};
+use crate::prelude::*;
+
+/// A stereo pair for VR headsets, laid out over-under: the left eye's view in the top half of
+/// the image and the right eye's in the bottom half. Each eye's camera sees its half as a whole
+/// image, so it should be made for half the height.
+pub struct Stereo {
+    left: CameraBox,
+    right: CameraBox,
+}
+
+impl Stereo {
+    pub fn new(left: CameraBox, right: CameraBox) -> Self {
+        Self { left, right }
+    }
+}
+
+impl Camera for Stereo {
+    fn get_ray(&self, u: Float, v: Float, sampler: &mut dyn Sampler) -> Option<Ray> {
+        if v >= 0.5 {
+            self.left.get_ray(u, 2. * v - 1., sampler)
+        } else {
+            self.right.get_ray(u, 2. * v, sampler)
+        }
+    }
+
+    fn exposure(&self) -> Float {
+        self.left.exposure()
+    }
+
+    fn autofocus(&mut self, hit: &mut dyn FnMut(&Ray) -> Option<Float>, u: Float, v: Float) {
+        // Both eyes focus on what each sees at the same point of its half
+        let v = if v >= 0.5 { 2. * v - 1. } else { 2. * v };
+        self.left.autofocus(hit, u, v);
+        self.right.autofocus(hit, u, v);
+    }
+}
@@ -0,0 +1,165 @@
+//! Denoising of the finished image, guided by what the camera rays first hit in each pixel. Noise
+//! is averaged away between nearby pixels which see the same kind of surface, facing the same
+//! way at the same distance, while the edges between different surfaces are kept.
+
+use crate::prelude::*;
+use rayon::prelude::*;
+
+/// Parameters of the joint bilateral filter. Each `sigma` is how large a difference, in the
+/// corresponding feature, it takes for a neighbour to count much less.
+#[derive(Clone, Copy, Debug)]
+pub struct Denoise {
+    /// Half the side of the square of neighbours considered, in pixels.
+    pub radius: usize,
+    /// In pixels.
+    pub sigma_spatial: Float,
+    /// In standard deviations of the noise of the two pixels' estimates.
+    pub sigma_colour: Float,
+    pub sigma_albedo: Float,
+    /// On the length of the difference between unit normals.
+    pub sigma_normal: Float,
+    /// Relative to the distance to the nearest of the two hits.
+    pub sigma_depth: Float,
+}
+
+impl Default for Denoise {
+    fn default() -> Self {
+        Self {
+            radius: 6,
+            sigma_spatial: 3.,
+            sigma_colour: 2.,
+            sigma_albedo: 0.1,
+            sigma_normal: 0.3,
+            sigma_depth: 0.1,
+        }
+    }
+}
+
+/// Features of the first hits in a pixel, averaged, to tell which pixels may be averaged
+/// together.
+#[derive(Clone, Copy, Debug)]
+pub struct Features {
+    /// Colour of the surface, or of the light or background seen.
+    pub albedo: Col,
+    /// Zero for rays escaping the world and hits in participating media.
+    pub normal: Dir,
+    /// Zero for rays escaping the world.
+    pub depth: Float,
+}
+
+/// What the denoiser knows about one pixel.
+#[derive(Clone, Copy, Debug)]
+pub struct Guide {
+    pub colour: Col,
+    /// Variance of the estimate of the pixel's luminance.
+    pub variance: Float,
+    /// Averaged over the pixel's samples.
+    pub features: Features,
+}
+
+/// Smallest albedo the light is divided by, so that black surfaces don't blow up.
+const MIN_ALBEDO: Float = 0.01;
+
+/// Filters the light reaching each pixel, that is its colour divided by its albedo, so that
+/// textures stay sharp however much their lighting is smoothed, with weights depending on the
+/// distance between pixels and the differences between their estimates and features.
+pub fn joint_bilateral(w: usize, h: usize, guides: &[Guide], params: &Denoise) -> Vec<Col> {
+    let albedo = |g: &Guide| {
+        let a = g.features.albedo;
+        col(
+            a.r().max(MIN_ALBEDO),
+            a.g().max(MIN_ALBEDO),
+            a.b().max(MIN_ALBEDO),
+        )
+    };
+    let irradiance: Vec<Col> = guides.iter().map(|g| g.colour / albedo(g)).collect();
+    let irradiance_variance: Vec<Float> = guides
+        .iter()
+        .map(|g| g.variance / albedo(g).luminance().powi(2))
+        .collect();
+    let gaussian = |d2: Float, sigma: Float| (-d2 / (2. * sigma * sigma)).exp();
+    let radius = params.radius as isize;
+
+    (0..w * h)
+        .into_par_iter()
+        .map(|p| {
+            let (x, y) = ((p % w) as isize, (p / w) as isize);
+            let gp = &guides[p];
+            let lp = irradiance[p].luminance();
+            let mut sum = Col::zero();
+            let mut weight = 0.;
+            for qy in (y - radius).max(0)..(y + radius + 1).min(h as isize) {
+                for qx in (x - radius).max(0)..(x + radius + 1).min(w as isize) {
+                    let q = qx as usize + w * qy as usize;
+                    let gq = &guides[q];
+                    let (dx, dy) = ((qx - x) as Float, (qy - y) as Float);
+
+                    let dl = lp - irradiance[q].luminance();
+                    let noise = irradiance_variance[p] + irradiance_variance[q] + 1e-6;
+                    let da = (gp.features.albedo - gq.features.albedo).squared_length();
+                    let dn = (gp.features.normal - gq.features.normal).squared_length();
+                    let depth = gp.features.depth.min(gq.features.depth).max(1e-3);
+                    let dd = (gp.features.depth - gq.features.depth) / depth;
+
+                    let wq = gaussian(dx * dx + dy * dy, params.sigma_spatial)
+                        * gaussian(dl * dl / noise, params.sigma_colour)
+                        * gaussian(da, params.sigma_albedo)
+                        * gaussian(dn, params.sigma_normal)
+                        * gaussian(dd * dd, params.sigma_depth);
+                    sum += wq * irradiance[q];
+                    weight += wq;
+                }
+            }
+            // The pixel itself always has weight one
+            albedo(gp) * sum / weight
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    fn guide(value: Float, normal: Dir) -> Guide {
+        Guide {
+            colour: col(value, value, value),
+            variance: 0.01,
+            features: Features {
+                albedo: col(0.5, 0.5, 0.5),
+                normal,
+                depth: 1.,
+            },
+        }
+    }
+
+    #[test]
+    fn test_noise_is_smoothed_but_edges_are_kept() {
+        let (w, h) = (16, 16);
+        let mut rng = StdRng::seed_from_u64(39);
+        let guides: Vec<Guide> = (0..w * h)
+            .map(|p| {
+                let noise = 0.2 * (rng.gen::<Float>() - 0.5);
+                if p % w < w / 2 {
+                    guide(0.8 + noise, dir(0., 0., 1.))
+                } else {
+                    guide(0.2 + noise, dir(1., 0., 0.))
+                }
+            })
+            .collect();
+        let denoised = joint_bilateral(w, h, &guides, &Denoise::default());
+
+        let error = |colours: &mut dyn Iterator<Item = Col>| -> Float {
+            colours
+                .enumerate()
+                .map(|(p, c)| {
+                    let expected = if p % w < w / 2 { 0.8 } else { 0.2 };
+                    (c.g() - expected).powi(2)
+                })
+                .sum()
+        };
+        let before = error(&mut guides.iter().map(|g| g.colour));
+        let after = error(&mut denoised.iter().cloned());
+        assert!(after < before / 4., "{} {}", before, after);
+    }
+}
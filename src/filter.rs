@@ -0,0 +1,196 @@
+//! Reconstruction filters, which spread each sample over the pixels around where it landed. A
+//! pixel's colour is then the average of the samples near it, weighted by the filter at their
+//! offset from its centre.
+
+use crate::{prelude::*, Settings};
+
+pub trait Filter: Send + Sync {
+    /// Distance from the sample, in pixels and along each axis, beyond which the filter is zero.
+    fn radius(&self) -> Float;
+
+    /// Weight of a sample at offset `x` from a pixel centre, along one axis.
+    fn eval_1d(&self, x: Float) -> Float;
+
+    /// Weight of a sample at offset (`x`, `y`) from a pixel centre.
+    fn eval(&self, x: Float, y: Float) -> Float {
+        self.eval_1d(x) * self.eval_1d(y)
+    }
+}
+
+pub type FilterBox = Box<dyn Filter>;
+
+#[derive(Clone, Copy, Debug)]
+pub enum FilterKind {
+    /// Every sample within the radius counts the same. With a radius of half a pixel, samples
+    /// only count towards the pixel they land in.
+    Box,
+    /// Weights falling off linearly to zero at the radius.
+    Tent,
+    /// A Gaussian of falloff `alpha`, shifted down to reach zero at the radius.
+    Gaussian { alpha: Float },
+    /// The Mitchell-Netravali cubic, with its two parameters. `b` = `c` = 1/3 is the usual
+    /// compromise between blurring and ringing.
+    Mitchell { b: Float, c: Float },
+    /// The sinc function windowed by a wider sinc, with as many lobes as the radius has pixels.
+    /// Sharpest, but rings around edges.
+    Lanczos,
+}
+
+pub fn filter(settings: &Settings) -> FilterBox {
+    let radius = settings.filter_radius;
+    match settings.filter {
+        FilterKind::Box => Box::new(BoxFilter { radius }),
+        FilterKind::Tent => Box::new(Tent { radius }),
+        FilterKind::Gaussian { alpha } => Box::new(Gaussian { radius, alpha }),
+        FilterKind::Mitchell { b, c } => Box::new(Mitchell { radius, b, c }),
+        FilterKind::Lanczos => Box::new(Lanczos { radius }),
+    }
+}
+
+pub struct BoxFilter {
+    pub radius: Float,
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> Float {
+        self.radius
+    }
+
+    fn eval_1d(&self, _x: Float) -> Float {
+        1.
+    }
+}
+
+struct Tent {
+    radius: Float,
+}
+
+impl Filter for Tent {
+    fn radius(&self) -> Float {
+        self.radius
+    }
+
+    fn eval_1d(&self, x: Float) -> Float {
+        (self.radius - x.abs()).max(0.)
+    }
+}
+
+struct Gaussian {
+    radius: Float,
+    alpha: Float,
+}
+
+impl Filter for Gaussian {
+    fn radius(&self) -> Float {
+        self.radius
+    }
+
+    fn eval_1d(&self, x: Float) -> Float {
+        let g = |x: Float| (-self.alpha * x * x).exp();
+        (g(x) - g(self.radius)).max(0.)
+    }
+}
+
+struct Mitchell {
+    radius: Float,
+    b: Float,
+    c: Float,
+}
+
+impl Filter for Mitchell {
+    fn radius(&self) -> Float {
+        self.radius
+    }
+
+    fn eval_1d(&self, x: Float) -> Float {
+        // The cubic is defined over [-2, 2]
+        let x = (2. * x / self.radius).abs();
+        let (b, c) = (self.b, self.c);
+        let p = if x < 1. {
+            (12. - 9. * b - 6. * c) * x * x * x + (-18. + 12. * b + 6. * c) * x * x + (6. - 2. * b)
+        } else if x < 2. {
+            (-b - 6. * c) * x * x * x
+                + (6. * b + 30. * c) * x * x
+                + (-12. * b - 48. * c) * x
+                + (8. * b + 24. * c)
+        } else {
+            0.
+        };
+        p / 6.
+    }
+}
+
+struct Lanczos {
+    radius: Float,
+}
+
+fn sinc(x: Float) -> Float {
+    if x.abs() < 1e-5 {
+        1.
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl Filter for Lanczos {
+    fn radius(&self) -> Float {
+        self.radius
+    }
+
+    fn eval_1d(&self, x: Float) -> Float {
+        if x.abs() > self.radius {
+            0.
+        } else {
+            sinc(x) * sinc(x / self.radius)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn filters() -> Vec<FilterBox> {
+        vec![
+            Box::new(BoxFilter { radius: 0.5 }),
+            Box::new(Tent { radius: 1. }),
+            Box::new(Gaussian {
+                radius: 1.5,
+                alpha: 2.,
+            }),
+            Box::new(Mitchell {
+                radius: 2.,
+                b: 1. / 3.,
+                c: 1. / 3.,
+            }),
+            Box::new(Lanczos { radius: 3. }),
+        ]
+    }
+
+    #[test]
+    fn test_filters_peak_at_the_centre_and_vanish_at_the_radius() {
+        for filter in filters() {
+            let r = filter.radius();
+            assert!(filter.eval(0., 0.) > 0.);
+            for i in 1..10 {
+                let x = r * i as Float / 10.;
+                assert!(filter.eval_1d(x) <= filter.eval_1d(0.));
+                assert_approx_eq!(filter.eval_1d(x), filter.eval_1d(-x));
+            }
+        }
+        for filter in &filters()[1..] {
+            assert_approx_eq!(0., filter.eval_1d(filter.radius()));
+        }
+    }
+
+    #[test]
+    fn test_mitchell_is_continuous_at_the_knot() {
+        let mitchell = Mitchell {
+            radius: 2.,
+            b: 1. / 3.,
+            c: 1. / 3.,
+        };
+        assert_approx_eq!(mitchell.eval_1d(0.9999), mitchell.eval_1d(1.0001), 1e-3);
+    }
+}
@@ -0,0 +1,147 @@
+//! Images of counts made for each pixel, coloured from black for none through to white for the
+//! most, with a legend of the colours and the counts they stand for below.
+
+use crate::{pixbuf::heat, prelude::*};
+use image::{ImageBuffer, Rgb};
+
+/// The digits, three pixels wide and five high, as three bits for each row from the top.
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+/// Size of a pixel of the digits, in pixels of the image.
+const SCALE: u32 = 2;
+const MARGIN: u32 = 4;
+/// Height of the bar of colours.
+const BAR: u32 = 10;
+/// Height of the legend: the bar, then the labels under it.
+pub const LEGEND: u32 = 3 * MARGIN + BAR + 5 * SCALE;
+
+const BACKGROUND: Rgb<u8> = Rgb { data: [64, 64, 64] };
+const TEXT: Rgb<u8> = Rgb {
+    data: [255, 255, 255],
+};
+
+fn colour(t: Float) -> Rgb<u8> {
+    let c = heat(t);
+    let byte = |f: Float| (255. * f).round() as u8;
+    Rgb([byte(c.r()), byte(c.g()), byte(c.b())])
+}
+
+fn text_width(text: &str) -> u32 {
+    (4 * text.len() as u32 - 1) * SCALE
+}
+
+/// Writes the digits of `text` with their top left corner at (`x`, `y`), cutting off whatever
+/// falls outside the image. Anything but a digit is left blank.
+fn draw_text(img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, x: i64, y: i64, text: &str) {
+    for (k, digit) in text.bytes().enumerate() {
+        let glyph = match digit {
+            b'0'..=b'9' => &DIGITS[(digit - b'0') as usize],
+            _ => continue,
+        };
+        let left = x + (4 * SCALE as i64) * k as i64;
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..SCALE as i64 {
+                    for dx in 0..SCALE as i64 {
+                        let px = left + column * SCALE as i64 + dx;
+                        let py = y + row as i64 * SCALE as i64 + dy;
+                        if 0 <= px && px < img.width() as i64 && 0 <= py && py < img.height() as i64
+                        {
+                            img.put_pixel(px as u32, py as u32, TEXT);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Heat map of `counts`, one for each pixel of a `w` by `h` image row by row from the top,
+/// relative to the largest. The legend is added below the image, labelled with no count, half
+/// the largest and the largest.
+pub fn heat_map(w: usize, h: usize, counts: &[usize]) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    assert_eq!(w * h, counts.len());
+    let max = counts.iter().cloned().max().unwrap_or(0).max(1);
+    let (w, h) = (w as u32, h as u32);
+    let mut img = ImageBuffer::from_pixel(w, h + LEGEND, BACKGROUND);
+    for (i, j, p) in img.enumerate_pixels_mut() {
+        if j < h {
+            *p = colour(counts[(i + w * j) as usize] as Float / max as Float);
+        }
+    }
+
+    let bar = w.saturating_sub(2 * MARGIN).max(2);
+    let top = h + MARGIN;
+    for x in (0..bar).take_while(|x| MARGIN + x < w) {
+        let c = colour(x as Float / (bar - 1) as Float);
+        for y in top..top + BAR {
+            img.put_pixel(MARGIN + x, y, c);
+        }
+    }
+
+    // Labels centred under where their colours are, but kept within the image
+    let labels = top + BAR + MARGIN;
+    for &count in &[0, max / 2, max] {
+        let text = count.to_string();
+        let centre = MARGIN as i64 + ((bar - 1) as usize * count / max) as i64;
+        let x = (centre - text_width(&text) as i64 / 2)
+            .min(w as i64 - text_width(&text) as i64)
+            .max(0);
+        draw_text(&mut img, x, labels as i64, &text);
+    }
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heat_map_with_legend() {
+        let (w, h) = (60, 2);
+        let counts: Vec<usize> = (0..w * h).map(|i| i % w).collect();
+        let img = heat_map(w, h, &counts);
+        assert_eq!((w as u32, h as u32 + LEGEND), img.dimensions());
+        assert_eq!(Rgb([0, 0, 0]), *img.get_pixel(0, 1));
+        assert_eq!(Rgb([255, 255, 255]), *img.get_pixel(w as u32 - 1, 1));
+
+        // The bar goes through the same colours as the image
+        let bar = h as u32 + MARGIN;
+        assert_eq!(colour(0.), *img.get_pixel(MARGIN, bar));
+        assert_eq!(colour(1.), *img.get_pixel(w as u32 - MARGIN - 1, bar));
+
+        // "0" centred under the left end: its top row is lit, and the middle of it isn't
+        let labels = bar + BAR + MARGIN;
+        let zero = MARGIN - 3 * SCALE / 2;
+        assert_eq!(TEXT, *img.get_pixel(zero, labels));
+        assert_eq!(BACKGROUND, *img.get_pixel(zero + SCALE, labels + 2 * SCALE));
+        // "59" at the right, ending at the edge
+        assert_eq!(TEXT, *img.get_pixel(w as u32 - 1, labels));
+    }
+
+    #[test]
+    fn test_text_other_than_digits_is_blank() {
+        let mut img = ImageBuffer::from_pixel(12 * SCALE, 5 * SCALE, BACKGROUND);
+        draw_text(&mut img, 0, 0, "1 -1");
+        // The space and the minus sign keep their places, but draw nothing
+        for x in 4 * SCALE..12 * SCALE {
+            for y in 0..5 * SCALE {
+                assert_eq!(BACKGROUND, *img.get_pixel(x, y));
+            }
+        }
+        assert_eq!(TEXT, *img.get_pixel(SCALE, 0));
+    }
+}
@@ -1,146 +1,252 @@
 mod bounding_box;
+mod constant_medium;
+mod heterogeneous_medium;
 mod list;
 mod moving_sphere;
 mod prelude;
 mod sphere;
-mod stats;
-
+#[cfg(feature = "stats")]
+mod stats_recorder;
+mod transformed;
+
+use crate::{
+    animation::Keyframes,
+    material::isotropic,
+    stats::{self, Category},
+    texture::DensityBox,
+    transform::Transform,
+};
 use prelude::*;
-use stats::StatsRecorder;
+#[cfg(feature = "stats")]
+use stats_recorder::StatsRecorder;
+use std::{sync::Arc, time::Instant};
 
 pub struct HitRecord<'a> {
     pub t: Float,
     pub p: Pos,
+    /// Zero inside participating media, which have no surface.
     pub normal: Dir,
     pub mat: &'a Material,
+    /// Identifies the primitive which was hit, for the object ID pass.
+    pub object: usize,
 }
 
-pub trait Hitable<C> {
-    fn hit(&self, c: &mut C, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord>;
+pub trait Hitable {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord>;
     fn bounding_box(&self, t0: Float, t1: Float) -> Option<Cow<BoundingBox>>;
+
+    /// The first surface `r` hits, for shadow rays: they go through participating media, which
+    /// only dim them, as `transmittance` tells.
+    fn hit_surface(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        self.hit(r, t_min, t_max)
+    }
+
+    /// Fraction of the light going along `r` from `t_min` to `t_max` which gets through the
+    /// participating media in the hitable. Surfaces don't count, as `hit_surface` finds them.
+    fn transmittance(&self, _r: &Ray, _t_min: Float, _t_max: Float) -> Float {
+        1.
+    }
+
+    /// Density, per unit solid angle seen from `o` at `time`, with which `random` picks `v`.
+    /// Hitables which can't be sampled as lights return zero.
+    fn pdf_value(&self, _o: Pos, _v: Dir, _time: Float) -> Float {
+        0.
+    }
+
+    /// A random direction from `o` towards the hitable where it is at `time`, for sampling it
+    /// as a light.
+    fn random(&self, _o: Pos, _time: Float, _sampler: &mut dyn Sampler) -> Dir {
+        dir(1., 0., 0.)
+    }
+
+    /// A random point on the surface at `time`, with the density per unit area it was picked
+    /// with, for starting light paths on the hitable.
+    fn sample_surface(
+        &self,
+        _time: Float,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<(HitRecord<'_>, Float)> {
+        None
+    }
+
+    /// Density per unit area with which `sample_surface` picks `p` at `time`.
+    fn area_pdf(&self, _p: Pos, _time: Float) -> Float {
+        0.
+    }
 }
 
-pub type HitableBox<C> = Box<Hitable<C> + Send + Sync>;
+pub type HitableBox = Box<Hitable + Send + Sync>;
 
-impl<C> Hitable<C> for HitableBox<C> {
-    fn hit(&self, c: &mut C, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
-        (&(**self)).hit(c, r, t_min, t_max)
+impl Hitable for HitableBox {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        (&(**self)).hit(r, t_min, t_max)
     }
 
     fn bounding_box(&self, t0: Float, t1: Float) -> Option<Cow<BoundingBox>> {
         (&(**self)).bounding_box(t0, t1)
     }
-}
 
-pub trait HitableFactory<C> {
-    fn bounding_hierarchy(
-        &self,
-        list: Vec<HitableBox<C>>,
-        time0: Float,
-        time1: Float,
-    ) -> HitableBox<C>;
-    fn hitable_list(&self, list: Vec<HitableBox<C>>) -> HitableBox<C>;
-    fn sphere(&self, center: Pos, radius: Float, mat: Box<Material + Sync + Send>)
-        -> HitableBox<C>;
-    fn moving_sphere(
+    fn hit_surface(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        (**self).hit_surface(r, t_min, t_max)
+    }
+
+    fn transmittance(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        (**self).transmittance(r, t_min, t_max)
+    }
+
+    fn pdf_value(&self, o: Pos, v: Dir, time: Float) -> Float {
+        (**self).pdf_value(o, v, time)
+    }
+
+    fn random(&self, o: Pos, time: Float, sampler: &mut dyn Sampler) -> Dir {
+        (**self).random(o, time, sampler)
+    }
+
+    fn sample_surface(
         &self,
-        cen0: Pos,
-        cen1: Pos,
-        t0: Float,
-        t1: Float,
-        radius: Float,
-        mat: Box<Material + Sync + Send>,
-    ) -> HitableBox<C>;
+        time: Float,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(HitRecord<'_>, Float)> {
+        (**self).sample_surface(time, sampler)
+    }
+
+    fn area_pdf(&self, p: Pos, time: Float) -> Float {
+        (**self).area_pdf(p, time)
+    }
 }
 
-pub struct PlainHitableFactory;
+impl Hitable for Arc<HitableBox> {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        (**self).hit(r, t_min, t_max)
+    }
 
-impl<C: 'static> HitableFactory<C> for PlainHitableFactory {
-    fn bounding_hierarchy(
-        &self,
-        list: Vec<HitableBox<C>>,
-        time0: Float,
-        time1: Float,
-    ) -> HitableBox<C> {
-        bounding_box::BoundingHierarchy::build(self, list, time0, time1)
+    fn bounding_box(&self, t0: Float, t1: Float) -> Option<Cow<'_, BoundingBox>> {
+        (**self).bounding_box(t0, t1)
     }
 
-    fn hitable_list(&self, list: Vec<HitableBox<C>>) -> HitableBox<C> {
-        Box::new(list::HitableList::new(list))
+    fn hit_surface(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        (**self).hit_surface(r, t_min, t_max)
     }
 
-    fn sphere(
-        &self,
-        center: Pos,
-        radius: Float,
-        mat: Box<Material + Sync + Send>,
-    ) -> HitableBox<C> {
-        Box::new(sphere::Sphere::new(center, radius, mat))
+    fn transmittance(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        (**self).transmittance(r, t_min, t_max)
+    }
+
+    fn pdf_value(&self, o: Pos, v: Dir, time: Float) -> Float {
+        (**self).pdf_value(o, v, time)
+    }
+
+    fn random(&self, o: Pos, time: Float, sampler: &mut dyn Sampler) -> Dir {
+        (**self).random(o, time, sampler)
     }
 
-    fn moving_sphere(
+    fn sample_surface(
         &self,
-        cen0: Pos,
-        cen1: Pos,
-        t0: Float,
-        t1: Float,
-        radius: Float,
-        mat: Box<Material + Sync + Send>,
-    ) -> HitableBox<C> {
-        Box::new(moving_sphere::MovingSphere::new(
-            cen0, cen1, t0, t1, radius, mat,
-        ))
+        time: Float,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(HitRecord<'_>, Float)> {
+        (**self).sample_surface(time, sampler)
+    }
+
+    fn area_pdf(&self, p: Pos, time: Float) -> Float {
+        (**self).area_pdf(p, time)
     }
 }
 
-pub struct TracingHitableFactory;
+/// The stretch of `r` between `t_min` and `t_max` inside the closed `boundary` of a medium, if
+/// any.
+fn inside(boundary: &HitableBox, r: &Ray, t_min: Float, t_max: Float) -> Option<(Float, Float)> {
+    let enter = boundary.hit(r, -Float::MAX, Float::MAX)?.t;
+    let exit = boundary.hit(r, enter + 0.0001, Float::MAX)?.t;
+
+    let t1 = enter.max(t_min).max(0.);
+    let t2 = exit.min(t_max);
+    if t1 >= t2 {
+        None
+    } else {
+        Some((t1, t2))
+    }
+}
 
-pub type Stats = stats::Stats<&'static str>;
-fn stats_recorder<H>(category: &'static str, hitable: H) -> HitableBox<Stats>
+/// Boxes `hitable`, counting its hits and misses under `category` when built with statistics.
+#[cfg(feature = "stats")]
+fn counted<H>(category: Category, hitable: H) -> HitableBox
 where
-    H: 'static + Hitable<Stats> + Send + Sync,
+    H: 'static + Hitable + Send + Sync,
 {
     Box::new(StatsRecorder::new(category, hitable))
 }
 
-impl HitableFactory<Stats> for TracingHitableFactory {
-    fn bounding_hierarchy(
-        &self,
-        list: Vec<HitableBox<Stats>>,
-        time0: Float,
-        time1: Float,
-    ) -> HitableBox<Stats> {
-        Box::new(stats_recorder(
-            "bounding_box",
-            bounding_box::BoundingHierarchy::build(self, list, time0, time1),
-        ))
-    }
+#[cfg(not(feature = "stats"))]
+fn counted<H>(_category: Category, hitable: H) -> HitableBox
+where
+    H: 'static + Hitable + Send + Sync,
+{
+    Box::new(hitable)
+}
 
-    fn hitable_list(&self, list: Vec<HitableBox<Stats>>) -> HitableBox<Stats> {
-        stats_recorder("list", list::HitableList::new(list))
-    }
+pub fn bounding_hierarchy(list: Vec<HitableBox>, time0: Float, time1: Float) -> HitableBox {
+    let start = Instant::now();
+    let bvh = bounding_box::BoundingHierarchy::build(list, time0, time1);
+    stats::bvh_built(start.elapsed());
+    bvh
+}
 
-    fn sphere(
-        &self,
-        center: Pos,
-        radius: Float,
-        mat: Box<Material + Sync + Send>,
-    ) -> HitableBox<Stats> {
-        stats_recorder("sphere", sphere::Sphere::new(center, radius, mat))
-    }
+pub fn constant_medium(boundary: HitableBox, density: Float, albedo: TextureBox) -> HitableBox {
+    counted(
+        Category::ConstantMedium,
+        constant_medium::ConstantMedium::new(boundary, density, isotropic(albedo)),
+    )
+}
 
-    fn moving_sphere(
-        &self,
-        cen0: Pos,
-        cen1: Pos,
-        t0: Float,
-        t1: Float,
-        radius: Float,
-        mat: Box<Material + Sync + Send>,
-    ) -> HitableBox<Stats> {
-        stats_recorder(
-            "moving_sphere",
-            moving_sphere::MovingSphere::new(cen0, cen1, t0, t1, radius, mat),
-        )
-    }
+pub fn heterogeneous_medium(
+    boundary: HitableBox,
+    density: DensityBox,
+    albedo: TextureBox,
+) -> HitableBox {
+    counted(
+        Category::HeterogeneousMedium,
+        heterogeneous_medium::HeterogeneousMedium::new(boundary, density, isotropic(albedo)),
+    )
+}
+
+/// Another handle on the very same hitable, with the same ID, to put a light both in the world
+/// and among the lights sampled directly.
+pub fn shared(hitable: &Arc<HitableBox>) -> HitableBox {
+    Box::new(Arc::clone(hitable))
+}
+
+pub fn hitable_list(list: Vec<HitableBox>) -> HitableBox {
+    counted(Category::List, list::HitableList::new(list))
+}
+
+pub fn sphere(center: Pos, radius: Float, mat: Box<Material + Sync + Send>) -> HitableBox {
+    counted(Category::Sphere, sphere::Sphere::new(center, radius, mat))
+}
+
+pub fn moving_sphere(
+    cen0: Pos,
+    cen1: Pos,
+    t0: Float,
+    t1: Float,
+    radius: Float,
+    mat: Box<Material + Sync + Send>,
+) -> HitableBox {
+    counted(
+        Category::MovingSphere,
+        moving_sphere::MovingSphere::new(cen0, cen1, t0, t1, radius, mat),
+    )
+}
+
+pub fn transformed(hitable: HitableBox, transform: Transform) -> HitableBox {
+    animated(hitable, Keyframes::constant(transform))
+}
+
+/// The hitable moved by a transform which changes over time, blurring it when it moves while the
+/// shutter is open.
+pub fn animated(hitable: HitableBox, keys: Keyframes<Transform>) -> HitableBox {
+    counted(
+        Category::Transformed,
+        transformed::Transformed::new(hitable, keys),
+    )
 }
@@ -1,5 +1,8 @@
 use super::prelude::*;
-use crate::hitable::{HitableBox, HitableFactory};
+use crate::{
+    hitable::{counted, HitableBox},
+    stats::Category,
+};
 use itertools::izip;
 use rand::prelude::*;
 use std::{cmp::Ordering, ops::Deref};
@@ -33,6 +36,35 @@ impl BoundingBox {
         }
     }
 
+    /// The smallest box containing all the `points`.
+    pub fn around<I: IntoIterator<Item = Pos>>(points: I) -> Self {
+        let mut points = points.into_iter();
+        let first = points.next().expect("A box needs at least one point");
+        points.fold(Self::new(first, first), |b, p| {
+            Self::surrounding(&b, &Self::new(p, p))
+        })
+    }
+
+    /// The box grown by `margin` on every side.
+    pub fn padded(&self, margin: Float) -> Self {
+        let d = dir(margin, margin, margin);
+        Self::new(self.min - d, self.max + d)
+    }
+
+    pub fn corners(&self) -> [Pos; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            pos(a.x(), a.y(), a.z()),
+            pos(b.x(), a.y(), a.z()),
+            pos(a.x(), b.y(), a.z()),
+            pos(b.x(), b.y(), a.z()),
+            pos(a.x(), a.y(), b.z()),
+            pos(b.x(), a.y(), b.z()),
+            pos(a.x(), b.y(), b.z()),
+            pos(b.x(), b.y(), b.z()),
+        ]
+    }
+
     #[inline]
     fn hit(&self, r: &Ray, mut t_min: Float, mut t_max: Float) -> bool {
         for (min, max, d, o) in izip!(
@@ -55,10 +87,10 @@ impl BoundingBox {
     }
 }
 
-pub struct BoundingHierarchy<C> {
+pub struct BoundingHierarchy {
     bounds: BoundingBox,
-    left: HitableBox<C>,
-    right: HitableBox<C>,
+    left: HitableBox,
+    right: HitableBox,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -90,12 +122,12 @@ impl Distribution<Axis> for rand::distributions::Standard {
     }
 }
 
-fn bounding_box_compare<C>(
+fn bounding_box_compare(
     axis: Axis,
     time0: Float,
     time1: Float,
-    h1: &HitableBox<C>,
-    h2: &HitableBox<C>,
+    h1: &HitableBox,
+    h2: &HitableBox,
 ) -> Ordering {
     let c1 = axis.get(h1.bounding_box(time0, time1).expect("no bounding box").min);
     let c2 = axis.get(h2.bounding_box(time0, time1).expect("no bounding box").min);
@@ -103,13 +135,8 @@ fn bounding_box_compare<C>(
     c1.partial_cmp(&c2).unwrap()
 }
 
-impl<C: 'static> BoundingHierarchy<C> {
-    pub fn build(
-        factory: &dyn HitableFactory<C>,
-        mut list: Vec<HitableBox<C>>,
-        time0: Float,
-        time1: Float,
-    ) -> HitableBox<C> {
+impl BoundingHierarchy {
+    pub fn build(mut list: Vec<HitableBox>, time0: Float, time1: Float) -> HitableBox {
         let n = list.len();
         if n == 1 {
             list.into_iter().next().unwrap()
@@ -125,28 +152,31 @@ impl<C: 'static> BoundingHierarchy<C> {
                 let axis = rng.gen();
                 list.sort_unstable_by(|h1, h2| bounding_box_compare(axis, time0, time1, h1, h2));
                 let tail = list.split_off(n / 2);
-                let left = factory.bounding_hierarchy(list, time0, time1);
-                let right = factory.bounding_hierarchy(tail, time0, time1);
+                let left = Self::build(list, time0, time1);
+                let right = Self::build(tail, time0, time1);
                 (left, right)
             };
             let lbounds = left.bounding_box(time0, time1).expect("no bounding box");
             let rbounds = right.bounding_box(time0, time1).expect("no bounding box");
             let bounds = BoundingBox::surrounding(lbounds, rbounds);
-            Box::new(Self {
-                left,
-                right,
-                bounds,
-            })
+            counted(
+                Category::BoundingBox,
+                Self {
+                    left,
+                    right,
+                    bounds,
+                },
+            )
         }
     }
 }
 
-impl<C: 'static> Hitable<C> for BoundingHierarchy<C> {
+impl Hitable for BoundingHierarchy {
     #[inline]
-    fn hit(&self, c: &mut C, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
         if self.bounds.hit(r, t_min, t_max) {
-            let left = self.left.hit(c, r, t_min, t_max);
-            let right = self.right.hit(c, r, t_min, t_max);
+            let left = self.left.hit(r, t_min, t_max);
+            let right = self.right.hit(r, t_min, t_max);
             match (&left, &right) {
                 (Some(l), Some(r)) => {
                     if l.t < r.t {
@@ -166,6 +196,22 @@ impl<C: 'static> Hitable<C> for BoundingHierarchy<C> {
     fn bounding_box(&self, _t0: Float, _t1: Float) -> Option<Cow<BoundingBox>> {
         Some(Cow::Borrowed(&self.bounds))
     }
+
+    fn hit_surface(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        if !self.bounds.hit(r, t_min, t_max) {
+            return None;
+        }
+        let left = self.left.hit_surface(r, t_min, t_max);
+        let t_max = left.as_ref().map_or(t_max, |l| l.t);
+        self.right.hit_surface(r, t_min, t_max).or(left)
+    }
+
+    fn transmittance(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        if !self.bounds.hit(r, t_min, t_max) {
+            return 1.;
+        }
+        self.left.transmittance(r, t_min, t_max) * self.right.transmittance(r, t_min, t_max)
+    }
 }
 
 #[cfg(test)]
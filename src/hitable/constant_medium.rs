@@ -0,0 +1,105 @@
+use super::prelude::*;
+use crate::{
+    hitable::{inside, HitableBox},
+    material::MaterialBox,
+};
+use rand::prelude::*;
+
+/// A volume of constant density filling the inside of a closed boundary. Rays going through it
+/// scatter after a random, exponentially distributed distance, so the denser the medium the
+/// sooner they do.
+pub struct ConstantMedium {
+    boundary: HitableBox,
+    neg_inv_density: Float,
+    phase_function: MaterialBox,
+    id: usize,
+}
+
+impl ConstantMedium {
+    /// Panics unless `density` is positive: an empty volume is better left out of the scene.
+    pub fn new(boundary: HitableBox, density: Float, phase_function: MaterialBox) -> Self {
+        assert!(
+            density > 0.,
+            "A constant medium needs a positive density, not {}",
+            density
+        );
+        Self {
+            boundary,
+            neg_inv_density: -1. / density,
+            phase_function,
+            id: crate::id::object(),
+        }
+    }
+}
+
+impl Hitable for ConstantMedium {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let (t1, t2) = inside(&self.boundary, r, t_min, t_max)?;
+
+        let ray_length = r.direction().length();
+        let distance_inside_boundary = (t2 - t1) * ray_length;
+        // Not from the path's sampler: see the `sampler` module for why
+        let hit_distance = self.neg_inv_density * thread_rng().gen::<Float>().ln();
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        let t = t1 + hit_distance / ray_length;
+        Some(HitRecord {
+            t,
+            p: r.point_at(t),
+            normal: Dir::zero(),
+            mat: &*self.phase_function,
+            object: self.id,
+        })
+    }
+
+    fn hit_surface(&self, _r: &Ray, _t_min: Float, _t_max: Float) -> Option<HitRecord<'_>> {
+        None
+    }
+
+    fn transmittance(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        match inside(&self.boundary, r, t_min, t_max) {
+            Some((t1, t2)) => ((t2 - t1) * r.direction().length() / self.neg_inv_density).exp(),
+            None => 1.,
+        }
+    }
+
+    fn bounding_box(&self, t0: Float, t1: Float) -> Option<Cow<'_, BoundingBox>> {
+        self.boundary.bounding_box(t0, t1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hitable::sphere, material::isotropic, texture::constant_texture};
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    #[should_panic(expected = "positive density")]
+    fn test_zero_density_is_rejected() {
+        let white = || constant_texture(col(1., 1., 1.));
+        ConstantMedium::new(
+            sphere(Pos::zero(), 1., isotropic(white())),
+            0.,
+            isotropic(white()),
+        );
+    }
+
+    #[test]
+    fn test_shadow_rays_go_through_dimmed() {
+        let white = || constant_texture(col(1., 1., 1.));
+        let medium = ConstantMedium::new(
+            sphere(Pos::zero(), 1., isotropic(white())),
+            0.5,
+            isotropic(white()),
+        );
+        let r = Ray::new(pos(-2., 0., 0.), dir(2., 0., 0.), 0.);
+
+        assert!(medium.hit_surface(&r, 0., 10.).is_none());
+        assert_approx_eq!((-1. as Float).exp(), medium.transmittance(&r, 0., 10.));
+        // Stopping halfway through the medium
+        assert_approx_eq!((-0.5 as Float).exp(), medium.transmittance(&r, 0., 1.));
+    }
+}
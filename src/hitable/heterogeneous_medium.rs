@@ -0,0 +1,133 @@
+use super::prelude::*;
+use crate::{
+    hitable::{inside, HitableBox},
+    material::MaterialBox,
+    texture::DensityBox,
+};
+use rand::prelude::*;
+
+/// A volume whose density varies in space, filling the inside of a closed boundary.
+///
+/// Scattering distances are sampled with delta tracking: tentative collisions are drawn as if
+/// the whole volume had the maximum density, and each is kept with probability
+/// `density / max_density`, the others being null collisions that let the ray carry on. Shadow
+/// rays go through, dimmed by a transmittance estimated by ratio tracking.
+pub struct HeterogeneousMedium {
+    boundary: HitableBox,
+    density: DensityBox,
+    phase_function: MaterialBox,
+    id: usize,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(boundary: HitableBox, density: DensityBox, phase_function: MaterialBox) -> Self {
+        Self {
+            boundary,
+            density,
+            phase_function,
+            id: crate::id::object(),
+        }
+    }
+}
+
+impl Hitable for HeterogeneousMedium {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let max_density = self.density.max_density();
+        if max_density <= 0. {
+            return None;
+        }
+        let (t1, t2) = inside(&self.boundary, r, t_min, t_max)?;
+
+        // Not from the path's sampler: see the `sampler` module for why
+        let mut rng = thread_rng();
+        let inv_majorant = 1. / (max_density * r.direction().length());
+        let mut t = t1;
+        loop {
+            t -= (1. - rng.gen::<Float>()).ln() * inv_majorant;
+            if t >= t2 {
+                return None;
+            }
+            let p = r.point_at(t);
+            if rng.gen::<Float>() * max_density < self.density.density(p) {
+                return Some(HitRecord {
+                    t,
+                    p,
+                    normal: Dir::zero(),
+                    mat: &*self.phase_function,
+                    object: self.id,
+                });
+            }
+        }
+    }
+
+    fn hit_surface(&self, _r: &Ray, _t_min: Float, _t_max: Float) -> Option<HitRecord<'_>> {
+        None
+    }
+
+    /// Ratio tracking: the same tentative collisions as delta tracking, but rather than stopping
+    /// at a real one, each scales the transmittance by the chance that it was a null collision.
+    /// This never returns a transmittance of zero only because the ray happened to scatter.
+    fn transmittance(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        let max_density = self.density.max_density();
+        let (t1, t2) = match inside(&self.boundary, r, t_min, t_max) {
+            Some(stretch) if max_density > 0. => stretch,
+            _ => return 1.,
+        };
+
+        let mut rng = thread_rng();
+        let inv_majorant = 1. / (max_density * r.direction().length());
+        let mut transmittance = 1.;
+        let mut t = t1;
+        loop {
+            t -= (1. - rng.gen::<Float>()).ln() * inv_majorant;
+            if t >= t2 {
+                return transmittance;
+            }
+            transmittance *= 1. - self.density.density(r.point_at(t)) / max_density;
+        }
+    }
+
+    fn bounding_box(&self, t0: Float, t1: Float) -> Option<Cow<'_, BoundingBox>> {
+        self.boundary.bounding_box(t0, t1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hitable::sphere,
+        material::isotropic,
+        texture::{constant_texture, noise_density},
+    };
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_ratio_tracking_matches_optical_depth() {
+        let white = || constant_texture(col(1., 1., 1.));
+        let medium = HeterogeneousMedium::new(
+            sphere(Pos::zero(), 1., isotropic(white())),
+            noise_density(2., 2.),
+            isotropic(white()),
+        );
+        let r = Ray::new(pos(-2., 0.1, 0.2), dir(1., 0., 0.), 0.);
+
+        // Optical depth along the chord through the sphere, by the midpoint rule
+        let (t1, t2) = inside(&medium.boundary, &r, 0., 10.).unwrap();
+        let steps = 10_000;
+        let dt = (t2 - t1) / steps as Float;
+        let depth: Float = (0..steps)
+            .map(|i| {
+                medium
+                    .density
+                    .density(r.point_at(t1 + (i as Float + 0.5) * dt))
+                    * dt
+            })
+            .sum();
+
+        let n = 100_000;
+        let total: Float = (0..n).map(|_| medium.transmittance(&r, 0., 10.)).sum();
+        assert_approx_eq!((-depth).exp(), total / n as Float, 5e-3);
+        assert!(medium.hit_surface(&r, 0., 10.).is_none());
+    }
+}
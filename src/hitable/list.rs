@@ -1,22 +1,22 @@
 use super::prelude::*;
 
-pub struct HitableList<C> {
-    list: Vec<Box<Hitable<C> + Send + Sync>>,
+pub struct HitableList {
+    list: Vec<Box<Hitable + Send + Sync>>,
 }
 
-impl<C> HitableList<C> {
-    pub fn new(list: Vec<Box<Hitable<C> + Send + Sync>>) -> Self {
+impl HitableList {
+    pub fn new(list: Vec<Box<Hitable + Send + Sync>>) -> Self {
         Self { list }
     }
 }
 
-impl<C> Hitable<C> for HitableList<C> {
-    fn hit(&self, c: &mut C, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+impl Hitable for HitableList {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
         let mut result = None;
         let mut closest_so_far = t_max;
 
         for h in self.list.iter() {
-            let res = h.hit(c, r, t_min, closest_so_far);
+            let res = h.hit(r, t_min, closest_so_far);
             if let Some(HitRecord { t, .. }) = res {
                 closest_so_far = t;
                 result = res;
@@ -26,6 +26,28 @@ impl<C> Hitable<C> for HitableList<C> {
         result
     }
 
+    fn hit_surface(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let mut result = None;
+        let mut closest_so_far = t_max;
+
+        for h in self.list.iter() {
+            let res = h.hit_surface(r, t_min, closest_so_far);
+            if let Some(HitRecord { t, .. }) = res {
+                closest_so_far = t;
+                result = res;
+            }
+        }
+
+        result
+    }
+
+    fn transmittance(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        self.list
+            .iter()
+            .map(|h| h.transmittance(r, t_min, t_max))
+            .product()
+    }
+
     fn bounding_box(&self, t0: Float, t1: Float) -> Option<Cow<BoundingBox>> {
         let first = self.list.first()?.bounding_box(t0, t1)?;
 
@@ -38,4 +60,32 @@ impl<C> Hitable<C> for HitableList<C> {
 
         r.ok()
     }
+
+    fn pdf_value(&self, o: Pos, v: Dir, time: Float) -> Float {
+        let weight = 1. / self.list.len() as Float;
+        self.list
+            .iter()
+            .map(|h| weight * h.pdf_value(o, v, time))
+            .sum()
+    }
+
+    fn random(&self, o: Pos, time: Float, sampler: &mut dyn Sampler) -> Dir {
+        self.list[sampler.next_index(self.list.len())].random(o, time, sampler)
+    }
+
+    fn sample_surface(
+        &self,
+        time: Float,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(HitRecord<'_>, Float)> {
+        let weight = 1. / self.list.len() as Float;
+        let (rec, pdf) =
+            self.list[sampler.next_index(self.list.len())].sample_surface(time, sampler)?;
+        Some((rec, weight * pdf))
+    }
+
+    fn area_pdf(&self, p: Pos, time: Float) -> Float {
+        let weight = 1. / self.list.len() as Float;
+        self.list.iter().map(|h| weight * h.area_pdf(p, time)).sum()
+    }
 }
@@ -8,6 +8,7 @@ pub struct MovingSphere {
     time1: Float,
     radius: Float,
     mat: Box<Material + Send + Sync>,
+    id: usize,
 }
 
 impl MovingSphere {
@@ -26,6 +27,7 @@ impl MovingSphere {
             time1: t1,
             radius,
             mat,
+            id: crate::id::object(),
         }
     }
 
@@ -43,13 +45,14 @@ impl MovingSphere {
             p,
             normal: (p - self.center(r.time())) / self.radius,
             mat: &*self.mat,
+            object: self.id,
         }
     }
 }
 
-impl<C> Hitable<C> for MovingSphere {
+impl Hitable for MovingSphere {
     #[inline]
-    fn hit(&self, _c: &mut C, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
         let oc = ray.origin() - self.center(ray.time());
         let a = ray.direction().squared_length();
         let b = oc.dot(ray.direction());
@@ -5,6 +5,7 @@ pub struct Sphere {
     center: Pos,
     radius: Float,
     mat: Box<Material + Send + Sync>,
+    id: usize,
 }
 
 impl Sphere {
@@ -13,6 +14,7 @@ impl Sphere {
             center,
             radius,
             mat,
+            id: crate::id::object(),
         }
     }
 
@@ -24,13 +26,23 @@ impl Sphere {
             p,
             normal: (p - self.center) / self.radius,
             mat: &*self.mat,
+            object: self.id,
         }
     }
 }
 
-impl<C> Hitable<C> for Sphere {
+/// A random direction within the cone of half-angle `acos(cos_theta_max)` around the z axis.
+fn random_in_cone(cos_theta_max: Float, sampler: &mut dyn Sampler) -> Dir {
+    let (r1, r2) = sampler.next_2d();
+    let z = 1. + r2 * (cos_theta_max - 1.);
+    let phi = 2. * PI * r1;
+    let sin_theta = (1. - z * z).max(0.).sqrt();
+    dir(phi.cos() * sin_theta, phi.sin() * sin_theta, z)
+}
+
+impl Hitable for Sphere {
     #[inline]
-    fn hit(&self, _c: &mut C, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
         let oc = ray.origin() - self.center;
         let a = ray.direction().squared_length();
         let b = oc.dot(ray.direction());
@@ -57,4 +69,57 @@ impl<C> Hitable<C> for Sphere {
             self.center + half_diag,
         )))
     }
+
+    /// Uniform over the cone of directions under which the sphere is seen from `o`.
+    fn pdf_value(&self, o: Pos, v: Dir, time: Float) -> Float {
+        let distance_squared = (self.center - o).squared_length();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared
+            || self.hit(&Ray::new(o, v, time), 0.001, Float::MAX).is_none()
+        {
+            return 0.;
+        }
+        let cos_theta_max = (1. - radius_squared / distance_squared).sqrt();
+        let solid_angle = 2. * PI * (1. - cos_theta_max);
+        1. / solid_angle
+    }
+
+    fn random(&self, o: Pos, _time: Float, sampler: &mut dyn Sampler) -> Dir {
+        let direction = self.center - o;
+        let distance_squared = direction.squared_length();
+        let cos_theta_max = (1. - self.radius * self.radius / distance_squared)
+            .max(0.)
+            .sqrt();
+        let d = random_in_cone(cos_theta_max, sampler);
+        Onb::from_w(direction).local(d.x(), d.y(), d.z())
+    }
+
+    /// Uniform over the whole surface.
+    fn sample_surface(
+        &self,
+        _time: Float,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(HitRecord<'_>, Float)> {
+        let (r1, r2) = sampler.next_2d();
+        let z = 1. - 2. * r1;
+        let phi = 2. * PI * r2;
+        let r = (1. - z * z).max(0.).sqrt();
+        let normal = dir(r * phi.cos(), r * phi.sin(), z);
+        let rec = HitRecord {
+            t: 0.,
+            p: self.center + self.radius.abs() * normal,
+            normal: self.radius.signum() * normal,
+            mat: &*self.mat,
+            object: self.id,
+        };
+        Some((rec, 1. / (4. * PI * self.radius * self.radius)))
+    }
+
+    fn area_pdf(&self, p: Pos, _time: Float) -> Float {
+        let radius = self.radius.abs();
+        if ((p - self.center).length() - radius).abs() > 1e-3 * radius {
+            return 0.;
+        }
+        1. / (4. * PI * radius * radius)
+    }
 }
@@ -0,0 +1,58 @@
+use super::prelude::*;
+use crate::stats::{self, Category};
+
+/// Counts the hits and misses of the hitable it wraps.
+pub struct StatsRecorder<H> {
+    hitable: H,
+    category: Category,
+}
+
+impl<H> StatsRecorder<H> {
+    pub fn new(category: Category, hitable: H) -> Self {
+        Self { hitable, category }
+    }
+}
+
+impl<H: Hitable> Hitable for StatsRecorder<H> {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        let res = self.hitable.hit(r, t_min, t_max);
+        stats::test(self.category, res.is_some());
+        res
+    }
+
+    fn bounding_box(&self, t0: Float, t1: Float) -> Option<Cow<BoundingBox>> {
+        self.hitable.bounding_box(t0, t1)
+    }
+
+    fn hit_surface(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let res = self.hitable.hit_surface(r, t_min, t_max);
+        stats::test(self.category, res.is_some());
+        res
+    }
+
+    fn transmittance(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        let transmittance = self.hitable.transmittance(r, t_min, t_max);
+        stats::test(self.category, transmittance < 1.);
+        transmittance
+    }
+
+    fn pdf_value(&self, o: Pos, v: Dir, time: Float) -> Float {
+        self.hitable.pdf_value(o, v, time)
+    }
+
+    fn random(&self, o: Pos, time: Float, sampler: &mut dyn Sampler) -> Dir {
+        self.hitable.random(o, time, sampler)
+    }
+
+    fn sample_surface(
+        &self,
+        time: Float,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(HitRecord<'_>, Float)> {
+        self.hitable.sample_surface(time, sampler)
+    }
+
+    fn area_pdf(&self, p: Pos, time: Float) -> Float {
+        self.hitable.area_pdf(p, time)
+    }
+}
@@ -0,0 +1,224 @@
+use super::prelude::*;
+use crate::{animation::Keyframes, hitable::HitableBox, transform::Transform};
+
+/// Steps each stretch between keys is cut into when bounding the motion.
+const BOUNDING_STEPS: usize = 16;
+
+/// A hitable moved, turned and scaled from where it was made by a transform which may change
+/// over time, for motion blur.
+pub struct Transformed {
+    hitable: HitableBox,
+    keys: Keyframes<Transform>,
+}
+
+impl Transformed {
+    pub fn new(hitable: HitableBox, keys: Keyframes<Transform>) -> Self {
+        Self { hitable, keys }
+    }
+
+    /// The transform at the time of `r`, and `r` moved back into the untransformed hitable's
+    /// space by it.
+    fn local(&self, r: &Ray) -> (Transform, Ray) {
+        let transform = self.keys.at(r.time());
+        let local = r.spawn(
+            transform.inverse_point(r.origin()),
+            transform.inverse_vector(r.direction()),
+        );
+        (transform, local)
+    }
+
+    /// Moves a record of the untransformed hitable to where `transform` puts it. Points along
+    /// the ray keep their parameter, as the ray is transformed with them.
+    fn to_world<'a>(transform: &Transform, rec: HitRecord<'a>) -> HitRecord<'a> {
+        HitRecord {
+            p: transform.point(rec.p),
+            normal: transform.normal(rec.normal),
+            ..rec
+        }
+    }
+}
+
+impl Hitable for Transformed {
+    fn hit(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let (transform, local) = self.local(r);
+        self.hitable
+            .hit(&local, t_min, t_max)
+            .map(|rec| Self::to_world(&transform, rec))
+    }
+
+    fn hit_surface(&self, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let (transform, local) = self.local(r);
+        self.hitable
+            .hit_surface(&local, t_min, t_max)
+            .map(|rec| Self::to_world(&transform, rec))
+    }
+
+    fn transmittance(&self, r: &Ray, t_min: Float, t_max: Float) -> Float {
+        self.hitable.transmittance(&self.local(r).1, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: Float, t1: Float) -> Option<Cow<'_, BoundingBox>> {
+        let b = self.hitable.bounding_box(t0, t1)?;
+        // Follow the corners through the interval, in steps between the keys within it
+        let mut stops = vec![t0];
+        stops.extend(self.keys.times().filter(|&t| t0 < t && t < t1));
+        stops.push(t1);
+        let mut times = vec![t0];
+        for stop in stops.windows(2) {
+            let (from, to) = (stop[0], stop[1]);
+            times.extend(
+                (1..=BOUNDING_STEPS)
+                    .map(|i| from + (to - from) * i as Float / BOUNDING_STEPS as Float),
+            );
+        }
+        let paths: Vec<Vec<Pos>> = times
+            .iter()
+            .map(|&t| {
+                let transform = self.keys.at(t);
+                b.corners().iter().map(|&p| transform.point(p)).collect()
+            })
+            .collect();
+        // Between steps, corners stay within about half a step of either end
+        let margin = paths
+            .windows(2)
+            .flat_map(|w| w[0].iter().zip(&w[1]).map(|(&p, &q)| (q - p).length()))
+            .fold(0., Float::max)
+            / 2.;
+        Some(Cow::Owned(
+            BoundingBox::around(paths.into_iter().flatten()).padded(margin),
+        ))
+    }
+
+    fn pdf_value(&self, o: Pos, v: Dir, time: Float) -> Float {
+        // Solid angles are unchanged by rigid motions and uniform scaling
+        let transform = self.keys.at(time);
+        self.hitable.pdf_value(
+            transform.inverse_point(o),
+            transform.inverse_vector(v),
+            time,
+        )
+    }
+
+    fn random(&self, o: Pos, time: Float, sampler: &mut dyn Sampler) -> Dir {
+        let transform = self.keys.at(time);
+        transform.vector(
+            self.hitable
+                .random(transform.inverse_point(o), time, sampler),
+        )
+    }
+
+    fn sample_surface(
+        &self,
+        time: Float,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(HitRecord<'_>, Float)> {
+        let transform = self.keys.at(time);
+        let s = transform.scale;
+        self.hitable
+            .sample_surface(time, sampler)
+            .map(|(rec, pdf)| (Self::to_world(&transform, rec), pdf / (s * s)))
+    }
+
+    fn area_pdf(&self, p: Pos, time: Float) -> Float {
+        let transform = self.keys.at(time);
+        let s = transform.scale;
+        self.hitable.area_pdf(transform.inverse_point(p), time) / (s * s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        animation::Interpolation, hitable, material::lambertian, sampler::Independent,
+        texture::constant_texture,
+    };
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_transformed_sphere() {
+        let sphere: HitableBox = hitable::sphere(
+            Pos::zero(),
+            1.,
+            lambertian(constant_texture(col(0.5, 0.5, 0.5))),
+        );
+        let transform = Transform {
+            scale: 2.,
+            ..Transform::translate(dir(0., 0., -5.))
+        };
+        let moved = Transformed::new(sphere, Keyframes::constant(transform));
+
+        let r = Ray::new(Pos::zero(), dir(0., 0., -1.), 0.);
+        let rec = moved.hit(&r, 0., Float::MAX).unwrap();
+        assert_approx_eq!(3., rec.t);
+        assert_eq!(pos(0., 0., -3.), rec.p);
+        assert_eq!(dir(0., 0., 1.), rec.normal);
+
+        let b = moved.bounding_box(0., 0.).unwrap();
+        assert_eq!(BoundingBox::new(pos(-2., -2., -7.), pos(2., 2., -3.)), *b);
+    }
+
+    #[test]
+    fn test_bounding_box_covers_rotation_between_keys() {
+        let sphere: HitableBox = hitable::sphere(
+            pos(2., 0., 0.),
+            0.5,
+            lambertian(constant_texture(col(0.5, 0.5, 0.5))),
+        );
+        // Half a turn, keyed every quarter turn
+        let keys = Keyframes::new(
+            Interpolation::Linear,
+            vec![
+                (0., Transform::rotate(dir(0., 0., 1.), 0.)),
+                (1., Transform::rotate(dir(0., 0., 1.), 90.)),
+                (2., Transform::rotate(dir(0., 0., 1.), 180.)),
+            ],
+        );
+        let spinning = Transformed::new(sphere, keys.clone());
+        let contains =
+            |b: &BoundingBox, p: Pos| BoundingBox::surrounding(b, &BoundingBox::new(p, p)) == *b;
+        let b = spinning.bounding_box(0., 2.).unwrap();
+        for i in 0..=100 {
+            let time = 2. * i as Float / 100.;
+            // Ray straight down onto the centre of the sphere at that time
+            let centre = keys.at(time).point(pos(2., 0., 0.));
+            let r = Ray::new(centre + dir(0., 0., 5.), dir(0., 0., -1.), time);
+            assert!(spinning.hit(&r, 0., Float::MAX).is_some());
+            for &p in &[centre + dir(0.5, 0.5, 0.5), centre - dir(0.5, 0.5, 0.5)] {
+                assert!(contains(&b, p), "{:?} {:?}", b, p);
+            }
+        }
+        // Only moved forward for a quarter turn in the first half
+        let b = spinning.bounding_box(0., 1.).unwrap();
+        assert!(!contains(&b, pos(-2., 0., 0.)));
+    }
+
+    #[test]
+    fn test_sampled_as_a_light_where_it_is_at_the_time() {
+        let sphere: HitableBox = hitable::sphere(
+            Pos::zero(),
+            1.,
+            lambertian(constant_texture(col(0.5, 0.5, 0.5))),
+        );
+        let keys = Keyframes::new(
+            Interpolation::Linear,
+            vec![
+                (0., Transform::translate(dir(0., 0., -5.))),
+                (1., Transform::translate(dir(5., 0., -5.))),
+            ],
+        );
+        let moving = Transformed::new(sphere, keys);
+        let mut sampler = Independent::new();
+        for &(time, centre) in &[(0., pos(0., 0., -5.)), (1., pos(5., 0., -5.))] {
+            let o = Pos::zero();
+            assert!(moving.pdf_value(o, centre - o, time) > 0.);
+            let d = moving.random(o, time, &mut sampler);
+            assert!(moving.hit(&Ray::new(o, d, time), 0., Float::MAX).is_some());
+            let (rec, pdf) = moving.sample_surface(time, &mut sampler).unwrap();
+            assert_approx_eq!(1., (rec.p - centre).length());
+            assert_approx_eq!(pdf, moving.area_pdf(rec.p, time));
+        }
+        // Not where it started
+        assert_eq!(0., moving.pdf_value(Pos::zero(), dir(0., 0., -1.), 1.));
+    }
+}
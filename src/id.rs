@@ -0,0 +1,47 @@
+//! Identifiers for the object and material ID passes. They number primitives and materials in
+//! the order the scene makes them, so the same scene gets the same colours in every render and
+//! every frame.
+
+use std::cell::Cell;
+
+thread_local! {
+    static OBJECTS: Cell<usize> = const { Cell::new(0) };
+    static MATERIALS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn next(counter: &'static std::thread::LocalKey<Cell<usize>>) -> usize {
+    counter.with(|c| {
+        let id = c.get();
+        c.set(id + 1);
+        id
+    })
+}
+
+/// The identifier of the next primitive made on this thread.
+pub fn object() -> usize {
+    next(&OBJECTS)
+}
+
+/// The identifier of the next material made on this thread.
+pub fn material() -> usize {
+    next(&MATERIALS)
+}
+
+/// Starts numbering again from zero, before making a scene.
+pub fn reset() {
+    OBJECTS.with(|c| c.set(0));
+    MATERIALS.with(|c| c.set(0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbering_restarts_for_each_scene() {
+        reset();
+        assert_eq!((0, 1, 0), (object(), object(), material()));
+        reset();
+        assert_eq!((0, 0), (object(), material()));
+    }
+}
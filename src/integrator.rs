@@ -0,0 +1,595 @@
+mod ambient_occlusion;
+mod bidirectional;
+mod debug;
+mod iterative;
+mod photon_map;
+mod recursive;
+
+use crate::{aov::Aovs, hitable::HitableBox, prelude::*, scene::Scene, spectrum, Settings};
+
+pub use debug::DebugChannel;
+
+/// Radiance coming back along a camera ray, split by how many times it was scattered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lighting {
+    /// Light seen directly, or scattered once towards the camera.
+    pub direct: Col,
+    /// Light scattered more than once.
+    pub indirect: Col,
+}
+
+impl Lighting {
+    pub fn zero() -> Self {
+        Self {
+            direct: Col::zero(),
+            indirect: Col::zero(),
+        }
+    }
+
+    /// Light which was scattered `bounces` times before reaching the camera.
+    pub fn add(&mut self, bounces: usize, radiance: Col) {
+        if bounces <= 1 {
+            self.direct += radiance;
+        } else {
+            self.indirect += radiance;
+        }
+    }
+
+    pub fn total(&self) -> Col {
+        self.direct + self.indirect
+    }
+}
+
+impl std::ops::AddAssign for Lighting {
+    fn add_assign(&mut self, o: Self) {
+        self.direct += o.direct;
+        self.indirect += o.indirect;
+    }
+}
+
+impl std::ops::Mul<Col> for Lighting {
+    type Output = Self;
+
+    fn mul(self, c: Col) -> Self {
+        Self {
+            direct: self.direct * c,
+            indirect: self.indirect * c,
+        }
+    }
+}
+
+/// A light transport algorithm: computes the radiance coming back along a camera ray.
+pub trait Integrator: Send + Sync {
+    /// Prepares anything which depends on the whole scene, before any camera ray is traced.
+    fn preprocess(&mut self, _scene: &Scene) {}
+
+    /// The radiance coming back along camera ray `r`, as the `lighting` of the output variables
+    /// describing what it first hit.
+    fn lighting(&self, scene: &Scene, r: &Ray, sampler: &mut dyn Sampler) -> Aovs;
+}
+
+pub type IntegratorBox = Box<dyn Integrator>;
+
+#[derive(Clone, Copy, Debug)]
+pub enum IntegratorKind {
+    /// Recursive path tracing, stopping after `Settings::depth` bounces.
+    Recursive,
+    /// Iterative path tracing, ending paths by Russian roulette after
+    /// `Settings::roulette_depth` bounces.
+    Iterative,
+    /// Bidirectional path tracing, with subpaths of up to `Settings::depth` bounces.
+    Bidirectional,
+    /// Iterative path tracing, with caustics estimated from `photons` photons shot from the lights
+    /// before rendering, gathered within `radius` of each diffuse hit.
+    PhotonMapping { photons: usize, radius: Float },
+    /// Shows a feature of the first hit rather than a rendered image.
+    Debug(DebugChannel),
+    /// White where the first hit is unoccluded within `distance`, black otherwise.
+    AmbientOcclusion { distance: Float },
+}
+
+pub fn integrator(settings: &Settings) -> IntegratorBox {
+    match settings.integrator {
+        IntegratorKind::Recursive => Box::new(recursive::RecursivePathTracer::new(settings.depth)),
+        IntegratorKind::Iterative => {
+            Box::new(iterative::IterativePathTracer::new(settings.roulette_depth))
+        }
+        IntegratorKind::Bidirectional => {
+            Box::new(bidirectional::Bidirectional::new(settings.depth))
+        }
+        IntegratorKind::PhotonMapping { photons, radius } => Box::new(
+            photon_map::PhotonMapper::new(photons, radius, settings.depth, settings.roulette_depth),
+        ),
+        IntegratorKind::Debug(channel) => Box::new(debug::Debug::new(channel)),
+        IntegratorKind::AmbientOcclusion { distance } => {
+            Box::new(ambient_occlusion::AmbientOcclusion::new(distance))
+        }
+    }
+}
+
+/// Radiance for a ray escaping the world.
+fn background(scene: &Scene, r: &Ray) -> Col {
+    spectrum::project((scene.background)(r), r.wavelength())
+}
+
+/// Combines two sampling strategies by the power heuristic: the weight of a sample drawn with
+/// density `pdf` when the other strategy would have drawn it with density `other_pdf`.
+#[inline]
+fn power_heuristic(pdf: Float, other_pdf: Float) -> Float {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    a / (a + b)
+}
+
+/// Density with which sampling a light picks direction `v` from `o` at `time`: lights are picked
+/// uniformly, then sampled by their own strategy.
+fn light_pdf(lights: &[HitableBox], o: Pos, v: Dir, time: Float) -> Float {
+    if lights.is_empty() {
+        return 0.;
+    }
+    let weight = 1. / lights.len() as Float;
+    lights
+        .iter()
+        .map(|l| weight * l.pdf_value(o, v, time))
+        .sum()
+}
+
+/// Radiance emitted at `rec` back along `r`. When `r` was picked by sampling a BSDF with density
+/// `bsdf_pdf`, it is weighted against the chance of having found the same light by sampling it
+/// directly instead.
+fn emitted(scene: &Scene, r: &Ray, rec: &HitRecord, bsdf_pdf: Option<Float>) -> Col {
+    let emitted = rec.mat.emitted(r, rec);
+    match bsdf_pdf {
+        Some(pdf) if emitted != Col::zero() => {
+            let light_pdf = light_pdf(&scene.lights, r.origin(), r.direction(), r.time());
+            emitted * power_heuristic(pdf, light_pdf)
+        }
+        _ => emitted,
+    }
+}
+
+/// Light reaching `rec` straight from a randomly chosen light, weighted against the chance of
+/// finding the same light by sampling the BSDF. Only the non-specular part of the BSDF counts,
+/// so this is added at every hit, whichever lobe goes on to scatter the path.
+fn sample_light(scene: &Scene, r: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Col {
+    let lights = &scene.lights;
+    if lights.is_empty() {
+        return Col::zero();
+    }
+
+    let light = &lights[sampler.next_index(lights.len())];
+    let direction = light.random(rec.p, r.time(), sampler);
+    // Specular materials can't be lit this way, and are cheap to rule out
+    let f = rec.mat.eval(r, rec, direction);
+    if f == Col::zero() {
+        return Col::zero();
+    }
+    let pdf = light_pdf(lights, rec.p, direction, r.time());
+    if pdf <= 0. {
+        return Col::zero();
+    }
+
+    let shadow = r.spawn(rec.p, direction);
+    match scene.shadow(&shadow, 0.001, Float::MAX) {
+        (Some(light_rec), transmittance) => {
+            let emitted = light_rec.mat.emitted(&shadow, &light_rec);
+            let weight = power_heuristic(pdf, rec.mat.pdf(r, rec, direction));
+            f * emitted * shadow.transmittance(light_rec.t) * (transmittance * weight / pdf)
+        }
+        (None, _) => Col::zero(),
+    }
+}
+
+fn random_cosine_direction(normal: Dir, sampler: &mut dyn Sampler) -> Dir {
+    let (r1, r2) = sampler.next_2d();
+    let phi = 2. * PI * r1;
+    let sqrt_r2 = r2.sqrt();
+    Onb::from_w(normal).local(phi.cos() * sqrt_r2, phi.sin() * sqrt_r2, (1. - r2).sqrt())
+}
+
+/// Light leaving a random point of a random light, in a cosine-distributed direction.
+struct Emission<'a> {
+    rec: HitRecord<'a>,
+    /// Has the time and wavelength of the ray `emit` was given.
+    ray: Ray,
+    /// Emitted radiance along `ray`.
+    radiance: Col,
+    /// Density per unit area of having started from this point, over all the lights.
+    pdf_origin: Float,
+}
+
+fn emit<'a>(scene: &'a Scene, r: &Ray, sampler: &mut dyn Sampler) -> Option<Emission<'a>> {
+    let lights = &scene.lights;
+    if lights.is_empty() {
+        return None;
+    }
+
+    let light = &lights[sampler.next_index(lights.len())];
+    let (rec, area_pdf) = light.sample_surface(r.time(), sampler)?;
+    let direction = random_cosine_direction(rec.normal, sampler);
+    let radiance = rec
+        .mat
+        .emitted(&r.spawn(rec.p + direction, -direction), &rec);
+    Some(Emission {
+        ray: r.spawn(rec.p, direction),
+        radiance,
+        pdf_origin: area_pdf / lights.len() as Float,
+        rec,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::{Perspective, Placement},
+        hitable,
+        material::{
+            absorbing_dielectric, coated, diffuse_light, lambertian, metal, mix, MaterialBox,
+        },
+        sampler::Independent,
+        texture::constant_texture,
+    };
+
+    fn white(_r: &Ray) -> Col {
+        col(1., 1., 1.)
+    }
+
+    fn scene(world: HitableBox) -> Scene {
+        Scene {
+            camera: Box::new(Perspective::new(
+                Placement {
+                    look_from: Pos::zero(),
+                    look_at: pos(0., 0., -1.),
+                    up: dir(0., 1., 0.),
+                },
+                90.,
+                2.,
+                0.,
+                1.,
+                (0., 0.),
+            )),
+            world,
+            lights: vec![],
+            background: white,
+        }
+    }
+
+    fn furnace() -> Scene {
+        scene(hitable::sphere(
+            pos(0., 0., -2.),
+            1.,
+            lambertian(constant_texture(col(1., 1., 1.))),
+        ))
+    }
+
+    fn assert_col_eq(expected: Col, actual: Col) {
+        use assert_approx_eq::assert_approx_eq;
+        assert_approx_eq!(expected.r(), actual.r());
+        assert_approx_eq!(expected.g(), actual.g());
+        assert_approx_eq!(expected.b(), actual.b());
+    }
+
+    /// A white convex object under a uniformly white sky is exactly as bright as the sky.
+    #[test]
+    fn test_path_tracers_pass_white_furnace() {
+        let scene = furnace();
+        let r = Ray::new(Pos::zero(), dir(0., 0., -1.), 0.);
+        let mut integrators: Vec<IntegratorBox> = vec![
+            Box::new(recursive::RecursivePathTracer::new(10)),
+            Box::new(iterative::IterativePathTracer::new(3)),
+            Box::new(bidirectional::Bidirectional::new(10)),
+            Box::new(photon_map::PhotonMapper::new(1000, 0.1, 10, 3)),
+        ];
+        for integrator in integrators.iter_mut() {
+            integrator.preprocess(&scene);
+        }
+        for integrator in integrators {
+            for _ in 0..100 {
+                let c = integrator
+                    .lighting(&scene, &r, &mut Independent::new())
+                    .lighting
+                    .total();
+                assert_col_eq(col(1., 1., 1.), c);
+            }
+        }
+    }
+
+    /// The output variables come from the integrator's own camera ray, which it doesn't trace
+    /// again.
+    #[test]
+    fn test_integrators_describe_first_hit() {
+        let scene = furnace();
+        let mut integrators: Vec<IntegratorBox> = vec![
+            Box::new(recursive::RecursivePathTracer::new(10)),
+            Box::new(iterative::IterativePathTracer::new(3)),
+            Box::new(bidirectional::Bidirectional::new(10)),
+            Box::new(photon_map::PhotonMapper::new(1000, 0.1, 10, 3)),
+            Box::new(debug::Debug::new(DebugChannel::Normals)),
+            Box::new(ambient_occlusion::AmbientOcclusion::new(1.)),
+        ];
+        for integrator in integrators.iter_mut() {
+            integrator.preprocess(&scene);
+        }
+        for integrator in integrators {
+            let r = Ray::new(Pos::zero(), dir(0., 0., -1.), 0.);
+            let aovs = integrator.lighting(&scene, &r, &mut Independent::new());
+            assert_eq!(1, aovs.hits);
+            assert_eq!(1., aovs.depth);
+            assert_eq!(dir(0., 0., 1.), aovs.normal);
+            assert_eq!(col(1., 1., 1.), aovs.albedo);
+            assert!(aovs.object.is_some());
+
+            let r = Ray::new(Pos::zero(), dir(0., 1., 0.), 0.);
+            let aovs = integrator.lighting(&scene, &r, &mut Independent::new());
+            assert_eq!(0, aovs.hits);
+            assert_eq!(col(1., 1., 1.), aovs.albedo);
+        }
+    }
+
+    /// Light leaving a convex object can't hit it again, so it has all been scattered once.
+    #[test]
+    fn test_light_off_a_convex_object_is_direct() {
+        let scene = furnace();
+        let r = Ray::new(Pos::zero(), dir(0., 0., -1.), 0.);
+        let mut integrators: Vec<IntegratorBox> = vec![
+            Box::new(recursive::RecursivePathTracer::new(10)),
+            Box::new(iterative::IterativePathTracer::new(3)),
+            Box::new(bidirectional::Bidirectional::new(10)),
+            Box::new(photon_map::PhotonMapper::new(1000, 0.1, 10, 3)),
+        ];
+        for integrator in integrators.iter_mut() {
+            integrator.preprocess(&scene);
+        }
+        for integrator in integrators {
+            let lighting = integrator
+                .lighting(&scene, &r, &mut Independent::new())
+                .lighting;
+            assert_col_eq(col(1., 1., 1.), lighting.direct);
+            assert_eq!(Col::zero(), lighting.indirect);
+        }
+    }
+
+    #[test]
+    fn test_path_tracers_see_lights() {
+        let scene = scene(hitable::sphere(
+            pos(0., 0., -2.),
+            1.,
+            diffuse_light(constant_texture(col(4., 2., 1.))),
+        ));
+        let r = Ray::new(Pos::zero(), dir(0., 0., -1.), 0.);
+        let recursive = recursive::RecursivePathTracer::new(10);
+        let c = recursive
+            .lighting(&scene, &r, &mut Independent::new())
+            .lighting
+            .total();
+        assert_col_eq(col(4., 2., 1.), c);
+        let iterative = iterative::IterativePathTracer::new(3);
+        let c = iterative
+            .lighting(&scene, &r, &mut Independent::new())
+            .lighting
+            .total();
+        assert_col_eq(col(4., 2., 1.), c);
+        let bidirectional = bidirectional::Bidirectional::new(10);
+        let c = bidirectional
+            .lighting(&scene, &r, &mut Independent::new())
+            .lighting
+            .total();
+        assert_col_eq(col(4., 2., 1.), c);
+        let mut photon_mapper = photon_map::PhotonMapper::new(1000, 0.1, 10, 3);
+        photon_mapper.preprocess(&scene);
+        let c = photon_mapper
+            .lighting(&scene, &r, &mut Independent::new())
+            .lighting
+            .total();
+        assert_col_eq(col(4., 2., 1.), c);
+    }
+
+    /// Light from inside coloured glass is absorbed on its way to the surface, even though it
+    /// doesn't leave through the same glass it entered.
+    #[test]
+    fn test_light_inside_coloured_glass_is_tinted() {
+        let scene = scene(hitable::hitable_list(vec![
+            hitable::sphere(
+                pos(0., 0., -3.),
+                2.,
+                absorbing_dielectric(1., col(0.5, 0.8, 1.), 1.),
+            ),
+            hitable::sphere(
+                pos(0., 0., -3.),
+                1.,
+                diffuse_light(constant_texture(col(1., 1., 1.))),
+            ),
+        ]));
+        // Straight through the glass at normal incidence, which reflects nothing
+        let r = Ray::new(Pos::zero(), dir(0., 0., -1.), 0.);
+        let integrators: Vec<IntegratorBox> = vec![
+            Box::new(recursive::RecursivePathTracer::new(10)),
+            Box::new(iterative::IterativePathTracer::new(3)),
+        ];
+        for integrator in integrators {
+            let c = integrator
+                .lighting(&scene, &r, &mut Independent::new())
+                .lighting
+                .total();
+            assert_col_eq(col(0.5, 0.8, 1.), c);
+        }
+    }
+
+    /// Average radiance `integrator` sees at a sphere of `mat` lit from above by a spherical
+    /// light. Unless `sample_lights`, the light is only found by BSDF sampling.
+    fn lit_by(integrator: &dyn Integrator, mat: MaterialBox, sample_lights: bool) -> Col {
+        let light = || {
+            hitable::sphere(
+                pos(0., 2., 0.),
+                1.,
+                diffuse_light(constant_texture(col(4., 4., 4.))),
+            )
+        };
+        let mut scene = scene(hitable::hitable_list(vec![
+            hitable::sphere(pos(0., 0., -3.), 1., mat),
+            light(),
+        ]));
+        scene.background = |_| Col::zero();
+        if sample_lights {
+            scene.lights.push(light());
+        }
+
+        let r = Ray::new(Pos::zero(), dir(0., 0., -1.), 0.);
+        let mut sampler = Independent::new();
+        let n = 100_000;
+        let total = (0..n).fold(Col::zero(), |total, _| {
+            total
+                + integrator
+                    .lighting(&scene, &r, &mut sampler)
+                    .lighting
+                    .total()
+        });
+        total / n as Float
+    }
+
+    fn lit(mat: MaterialBox, sample_lights: bool) -> Col {
+        lit_by(&iterative::IterativePathTracer::new(3), mat, sample_lights)
+    }
+
+    /// Asserts that `actual` is within a fraction `tolerance` of `expected`.
+    fn assert_col_near(expected: Col, actual: Col, tolerance: Float) {
+        for i in 0..3 {
+            let (e, a) = (expected[i], actual[i]);
+            assert!(
+                (e - a).abs() < tolerance * e,
+                "expected {}, got {}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    /// Materials picking between a specular and a diffuse lobe get all their direct light,
+    /// whichever lobe they pick.
+    #[test]
+    fn test_sampling_lights_through_layered_materials() {
+        let white = || lambertian(constant_texture(col(1., 1., 1.)));
+        let half = constant_texture(col(0.5, 0.5, 0.5));
+        // Half the time, a mirror which reflects nothing
+        let grey = lit(lambertian(half.clone()), true);
+        let mixed = lit(mix(white(), metal(Col::zero(), 0.), half), true);
+        assert_col_near(grey, mixed, 0.05);
+
+        let coated = || coated(1.5, white());
+        assert_col_near(lit(coated(), false), lit(coated(), true), 0.05);
+    }
+
+    /// Connecting camera and light subpaths finds the same light as path tracing.
+    #[test]
+    fn test_bidirectional_converges_to_path_tracing() {
+        let white = || lambertian(constant_texture(col(1., 1., 1.)));
+        let bidirectional = bidirectional::Bidirectional::new(10);
+        assert_col_near(
+            lit(white(), true),
+            lit_by(&bidirectional, white(), true),
+            0.05,
+        );
+        let coated = || coated(1.5, white());
+        assert_col_near(
+            lit(coated(), true),
+            lit_by(&bidirectional, coated(), true),
+            0.05,
+        );
+    }
+
+    /// Light reaching a diffuse floor off a mirror is a caustic, which the photon map holds
+    /// rather than the path tracer. It should match what the path tracer finds with the mirror
+    /// taken out, and the mirror image of everything in front of it put in.
+    #[test]
+    fn test_photon_map_finds_caustics_off_a_mirror() {
+        let floor = || {
+            hitable::sphere(
+                pos(0., -1001., 0.),
+                1000.,
+                lambertian(constant_texture(col(1., 1., 1.))),
+            )
+        };
+        let light = |z| {
+            hitable::sphere(
+                pos(0., 1., z),
+                0.25,
+                diffuse_light(constant_texture(col(40., 40., 40.))),
+            )
+        };
+        // Hides the light from the floor where the camera looks, but not its reflection
+        let shade = |z| {
+            hitable::sphere(
+                pos(0., 0., z),
+                0.2,
+                lambertian(constant_texture(Col::zero())),
+            )
+        };
+
+        let mut mirrored = scene(hitable::hitable_list(vec![
+            floor(),
+            hitable::sphere(pos(0., 0., -1005.), 1000., metal(col(1., 1., 1.), 0.)),
+            shade(-3.),
+            light(-4.),
+        ]));
+        mirrored.background = |_| Col::zero();
+        mirrored.lights.push(light(-4.));
+        let mut unfolded = scene(hitable::hitable_list(vec![
+            floor(),
+            shade(-3.),
+            shade(-7.),
+            light(-4.),
+            light(-6.),
+        ]));
+        unfolded.background = |_| Col::zero();
+        unfolded.lights = vec![light(-4.), light(-6.)];
+
+        // Few of the photons land where the camera looks, so it takes many to keep the density
+        // estimate within a few percent
+        let mut photon_mapper = photon_map::PhotonMapper::new(1_000_000, 0.5, 10, 3);
+        photon_mapper.preprocess(&mirrored);
+        let path_tracer = iterative::IterativePathTracer::new(3);
+        // Down at the floor in front of the shade
+        let r = Ray::new(Pos::zero(), dir(0., -1., -2.), 0.);
+        let mut sampler = Independent::new();
+        let n = 10_000;
+        let (mut expected, mut actual) = (Col::zero(), Col::zero());
+        for _ in 0..n {
+            expected += path_tracer
+                .lighting(&unfolded, &r, &mut sampler)
+                .lighting
+                .total();
+            actual += photon_mapper
+                .lighting(&mirrored, &r, &mut sampler)
+                .lighting
+                .total();
+        }
+        // Nearly all the light is caustic, so leaving it out or counting it twice would be far off
+        assert_col_near(expected / n as Float, actual / n as Float, 0.15);
+    }
+
+    #[test]
+    fn test_debug_normals() {
+        let scene = furnace();
+        let r = Ray::new(Pos::zero(), dir(0., 0., -1.), 0.);
+        let debug = debug::Debug::new(DebugChannel::Normals);
+        let c = debug
+            .lighting(&scene, &r, &mut Independent::new())
+            .lighting
+            .total();
+        assert_col_eq(col(0.5, 0.5, 1.), c);
+    }
+
+    #[test]
+    fn test_ambient_occlusion_of_convex_object_is_white() {
+        let scene = furnace();
+        let r = Ray::new(Pos::zero(), dir(0., 0., -1.), 0.);
+        let ao = ambient_occlusion::AmbientOcclusion::new(10.);
+        for _ in 0..100 {
+            let c = ao
+                .lighting(&scene, &r, &mut Independent::new())
+                .lighting
+                .total();
+            assert_col_eq(col(1., 1., 1.), c);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use super::{Integrator, Lighting};
+use crate::{
+    aov::{self, Aovs},
+    prelude::*,
+    scene::Scene,
+    stats::RayKind,
+};
+
+/// Ambient occlusion: casts one cosine-distributed ray from the first hit, and returns white if
+/// it gets further than `distance` without hitting anything. Averaged over many samples this
+/// shows how much each point is hidden by its surroundings.
+pub struct AmbientOcclusion {
+    distance: Float,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: Float) -> Self {
+        Self { distance }
+    }
+}
+
+fn random_cosine_direction(sampler: &mut dyn Sampler) -> Dir {
+    let (r1, r2) = sampler.next_2d();
+    let phi = 2. * PI * r1;
+    let sqrt_r2 = r2.sqrt();
+    dir(phi.cos() * sqrt_r2, phi.sin() * sqrt_r2, (1. - r2).sqrt())
+}
+
+fn random_unit_vector(sampler: &mut dyn Sampler) -> Dir {
+    let (u, v) = sampler.next_2d();
+    let z = 1. - 2. * u;
+    let phi = 2. * PI * v;
+    let r = (1. - z * z).max(0.).sqrt();
+    dir(r * phi.cos(), r * phi.sin(), z)
+}
+
+impl AmbientOcclusion {
+    fn shade(&self, scene: &Scene, r: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Col {
+        let direction = if rec.normal == Dir::zero() {
+            // Inside a medium, look all around
+            random_unit_vector(sampler)
+        } else {
+            // Look from the side of the surface the ray came from
+            let normal = if r.direction().dot(rec.normal) > 0. {
+                -rec.normal
+            } else {
+                rec.normal
+            };
+            let d = random_cosine_direction(sampler);
+            Onb::from_w(normal).local(d.x(), d.y(), d.z())
+        };
+        let t_max = self.distance / direction.length();
+        let occlusion = r.spawn(rec.p, direction);
+        match scene.hit(RayKind::Shadow, &occlusion, 0.001, t_max) {
+            Some(_) => Col::zero(),
+            None => col(1., 1., 1.),
+        }
+    }
+}
+
+/// Occlusion is a property of the first hit, so it counts as direct light.
+impl Integrator for AmbientOcclusion {
+    fn lighting(&self, scene: &Scene, r: &Ray, sampler: &mut dyn Sampler) -> Aovs {
+        let white = Lighting {
+            direct: col(1., 1., 1.),
+            indirect: Col::zero(),
+        };
+        let rec = match scene.hit(RayKind::Primary, r, 0.001, Float::MAX) {
+            Some(rec) => rec,
+            None => {
+                return Aovs {
+                    lighting: white,
+                    ..aov::missed(scene, r)
+                }
+            }
+        };
+        let direct = self.shade(scene, r, &rec, sampler);
+        let scatter = rec.mat.scatter(r, &rec, sampler);
+        Aovs {
+            lighting: white * direct,
+            ..aov::first_hit(r, &rec, scatter.as_ref())
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use super::{background, emit, Emission, Integrator, Lighting};
+use crate::{
+    aov::{self, Aovs},
+    prelude::*,
+    scene::Scene,
+    stats::RayKind,
+};
+
+/// Bidirectional path tracing: for each camera ray, traces one subpath from the camera and one
+/// from a random point on a light, then connects every vertex of one to every vertex of the
+/// other. Each complete path could have been found by several of these strategies, so their
+/// contributions are weighted by multiple importance sampling. Light bouncing off several
+/// surfaces before reaching a small light is much easier to find from both ends at once.
+///
+/// The strategy connecting light subpaths straight to the camera (t = 1) is left out: its paths
+/// land on arbitrary pixels, which the renderer, estimating one pixel at a time, has no way to
+/// add to. The MIS weights only count the strategies which are used, so the estimate stays
+/// unbiased, but caustics seen directly, which only that strategy finds easily, stay as noisy
+/// as with path tracing. `IntegratorKind::PhotonMapping` is the one for those.
+pub struct Bidirectional {
+    depth: usize,
+}
+
+impl Bidirectional {
+    pub fn new(depth: usize) -> Self {
+        Self { depth }
+    }
+}
+
+struct Vertex<'a> {
+    p: Pos,
+    normal: Dir,
+    /// The ray which reached this vertex.
+    r_in: Ray,
+    /// `None` for the camera.
+    rec: Option<HitRecord<'a>>,
+    /// Throughput of the subpath up to this vertex, not including its own scattering. For light
+    /// subpaths it includes the emitted radiance.
+    beta: Col,
+    /// Whether the subpath went on from this vertex by a specular bounce.
+    delta: bool,
+    /// Density per unit area of sampling this vertex from the previous one on its subpath.
+    pdf_fwd: Float,
+    /// Density per unit area of sampling this vertex from the next one, as if the path had been
+    /// traced in the opposite direction.
+    pdf_rev: Float,
+}
+
+fn abs_cos(normal: Dir, d: Dir) -> Float {
+    if normal == Dir::zero() {
+        // In a medium, there is no surface to be foreshortened
+        1.
+    } else {
+        normal.dot(d.unit_vector()).abs()
+    }
+}
+
+impl<'a> Vertex<'a> {
+    fn camera(r: &Ray) -> Self {
+        Self {
+            p: r.origin(),
+            normal: Dir::zero(),
+            r_in: *r,
+            rec: None,
+            beta: col(1., 1., 1.),
+            delta: false,
+            pdf_fwd: 0.,
+            pdf_rev: 0.,
+        }
+    }
+
+    /// Converts a density per unit solid angle of going from this vertex towards `next` into a
+    /// density per unit area at `next`.
+    fn to_area(&self, pdf: Float, next: &Vertex) -> Float {
+        let d = next.p - self.p;
+        pdf * abs_cos(next.normal, d) / d.squared_length()
+    }
+
+    /// Density per unit area with which a path reaching this vertex from `prev` continues to
+    /// `next`. Without `prev`, this vertex is on a light and emits towards `next`.
+    fn pdf(&self, prev: Option<&Vertex>, next: &Vertex) -> Float {
+        let rec = match &self.rec {
+            Some(rec) => rec,
+            None => return 0.,
+        };
+        let towards = next.p - self.p;
+        let pdf = match prev {
+            Some(prev) => rec
+                .mat
+                .pdf(&self.r_in.spawn(prev.p, self.p - prev.p), rec, towards),
+            None => rec.normal.dot(towards.unit_vector()).max(0.) / PI,
+        };
+        self.to_area(pdf, next)
+    }
+}
+
+/// Density per unit area with which light subpaths start at `p` at `time`.
+fn light_origin_pdf(scene: &Scene, p: Pos, time: Float) -> Float {
+    let lights = &scene.lights;
+    if lights.is_empty() {
+        return 0.;
+    }
+    let weight = 1. / lights.len() as Float;
+    lights.iter().map(|l| weight * l.area_pdf(p, time)).sum()
+}
+
+/// The ray leaving the last vertex of a subpath, with the throughput up to it and the solid angle
+/// density it was sampled with (zero if specular).
+struct Step {
+    ray: Ray,
+    beta: Col,
+    pdf: Float,
+}
+
+impl Bidirectional {
+    /// Extends `path` by following `step` from its last vertex, the first ray counting as `kind`.
+    /// Returns the ray and throughput if the path escapes. What the first ray hits is described
+    /// in `first_hit`, if given.
+    fn walk<'a>(
+        &self,
+        scene: &'a Scene,
+        mut kind: RayKind,
+        step: Step,
+        path: &mut Vec<Vertex<'a>>,
+        sampler: &mut dyn Sampler,
+        mut first_hit: Option<&mut Aovs>,
+    ) -> Option<(Ray, Col)> {
+        let Step {
+            mut ray,
+            mut beta,
+            mut pdf,
+        } = step;
+        while path.len() <= self.depth {
+            let rec = match scene.hit(kind, &ray, 0.001, Float::MAX) {
+                Some(rec) => rec,
+                None => {
+                    if let Some(first_hit) = first_hit {
+                        *first_hit = aov::missed(scene, &ray);
+                    }
+                    return Some((ray, beta));
+                }
+            };
+            beta *= ray.transmittance(rec.t);
+            kind = RayKind::Secondary;
+            let scatter = rec.mat.scatter(&ray, &rec, sampler);
+            if let Some(first_hit) = first_hit.take() {
+                *first_hit = aov::first_hit(&ray, &rec, scatter.as_ref());
+            }
+            let mut vertex = Vertex {
+                p: rec.p,
+                normal: rec.normal,
+                r_in: ray,
+                rec: Some(rec),
+                beta,
+                delta: false,
+                pdf_fwd: 0.,
+                pdf_rev: 0.,
+            };
+            vertex.pdf_fwd = path.last().unwrap().to_area(pdf, &vertex);
+            path.push(vertex);
+
+            let Scatter {
+                scattered,
+                attenuation,
+                specular,
+            } = match scatter {
+                Some(scatter) => scatter,
+                None => break,
+            };
+
+            let n = path.len();
+            let pdf_rev = if specular {
+                path[n - 1].delta = true;
+                pdf = 0.;
+                0.
+            } else {
+                let rec = path[n - 1].rec.as_ref().unwrap();
+                pdf = rec.mat.pdf(&ray, rec, scattered.direction());
+                let reversed = scattered.spawn(rec.p, -scattered.direction());
+                let pdf_rev = rec.mat.pdf(&reversed, rec, -ray.direction());
+                path[n - 1].to_area(pdf_rev, &path[n - 2])
+            };
+            path[n - 2].pdf_rev = pdf_rev;
+
+            beta *= attenuation;
+            if beta == Col::zero() {
+                break;
+            }
+            ray = scattered;
+        }
+        None
+    }
+
+    /// Starts a subpath at a random point of a random light, at the time and wavelength of `r`.
+    fn light_subpath<'a>(
+        &self,
+        scene: &'a Scene,
+        r: &Ray,
+        sampler: &mut dyn Sampler,
+    ) -> Vec<Vertex<'a>> {
+        let mut path = vec![];
+        if self.depth == 0 {
+            return path;
+        }
+        let Emission {
+            rec,
+            ray,
+            radiance,
+            pdf_origin,
+        } = match emit(scene, r, sampler) {
+            Some(emission) => emission,
+            None => return path,
+        };
+
+        let origin = Vertex {
+            p: rec.p,
+            normal: rec.normal,
+            r_in: r.spawn(rec.p + rec.normal, -rec.normal),
+            rec: Some(rec),
+            beta: radiance / pdf_origin,
+            delta: false,
+            pdf_fwd: pdf_origin,
+            pdf_rev: 0.,
+        };
+        // Cosine-weighted emission: the cosine and the density cancel out, but for a factor PI
+        let beta = origin.beta * PI;
+        let cosine = origin.normal.dot(ray.direction().unit_vector());
+        path.push(origin);
+        let step = Step {
+            ray,
+            beta,
+            pdf: cosine / PI,
+        };
+        self.walk(scene, RayKind::Secondary, step, &mut path, sampler, None);
+        path
+    }
+
+    /// Weight of the strategy using `s` light vertices and `t` camera vertices, relative to the
+    /// other strategies which could have found the same path, by the power heuristic.
+    fn mis_weight(
+        &self,
+        scene: &Scene,
+        camera: &[Vertex],
+        light: &[Vertex],
+        s: usize,
+        t: usize,
+    ) -> Float {
+        if s + t == 2 {
+            return 1.;
+        }
+
+        // Reverse densities of the vertices around the connection only exist for this strategy
+        let pt = &camera[t - 1];
+        let pt_minus = &camera[t - 2];
+        let qs = if s > 0 { Some(&light[s - 1]) } else { None };
+        let qs_minus = if s > 1 { Some(&light[s - 2]) } else { None };
+        let pt_rev = match qs {
+            Some(qs) => qs.pdf(qs_minus, pt),
+            None => light_origin_pdf(scene, pt.p, pt.r_in.time()),
+        };
+        if pt_rev == 0. && s == 0 {
+            // An emitter the light subpaths never start from
+            return 1.;
+        }
+        let pt_minus_rev = pt.pdf(qs, pt_minus);
+        let qs_rev = qs.map_or(0., |qs| pt.pdf(Some(pt_minus), qs));
+        let qs_minus_rev = match (qs, qs_minus) {
+            (Some(qs), Some(qs_minus)) => qs.pdf(Some(pt), qs_minus),
+            _ => 0.,
+        };
+
+        // Specular vertices have zero densities; they cancel out as long as no strategy tries to
+        // connect through them. The two vertices being connected scatter towards each other,
+        // whichever lobe their subpaths happened to go on by.
+        let remap0 = |f: Float| if f != 0. { f } else { 1. };
+        let camera_delta = |i: usize| i + 1 != t && camera[i].delta;
+        let light_delta = |i: usize| i + 1 != s && light[i].delta;
+        let mut sum_ri = 0.;
+
+        let mut ri = 1.;
+        for i in (2..t).rev() {
+            let pdf_rev = if i == t - 1 {
+                pt_rev
+            } else if i == t - 2 {
+                pt_minus_rev
+            } else {
+                camera[i].pdf_rev
+            };
+            ri *= remap0(pdf_rev) / remap0(camera[i].pdf_fwd);
+            if !camera_delta(i) && !camera_delta(i - 1) {
+                sum_ri += ri * ri;
+            }
+        }
+
+        let mut ri = 1.;
+        for i in (0..s).rev() {
+            let pdf_rev = if i == s - 1 {
+                qs_rev
+            } else if i == s - 2 {
+                qs_minus_rev
+            } else {
+                light[i].pdf_rev
+            };
+            ri *= remap0(pdf_rev) / remap0(light[i].pdf_fwd);
+            let delta_light_vertex = i > 0 && light_delta(i - 1);
+            if !light_delta(i) && !delta_light_vertex {
+                sum_ri += ri * ri;
+            }
+        }
+
+        1. / (1. + sum_ri)
+    }
+
+    /// Unweighted contribution of the path made of the first `s` light and `t` camera vertices.
+    fn connect(
+        &self,
+        scene: &Scene,
+        camera: &[Vertex],
+        light: &[Vertex],
+        s: usize,
+        t: usize,
+    ) -> Col {
+        let pt = &camera[t - 1];
+        let pt_rec = pt.rec.as_ref().unwrap();
+
+        if s == 0 {
+            return pt.beta * pt_rec.mat.emitted(&pt.r_in, pt_rec);
+        }
+
+        // A vertex which went on by a specular bounce may still have other lobes to connect
+        // through, such as the base under a coat; purely specular materials evaluate to zero
+        let qs = &light[s - 1];
+        let qs_rec = qs.rec.as_ref().unwrap();
+        let d = qs.p - pt.p;
+
+        let f_pt = pt_rec.mat.eval(&pt.r_in, pt_rec, d);
+        let f_qs = if s == 1 {
+            // The light vertex emits rather than scatters: its throughput holds the radiance
+            let cosine = qs.normal.dot(-d.unit_vector());
+            if cosine <= 0. {
+                return Col::zero();
+            }
+            col(cosine, cosine, cosine)
+        } else {
+            qs_rec.mat.eval(&qs.r_in, qs_rec, -d)
+        };
+        let contribution = pt.beta * f_pt * f_qs * qs.beta / d.squared_length();
+        if contribution == Col::zero() {
+            return contribution;
+        }
+
+        let shadow = pt.r_in.spawn(pt.p, d);
+        match scene.shadow(&shadow, 0.001, 0.999) {
+            (Some(_), _) => Col::zero(),
+            (None, transmittance) => contribution * shadow.transmittance(1.) * transmittance,
+        }
+    }
+}
+
+impl Integrator for Bidirectional {
+    fn lighting(&self, scene: &Scene, r: &Ray, sampler: &mut dyn Sampler) -> Aovs {
+        let mut camera = vec![Vertex::camera(r)];
+        let step = Step {
+            ray: *r,
+            beta: col(1., 1., 1.),
+            pdf: 0.,
+        };
+        let mut first_hit = Aovs::zero();
+        let escaped = self.walk(
+            scene,
+            RayKind::Primary,
+            step,
+            &mut camera,
+            sampler,
+            Some(&mut first_hit),
+        );
+        let light = self.light_subpath(scene, r, sampler);
+
+        // Nothing but the camera subpath can reach the background
+        let mut radiance = Lighting::zero();
+        if let Some((ray, beta)) = escaped {
+            radiance.add(camera.len() - 1, beta * background(scene, &ray));
+        }
+
+        for t in 2..=camera.len() {
+            for s in 0..=light.len() {
+                let contribution = self.connect(scene, &camera, &light, s, t);
+                if contribution != Col::zero() {
+                    let weight = self.mis_weight(scene, &camera, &light, s, t);
+                    // The path has s + t vertices, all but the first and last scattering
+                    radiance.add(s + t - 2, contribution * weight);
+                }
+            }
+        }
+
+        Aovs {
+            lighting: radiance,
+            ..first_hit
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use super::{Integrator, Lighting};
+use crate::{
+    aov::{self, Aovs},
+    prelude::*,
+    scene::Scene,
+    stats::RayKind,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum DebugChannel {
+    /// Shading normal, mapped from [-1, 1] to [0, 1].
+    Normals,
+    /// Attenuation of the first scattering event, or emitted colour for lights.
+    Albedo,
+    /// Distance to the first hit, from white close to the camera to black at `max_distance`.
+    Depth { max_distance: Float },
+}
+
+pub struct Debug {
+    channel: DebugChannel,
+}
+
+impl Debug {
+    pub fn new(channel: DebugChannel) -> Self {
+        Self { channel }
+    }
+}
+
+impl Debug {
+    /// `scatter` is what sampling the material at `rec` gave.
+    fn shade(&self, r: &Ray, rec: &HitRecord, scatter: Option<&Scatter>) -> Col {
+        match self.channel {
+            DebugChannel::Normals => {
+                let n = rec.normal.unit_vector();
+                0.5 * col(n.x() + 1., n.y() + 1., n.z() + 1.)
+            }
+            DebugChannel::Albedo => match scatter {
+                Some(scatter) => scatter.attenuation,
+                None => rec.mat.emitted(r, rec),
+            },
+            DebugChannel::Depth { max_distance } => {
+                let distance = rec.t * r.direction().length();
+                let grey = 1. - (distance / max_distance).min(1.);
+                col(grey, grey, grey)
+            }
+        }
+    }
+}
+
+/// The channels all describe the first hit, so they count as direct light.
+impl Integrator for Debug {
+    fn lighting(&self, scene: &Scene, r: &Ray, sampler: &mut dyn Sampler) -> Aovs {
+        let rec = match scene.hit(RayKind::Primary, r, 0.001, Float::MAX) {
+            Some(rec) => rec,
+            None => return aov::missed(scene, r),
+        };
+        let scatter = rec.mat.scatter(r, &rec, sampler);
+        Aovs {
+            lighting: Lighting {
+                direct: self.shade(r, &rec, scatter.as_ref()),
+                indirect: Col::zero(),
+            },
+            ..aov::first_hit(r, &rec, scatter.as_ref())
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use super::{background, emitted, sample_light, Integrator, Lighting};
+use crate::{
+    aov::{self, Aovs},
+    prelude::*,
+    scene::Scene,
+    stats::{self, RayKind},
+};
+
+/// Highest probability for a path to survive Russian roulette, so that even paths which keep all
+/// their energy, like those bouncing around inside clear glass, eventually end.
+pub const MAX_SURVIVAL: Float = 0.95;
+
+/// Path tracing as a loop, carrying the throughput of the path so far. After `roulette_depth`
+/// bounces, paths are ended at random with a probability that grows as their throughput drops,
+/// and the survivors are boosted to compensate. Unlike a fixed maximum depth, this keeps the
+/// estimate unbiased.
+pub struct IterativePathTracer {
+    roulette_depth: usize,
+}
+
+impl IterativePathTracer {
+    pub fn new(roulette_depth: usize) -> Self {
+        Self { roulette_depth }
+    }
+}
+
+impl Integrator for IterativePathTracer {
+    fn lighting(&self, scene: &Scene, r: &Ray, sampler: &mut dyn Sampler) -> Aovs {
+        let mut first_hit = Aovs::zero();
+        let mut radiance = Lighting::zero();
+        let mut throughput = col(1., 1., 1.);
+        let mut ray = *r;
+        let mut bsdf_pdf = None;
+        let mut bounce = 0;
+
+        loop {
+            let kind = if bounce == 0 {
+                RayKind::Primary
+            } else {
+                RayKind::Secondary
+            };
+            let rec = match scene.hit(kind, &ray, 0.001, Float::MAX) {
+                Some(rec) => rec,
+                None => {
+                    if bounce == 0 {
+                        first_hit = aov::missed(scene, &ray);
+                    }
+                    radiance.add(bounce, throughput * background(scene, &ray));
+                    break;
+                }
+            };
+            throughput *= ray.transmittance(rec.t);
+            radiance.add(bounce, throughput * emitted(scene, &ray, &rec, bsdf_pdf));
+            // Whichever lobe of the BSDF scatters the path, lights are sampled against all of it
+            let direct = sample_light(scene, &ray, &rec, sampler);
+            radiance.add(bounce + 1, throughput * direct);
+
+            let scatter = rec.mat.scatter(&ray, &rec, sampler);
+            if bounce == 0 {
+                first_hit = aov::first_hit(&ray, &rec, scatter.as_ref());
+            }
+            let Scatter {
+                scattered,
+                attenuation,
+                specular,
+            } = match scatter {
+                Some(scatter) => scatter,
+                None => break,
+            };
+
+            bsdf_pdf = if specular {
+                None
+            } else {
+                Some(rec.mat.pdf(&ray, &rec, scattered.direction()))
+            };
+
+            throughput *= attenuation;
+            bounce += 1;
+            if bounce > self.roulette_depth {
+                let survival = throughput
+                    .r()
+                    .max(throughput.g())
+                    .max(throughput.b())
+                    .min(MAX_SURVIVAL);
+                if sampler.next_1d() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = scattered;
+        }
+
+        stats::path_length(bounce);
+        Aovs {
+            lighting: radiance,
+            ..first_hit
+        }
+    }
+}
@@ -0,0 +1,212 @@
+mod kd_tree;
+
+use super::{
+    background, emit, emitted, iterative::MAX_SURVIVAL, sample_light, Emission, Integrator,
+    Lighting,
+};
+use crate::{
+    aov::{self, Aovs},
+    prelude::*,
+    sampler::Independent,
+    scene::Scene,
+    spectrum,
+    stats::{self, RayKind},
+};
+use kd_tree::KdTree;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Photon {
+    p: Pos,
+    /// Direction the photon was travelling in when it landed.
+    direction: Dir,
+    power: Col,
+}
+
+/// Path tracing with a caustic photon map. Before rendering, photons are shot from the lights,
+/// and those landing on a diffuse surface after bouncing off or through specular surfaces are
+/// stored. At each diffuse hit of a camera path, the caustic light arriving there is estimated
+/// from the density of nearby photons, and the path tracer leaves out the paths the photon map
+/// already accounts for. This finds in seconds the caustics under glass which camera paths only
+/// stumble upon by chance.
+///
+/// The photons are shot at time 0 and without a wavelength, so caustics show neither motion blur
+/// nor dispersion.
+pub struct PhotonMapper {
+    photons: usize,
+    radius: Float,
+    depth: usize,
+    roulette_depth: usize,
+    caustics: KdTree,
+}
+
+impl PhotonMapper {
+    pub fn new(photons: usize, radius: Float, depth: usize, roulette_depth: usize) -> Self {
+        Self {
+            photons,
+            radius,
+            depth,
+            roulette_depth,
+            caustics: KdTree::new(vec![]),
+        }
+    }
+
+    /// Whether the surface at `rec` reflects any light diffusely, rather than only in specular
+    /// lobes. Media have no surface for the density estimate.
+    fn diffuse(r: &Ray, rec: &HitRecord) -> bool {
+        rec.normal != Dir::zero() && rec.mat.eval(r, rec, rec.normal) != Col::zero()
+    }
+
+    /// Follows a photon through specular bounces, storing it in `photons` at every diffuse
+    /// surface it lands on after bouncing at least once, until it scatters diffusely.
+    fn trace(
+        &self,
+        scene: &Scene,
+        mut ray: Ray,
+        mut power: Col,
+        sampler: &mut dyn Sampler,
+        photons: &mut Vec<Photon>,
+    ) {
+        for bounce in 0..self.depth {
+            let rec = match scene.hit(RayKind::Secondary, &ray, 0.001, Float::MAX) {
+                Some(rec) => rec,
+                None => return,
+            };
+            power *= ray.transmittance(rec.t);
+            // Direct light is sampled by the path tracer. The photon lands whichever lobe then
+            // scatters it, as the density estimate uses the whole BSDF but its specular lobes.
+            if bounce > 0 && Self::diffuse(&ray, &rec) {
+                photons.push(Photon {
+                    p: rec.p,
+                    direction: ray.direction().unit_vector(),
+                    power,
+                });
+            }
+            let scatter = match rec.mat.scatter(&ray, &rec, sampler) {
+                Some(scatter) if scatter.specular => scatter,
+                _ => return,
+            };
+            power *= scatter.attenuation;
+            ray = scatter.scattered;
+        }
+    }
+
+    /// Radiance scattered back along `r` at `rec` from the caustic photons around it.
+    fn caustics(&self, r: &Ray, rec: &HitRecord) -> Col {
+        let mut flux = Col::zero();
+        self.caustics.within(rec.p, self.radius, |photon| {
+            // Only photons landing on the same side of the surface
+            let cosine = -photon.direction.dot(rec.normal);
+            if cosine > 0. {
+                flux += photon.power * rec.mat.eval(r, rec, -photon.direction) / cosine;
+            }
+        });
+        spectrum::project(flux, r.wavelength()) / (PI * self.radius * self.radius)
+    }
+}
+
+impl Integrator for PhotonMapper {
+    fn preprocess(&mut self, scene: &Scene) {
+        let mut sampler = Independent::new();
+        let template = Ray::new(Pos::zero(), dir(0., 0., 1.), 0.);
+        let mut photons = vec![];
+        for _ in 0..self.photons {
+            let Emission {
+                ray,
+                radiance,
+                pdf_origin,
+                ..
+            } = match emit(scene, &template, &mut sampler) {
+                Some(emission) => emission,
+                None => break,
+            };
+            // Emission is cosine-distributed, so the cosine and the density cancel out but for PI
+            let power = radiance * (PI / (pdf_origin * self.photons as Float));
+            self.trace(scene, ray, power, &mut sampler, &mut photons);
+        }
+        self.caustics = KdTree::new(photons);
+    }
+
+    fn lighting(&self, scene: &Scene, r: &Ray, sampler: &mut dyn Sampler) -> Aovs {
+        let mut first_hit = Aovs::zero();
+        let mut radiance = Lighting::zero();
+        let mut throughput = col(1., 1., 1.);
+        let mut ray = *r;
+        let mut bsdf_pdf = None;
+        let mut bounce = 0;
+        // Whether the path has been diffusely reflected off a surface, then only specularly
+        let mut after_diffuse = false;
+        let mut caustic = false;
+
+        loop {
+            let kind = if bounce == 0 {
+                RayKind::Primary
+            } else {
+                RayKind::Secondary
+            };
+            let rec = match scene.hit(kind, &ray, 0.001, Float::MAX) {
+                Some(rec) => rec,
+                None => {
+                    if bounce == 0 {
+                        first_hit = aov::missed(scene, &ray);
+                    }
+                    radiance.add(bounce, throughput * background(scene, &ray));
+                    break;
+                }
+            };
+            throughput *= ray.transmittance(rec.t);
+            if !caustic {
+                radiance.add(bounce, throughput * emitted(scene, &ray, &rec, bsdf_pdf));
+            }
+            // Both estimates cover the whole BSDF but its specular lobes, whichever lobe scatters
+            let direct = sample_light(scene, &ray, &rec, sampler);
+            radiance.add(bounce + 1, throughput * direct);
+            if Self::diffuse(&ray, &rec) {
+                // Caustic photons have been through at least one specular bounce
+                radiance.add(bounce + 2, throughput * self.caustics(&ray, &rec));
+            }
+
+            let scatter = rec.mat.scatter(&ray, &rec, sampler);
+            if bounce == 0 {
+                first_hit = aov::first_hit(&ray, &rec, scatter.as_ref());
+            }
+            let Scatter {
+                scattered,
+                attenuation,
+                specular,
+            } = match scatter {
+                Some(scatter) => scatter,
+                None => break,
+            };
+
+            bsdf_pdf = if specular {
+                caustic = after_diffuse;
+                None
+            } else {
+                after_diffuse = rec.normal != Dir::zero();
+                caustic = false;
+                Some(rec.mat.pdf(&ray, &rec, scattered.direction()))
+            };
+
+            throughput *= attenuation;
+            bounce += 1;
+            if bounce > self.roulette_depth {
+                let survival = throughput
+                    .r()
+                    .max(throughput.g())
+                    .max(throughput.b())
+                    .min(MAX_SURVIVAL);
+                if sampler.next_1d() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = scattered;
+        }
+
+        stats::path_length(bounce);
+        Aovs {
+            lighting: radiance,
+            ..first_hit
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use super::Photon;
+use crate::prelude::*;
+
+/// Photons sorted into a balanced kd-tree. The tree is implicit: each subtree is a slice whose
+/// middle photon splits the others along the axis stored at the same index in `axes`.
+pub struct KdTree {
+    photons: Vec<Photon>,
+    axes: Vec<usize>,
+}
+
+fn build(photons: &mut [Photon], axes: &mut [usize]) {
+    if photons.len() <= 1 {
+        return;
+    }
+
+    // Split along the axis the photons are most spread out on
+    let mut min = photons[0].p;
+    let mut max = photons[0].p;
+    for photon in photons.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(photon.p[axis]);
+            max[axis] = max[axis].max(photon.p[axis]);
+        }
+    }
+    let extent = max - min;
+    let axis = (0..3)
+        .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+        .unwrap();
+
+    let mid = photons.len() / 2;
+    photons.select_nth_unstable_by(mid, |a, b| a.p[axis].total_cmp(&b.p[axis]));
+    axes[mid] = axis;
+
+    let (left, right) = photons.split_at_mut(mid);
+    let (left_axes, right_axes) = axes.split_at_mut(mid);
+    build(left, left_axes);
+    build(&mut right[1..], &mut right_axes[1..]);
+}
+
+fn within<F: FnMut(&Photon)>(photons: &[Photon], axes: &[usize], p: Pos, radius: Float, f: &mut F) {
+    if photons.is_empty() {
+        return;
+    }
+
+    let mid = photons.len() / 2;
+    let photon = &photons[mid];
+    if (photon.p - p).squared_length() <= radius * radius {
+        f(photon);
+    }
+
+    let axis = axes[mid];
+    let d = p[axis] - photon.p[axis];
+    let (left, right) = (
+        (&photons[..mid], &axes[..mid]),
+        (&photons[mid + 1..], &axes[mid + 1..]),
+    );
+    let (near, far) = if d < 0. { (left, right) } else { (right, left) };
+    within(near.0, near.1, p, radius, f);
+    // The other side can only hold photons in range if the splitting plane is
+    if d.abs() <= radius {
+        within(far.0, far.1, p, radius, f);
+    }
+}
+
+impl KdTree {
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+        Self { photons, axes }
+    }
+
+    /// Calls `f` on every photon within `radius` of `p`.
+    pub fn within<F: FnMut(&Photon)>(&self, p: Pos, radius: Float, mut f: F) {
+        within(&self.photons, &self.axes, p, radius, &mut f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_within_finds_the_same_photons_as_brute_force() {
+        let mut rng = thread_rng();
+        let photons: Vec<Photon> = (0..1000)
+            .map(|i| Photon {
+                p: pos(rng.gen(), rng.gen(), rng.gen()),
+                direction: dir(0., -1., 0.),
+                power: col(i as Float, 0., 0.),
+            })
+            .collect();
+        let tree = KdTree::new(photons.clone());
+
+        for _ in 0..100 {
+            let p = pos(rng.gen(), rng.gen(), rng.gen());
+            let radius = 0.2 * rng.gen::<Float>();
+            let mut expected: Vec<Float> = photons
+                .iter()
+                .filter(|photon| (photon.p - p).squared_length() <= radius * radius)
+                .map(|photon| photon.power.r())
+                .collect();
+            let mut found = vec![];
+            tree.within(p, radius, |photon| found.push(photon.power.r()));
+            expected.sort_by(|a, b| a.total_cmp(b));
+            found.sort_by(|a, b| a.total_cmp(b));
+            assert_eq!(expected, found);
+        }
+    }
+
+    #[test]
+    fn test_build_with_non_finite_photons() {
+        let photon = |x| Photon {
+            p: pos(x, 0., 0.),
+            direction: dir(0., -1., 0.),
+            power: col(1., 1., 1.),
+        };
+        let tree = KdTree::new(vec![
+            photon(Float::NAN),
+            photon(0.),
+            photon(Float::INFINITY),
+        ]);
+        let mut found = 0;
+        tree.within(Pos::zero(), 1., |_| found += 1);
+        assert_eq!(1, found);
+    }
+}
@@ -0,0 +1,86 @@
+use super::{background, emitted, sample_light, Integrator, Lighting};
+use crate::{
+    aov::{self, Aovs},
+    prelude::*,
+    scene::Scene,
+    stats::RayKind,
+};
+
+/// Path tracing by recursion: follows one scattered ray per hit, up to a fixed number of
+/// bounces. At each hit lights are also sampled explicitly, and combined with BSDF sampling
+/// through multiple importance sampling.
+pub struct RecursivePathTracer {
+    depth: usize,
+}
+
+impl RecursivePathTracer {
+    pub fn new(depth: usize) -> Self {
+        Self { depth }
+    }
+
+    /// `bsdf_pdf` is the density with which `r` was picked at a diffuse hit, or `None` for
+    /// camera rays and specular bounces, which can't find lights any other way. For camera rays,
+    /// what `r` hits is described in `first_hit`.
+    fn colour_rec(
+        &self,
+        scene: &Scene,
+        r: &Ray,
+        sampler: &mut dyn Sampler,
+        depth: usize,
+        bsdf_pdf: Option<Float>,
+        first_hit: Option<&mut Aovs>,
+    ) -> Lighting {
+        let bounces = self.depth - depth;
+        let mut lighting = Lighting::zero();
+        let kind = if bounces == 0 {
+            RayKind::Primary
+        } else {
+            RayKind::Secondary
+        };
+        if let Some(rec) = scene.hit(kind, r, 0.001, Float::MAX) {
+            lighting.add(bounces, emitted(scene, r, &rec, bsdf_pdf));
+
+            let scatter = if depth > 0 {
+                lighting.add(bounces + 1, sample_light(scene, r, &rec, sampler));
+                rec.mat.scatter(r, &rec, sampler)
+            } else {
+                None
+            };
+            if let Some(first_hit) = first_hit {
+                *first_hit = aov::first_hit(r, &rec, scatter.as_ref());
+            }
+            if let Some(Scatter {
+                scattered,
+                attenuation,
+                specular,
+            }) = scatter
+            {
+                let pdf = if specular {
+                    None
+                } else {
+                    Some(rec.mat.pdf(r, &rec, scattered.direction()))
+                };
+                lighting +=
+                    self.colour_rec(scene, &scattered, sampler, depth - 1, pdf, None) * attenuation;
+            }
+            lighting = lighting * r.transmittance(rec.t);
+        } else {
+            if let Some(first_hit) = first_hit {
+                *first_hit = aov::missed(scene, r);
+            }
+            lighting.add(bounces, background(scene, r));
+        }
+        lighting
+    }
+}
+
+impl Integrator for RecursivePathTracer {
+    fn lighting(&self, scene: &Scene, r: &Ray, sampler: &mut dyn Sampler) -> Aovs {
+        let mut first_hit = Aovs::zero();
+        let lighting = self.colour_rec(scene, r, sampler, self.depth, None, Some(&mut first_hit));
+        Aovs {
+            lighting,
+            ..first_hit
+        }
+    }
+}
@@ -1,126 +1,366 @@
+mod animation;
+mod aov;
 mod camera;
+mod denoise;
+mod filter;
+mod heat_map;
 mod hitable;
+mod id;
+mod integrator;
 mod material;
 mod pixbuf;
 mod prelude;
 mod ray;
+mod report;
+mod sampler;
 mod scene;
 mod settings;
+mod spectrum;
+mod stats;
 mod texture;
+mod transform;
 mod vec3;
 
-use hitable::{HitableFactory, PlainHitableFactory, Stats, TracingHitableFactory};
+use animation::Animation;
+use camera::{Bokeh, Physical, Projection};
+use denoise::Denoise;
+use filter::FilterKind;
+use integrator::{Integrator, IntegratorKind};
 use pixbuf::Pixbuf;
 use prelude::*;
-use rand::prelude::*;
 use rayon::prelude::*;
+use report::Timings;
+use sampler::SamplerKind;
 use scene::Scene;
+use stats::{RayKind, Stats};
+use std::{fs, ops::Range, time::Instant};
 
+#[derive(Clone)]
 pub struct Settings {
     pub width: usize,
     pub height: usize,
+    /// Samples per pixel, or the most any pixel gets when sampling adaptively.
     pub samples: usize,
+    /// When set, pixels stop being sampled once half the 95% confidence interval of their
+    /// displayed brightness, between 0 and 1, falls below this.
+    pub target_noise: Option<Float>,
+    /// Maximum number of bounces for the recursive and bidirectional path tracers.
     pub depth: usize,
+    /// Number of bounces after which the iterative path tracer starts Russian roulette.
+    pub roulette_depth: usize,
+    /// Trace one wavelength per sample instead of RGB, so that dispersion shows up.
+    pub spectral: bool,
+    /// How the scene's camera projects it onto the image.
+    pub projection: Projection,
+    /// When set, renders a stereo pair with the eyes this far apart, left eye on top.
+    pub stereo: Option<Float>,
+    /// When set, the perspective camera is made from these settings of a real camera instead
+    /// of the scene's field of view, aperture and shutter interval. The realistic camera only
+    /// takes their f-number, shutter interval and ISO.
+    pub lens: Option<Physical>,
+    /// When set, overrides when the shutter opens and closes, for the scene's camera and the
+    /// keyframes of its animation. Set for each frame when rendering an animation.
+    pub shutter: Option<(Float, Float)>,
+    /// When set, the rows of the image are exposed one after another from the top, starting
+    /// over this fraction of the shutter interval, and each for the rest of it.
+    pub rolling_shutter: Option<Float>,
+    /// When set, renders a sequence of frames numbered from `frame_0001.png`.
+    pub animation: Option<Animation>,
+    /// The aperture's shape, vignetting, tilt and shift of the perspective and realistic
+    /// cameras.
+    pub bokeh: Bokeh,
+    /// When set, the camera focuses on whatever is seen through the centre of this pixel,
+    /// counted from the top left, instead of at the scene's focus distance.
+    pub autofocus: Option<(usize, usize)>,
+    /// Where the random numbers for each sample come from.
+    pub sampler: SamplerKind,
+    /// How samples are spread over the pixels around where they land.
+    pub filter: FilterKind,
+    /// Radius of the filter, in pixels.
+    pub filter_radius: Float,
+    /// When set, the image is denoised after rendering, guided by the albedo, normal and depth
+    /// of the first hits in each pixel.
+    pub denoise: Option<Denoise>,
+    /// Record the depth, position, normal, albedo, material and object IDs of the first hits,
+    /// and the direct and indirect lighting, and save an image of each next to the render.
+    pub aovs: bool,
+    /// Save statistics about the render to `stats.json`, and a heat map of the time spent on
+    /// each pixel. Rays and intersection tests are only counted, and saved, with the `stats`
+    /// feature.
+    pub stats: bool,
+    /// Save heat maps of how many nodes of bounding hierarchies and primitives the ray through
+    /// each pixel is tested against, which needs the `stats` feature.
+    pub heat_maps: bool,
+    pub integrator: IntegratorKind,
 }
 
-#[inline]
-fn colour<C>(c: &mut C, r: &Ray, world: &Hitable<C>, depth: usize) -> Col {
-    if let Some(rec) = world.hit(c, r, 0.001, MAX) {
-        if depth > 0 {
-            if let Some(Scatter {
-                scattered,
-                attenuation,
-            }) = rec.mat.scatter(r, &rec)
-            {
-                return attenuation * colour(c, &scattered, world, depth - 1);
-            }
-        }
+/// Number of samples per pixel between two checks for convergence, when sampling adaptively.
+const PASS_SAMPLES: usize = 16;
 
-        return Col::zero();
-    } else {
-        let unit_direction = r.direction().unit_vector();
-        let t = 0.5 * (unit_direction.y() + 1.);
-        (1. - t) * col(1., 1., 1.) + t * col(0.5, 0.7, 1.0)
+/// Number of bands of rows each thread gets in a pass, more than one so that threads whose rows
+/// converged or were quick to render can pick up others.
+const BANDS_PER_THREAD: usize = 4;
+
+fn autofocus(settings: &Settings, scene: &mut Scene) {
+    if let Some((i, j)) = settings.autofocus {
+        let u = (i as Float + 0.5) / (settings.width as Float);
+        let v = 1. - (j as Float + 0.5) / (settings.height as Float);
+        scene.autofocus(u, v);
     }
 }
 
-fn render_once<C>(c: &mut C, settings: &Settings, scene: &Scene<C>) -> Pixbuf {
+/// Takes the samples numbered `indices` for the pixels in `rows` which haven't converged.
+fn render_once(
+    settings: &Settings,
+    scene: &Scene,
+    integrator: &dyn Integrator,
+    converged: &[bool],
+    rows: Range<usize>,
+    indices: Range<usize>,
+) -> Pixbuf {
     let &Settings {
         width,
         height,
-        depth,
+        spectral,
+        denoise,
+        aovs,
+        stats,
         ..
     } = settings;
-    let Scene { world, camera } = scene;
-    let mut res = Pixbuf::new(width, height);
-    let mut rng = thread_rng();
-
-    for j in 0..height {
-        for i in 0..width {
-            let u = (i as Float + rng.gen::<Float>()) / (width as Float);
-            let v = 1. - (j as Float + rng.gen::<Float>()) / (height as Float);
-            let r = camera.get_ray(u, v);
-            let c = colour(c, &r, &**world, depth);
-            res.put(i, j, c);
+    let camera = &scene.camera;
+    let exposure = camera.exposure();
+    let exposure = col(exposure, exposure, exposure);
+    let mut res = Pixbuf::rows(width, height, rows.clone(), settings.filter_radius);
+    if aovs || denoise.is_some() {
+        res = res.with_aovs();
+    }
+    let mut sampler = sampler::sampler(settings);
+    let filter = filter::filter(settings);
+
+    for index in indices {
+        for j in rows.clone() {
+            for i in 0..width {
+                if converged[i + width * j] {
+                    continue;
+                }
+                sampler.start_pixel_sample(i, j, index);
+                let start = if stats { Some(Instant::now()) } else { None };
+                let (du, dv) = sampler.next_2d();
+                let (x, y) = (i as Float + du, j as Float + dv);
+                let u = x / (width as Float);
+                let v = 1. - y / (height as Float);
+                let r = match camera.get_ray(u, v, &mut *sampler) {
+                    Some(r) => r,
+                    None => {
+                        res.add_sample(&*filter, x, y, Col::zero());
+                        continue;
+                    }
+                };
+                let (r, wavelength) = if spectral {
+                    let wavelength = spectrum::sample_wavelength(sampler.next_1d());
+                    (r.with_wavelength(wavelength), Some(wavelength))
+                } else {
+                    (r, None)
+                };
+                let mut sample = integrator.lighting(scene, &r, &mut *sampler);
+                sample.lighting = sample.lighting * exposure;
+                res.add(&*filter, x, y, sample, wavelength);
+                if let Some(start) = start {
+                    res.add_time(i, j, start.elapsed());
+                }
+            }
         }
     }
 
     res
 }
 
-fn render<F>(settings: Settings, scene: F) -> Pixbuf
-where
-    F: Fn(&PlainHitableFactory, &Settings) -> Scene<()>,
-{
-    let scene = scene(&PlainHitableFactory, &settings);
-    let mut pixbuf = (0..settings.samples)
-        .into_par_iter()
-        .map(|_| {
-            let pixbuf = render_once(&mut (), &settings, &scene);
-            pixbuf
-        })
-        .reduce(
-            || (Pixbuf::new(settings.width, settings.height)),
-            |mut p1, p2| {
-                p1 += p2;
-                p1
-            },
-        );
+/// Takes all the samples for the image, in passes when sampling adaptively so that converged
+/// pixels can be left alone. Also returns what the threads which took them counted.
+fn render(settings: &Settings, scene: &Scene, integrator: &dyn Integrator) -> (Stats, Pixbuf) {
+    let &Settings {
+        width,
+        height,
+        samples,
+        target_noise,
+        ..
+    } = settings;
+    let mut stats = Stats::new();
+    let mut pixbuf = Pixbuf::new(width, height);
+    if settings.aovs || settings.denoise.is_some() {
+        pixbuf = pixbuf.with_aovs();
+    }
+    let mut converged = vec![false; width * height];
+    let mut done = 0;
+    let bands = (rayon::current_num_threads() * BANDS_PER_THREAD).min(height);
+    let rows = |b: usize| b * height / bands..(b + 1) * height / bands;
 
-    pixbuf /= settings.samples;
+    while done < samples {
+        // Each task takes a whole pass of samples of a band of rows, into a pixbuf holding just
+        // those rows. Without adaptive sampling, all the samples are taken in a single pass.
+        let pass = match target_noise {
+            Some(_) => PASS_SAMPLES.min(samples - done),
+            None => samples,
+        };
+        let bands: Vec<_> = (0..bands)
+            .into_par_iter()
+            .map(|b| {
+                let indices = done..done + pass;
+                let p = render_once(settings, scene, integrator, &converged, rows(b), indices);
+                (stats::take(), p)
+            })
+            .collect();
+        for (s, p) in bands {
+            stats += s;
+            pixbuf += p;
+        }
+        done += pass;
 
-    pixbuf
+        if let Some(target_noise) = target_noise {
+            converged = pixbuf.converged(target_noise, PASS_SAMPLES);
+            if converged.iter().all(|&c| c) {
+                break;
+            }
+        }
+    }
+
+    (stats, pixbuf)
 }
 
-fn render_with_stats<F>(settings: Settings, scene: F) -> Pixbuf
+/// Makes the scene, renders it and saves the image, numbered by `frame` in animations, with
+/// whatever else the settings ask for.
+fn render_and_save<F>(settings: &Settings, scene: F, frame: Option<usize>)
 where
-    F: Fn(&TracingHitableFactory, &Settings) -> Scene<Stats>,
+    F: Fn(&Settings) -> Scene,
 {
-    let scene = scene(&TracingHitableFactory, &settings);
-    let (stats, mut pixbuf) = (0..settings.samples)
+    let start = Instant::now();
+    id::reset();
+    let mut scene = scene(settings);
+    let scene_time = start.elapsed();
+
+    let start = Instant::now();
+    autofocus(settings, &mut scene);
+    let mut integrator = integrator::integrator(settings);
+    integrator.preprocess(&scene);
+    let (mut stats, pixbuf) = render(settings, &scene, &*integrator);
+    let render_time = start.elapsed();
+    // What was counted on this thread, while making the scene, focusing and preprocessing
+    stats += stats::take();
+
+    let start = Instant::now();
+    save(settings, &pixbuf, frame);
+    let timings = Timings {
+        scene: scene_time - stats.bvh_time(),
+        bvh: stats.bvh_time(),
+        render: render_time,
+        export: start.elapsed(),
+    };
+
+    if settings.heat_maps && stats::ENABLED {
+        save_heat_maps(settings, &scene, frame);
+    }
+    if settings.stats {
+        pixbuf
+            .time_image()
+            .save(output_name(frame, "time", "png"))
+            .unwrap();
+        fs::write(
+            output_name(frame, "stats", "json"),
+            report::json(&stats, &timings, &pixbuf),
+        )
+        .unwrap();
+    }
+}
+
+/// Saves heat maps of how many nodes of bounding hierarchies and how many primitives the ray
+/// through the centre of each pixel was tested against. They show where the hierarchies split
+/// the scene badly.
+fn save_heat_maps(settings: &Settings, scene: &Scene, frame: Option<usize>) {
+    let &Settings { width, height, .. } = settings;
+    let rows: Vec<Vec<(usize, usize)>> = (0..height)
         .into_par_iter()
-        .map(|_| {
-            let mut stats = Stats::new();
-            let pixbuf = render_once(&mut stats, &settings, &scene);
-            (stats, pixbuf)
+        .map(|j| {
+            let mut sampler = sampler::sampler(settings);
+            stats::take();
+            (0..width)
+                .map(|i| {
+                    sampler.start_pixel_sample(i, j, 0);
+                    let u = (i as Float + 0.5) / (width as Float);
+                    let v = 1. - (j as Float + 0.5) / (height as Float);
+                    if let Some(r) = scene.camera.get_ray(u, v, &mut *sampler) {
+                        scene.hit(RayKind::Primary, &r, 0.001, Float::MAX);
+                    }
+                    let stats = stats::take();
+                    (stats.box_tests(), stats.primitive_tests())
+                })
+                .collect()
         })
-        .reduce(
-            || (Stats::new(), Pixbuf::new(settings.width, settings.height)),
-            |(mut s1, mut p1), (s2, p2)| {
-                s1 += s2;
-                p1 += p2;
-                (s1, p1)
-            },
-        );
+        .collect();
+    let (nodes, primitives): (Vec<usize>, Vec<usize>) = rows.into_iter().flatten().unzip();
 
-    println!("{:#?}", stats);
-    pixbuf /= settings.samples;
+    heat_map::heat_map(width, height, &nodes)
+        .save(output_name(frame, "bvh_nodes", "png"))
+        .unwrap();
+    heat_map::heat_map(width, height, &primitives)
+        .save(output_name(frame, "primitive_tests", "png"))
+        .unwrap();
+}
+
+/// Name of the file `what` is saved to, numbered by `frame` in animations.
+fn output_name(frame: Option<usize>, what: &str, extension: &str) -> String {
+    match frame {
+        Some(frame) if what == "image" => format!("frame_{:04}.{}", frame, extension),
+        Some(frame) => format!("frame_{:04}_{}.{}", frame, what, extension),
+        None => format!("{}.{}", what, extension),
+    }
+}
 
-    pixbuf
+/// Saves the image, and the images made alongside it, numbered by `frame` in animations.
+fn save(settings: &Settings, pixbuf: &Pixbuf, frame: Option<usize>) {
+    let name = |what: &str| output_name(frame, what, "png");
+    if settings.aovs {
+        for &channel in &aov::CHANNELS {
+            pixbuf
+                .aov_image(channel)
+                .save(name(channel.name()))
+                .unwrap();
+            if channel.floats() {
+                fs::write(
+                    output_name(frame, channel.name(), "pfm"),
+                    pixbuf.aov_floats(channel),
+                )
+                .unwrap();
+            }
+        }
+    }
+    if let Some(denoise) = settings.denoise {
+        pixbuf.as_image().save(name("noisy")).unwrap();
+        pixbuf
+            .denoised(&denoise)
+            .as_image()
+            .save(name("image"))
+            .unwrap();
+    } else {
+        pixbuf.as_image().save(name("image")).unwrap();
+    }
+    if settings.target_noise.is_some() {
+        pixbuf.samples_image().save(name("samples")).unwrap();
+    }
 }
 
 fn main() {
-    let settings = settings::high();
+    // `cargo run --release -- preview` for a quick, denoised render, or `-- star_bokeh`.
+    let settings = match std::env::args().nth(1).as_deref() {
+        Some("preview") => settings::preview(),
+        Some("star_bokeh") => settings::star_bokeh(),
+        _ => settings::high(),
+    };
+    if (settings.stats || settings.heat_maps) && !stats::ENABLED {
+        eprintln!(
+            "Built without the `stats` feature, so stats.json will only hold timings, and no \
+             heat maps of tests will be saved"
+        );
+    }
 
     // let scene = scene::book_1::chap_03_simple_camera_and_background::scene;
     // let scene = scene::book_1::chap_07_sphere::scene;
@@ -139,9 +379,25 @@ fn main() {
     // let scene = scene::book_2::chap_03b_checker_spheres::scene;
     // let scene = scene::book_2::chap_04a_perlin_spheres::scene;
     let scene = scene::book_2::chap_04b_scaled_perlin_spheres::scene;
+    // let scene = scene::book_2::chap_06_simple_light::scene;
+    // let scene = scene::book_2::chap_08a_volumes::scene;
+    // let scene = scene::book_2::chap_08b_heterogeneous_volumes::scene;
+    // let scene = scene::animated::orbit::scene;
+    // let scene = scene::showcase::coloured_glass::scene;
+    // let scene = scene::showcase::dispersion::scene;
+    // let scene = scene::showcase::layered::scene;
+    // let scene = scene::showcase::voxel_smoke::scene;
 
-    render(settings, scene)
-        .as_image()
-        .save("image.png")
-        .unwrap();
+    match settings.animation {
+        Some(animation) => {
+            for frame in 0..animation.frames {
+                let settings = Settings {
+                    shutter: Some(animation.shutter_interval(frame)),
+                    ..settings.clone()
+                };
+                render_and_save(&settings, scene, Some(frame + 1));
+            }
+        }
+        None => render_and_save(&settings, scene, None),
+    }
 }
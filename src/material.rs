@@ -1,44 +1,157 @@
+mod coated;
 mod dielectric;
+mod diffuse_light;
+mod isotropic;
 mod lambertian;
 mod metal;
+mod mix;
 
 use crate::prelude::*;
-use rand::prelude::*;
 use std::fmt::Debug;
 
+pub use dielectric::Ior;
+
 pub struct Scatter {
     pub attenuation: Col,
     pub scattered: Ray,
+    /// Whether `scattered` was picked from a perfectly (or too nearly) specular lobe, whose
+    /// density can't be evaluated. Lights can't be sampled for such bounces.
+    pub specular: bool,
 }
 
 pub trait Material: Debug {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<Scatter>;
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<Scatter>;
+
+    /// Radiance emitted by the surface back along `r_in`.
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Col {
+        Col::zero()
+    }
+
+    /// The BSDF times the cosine term, for light arriving from `direction` and leaving back
+    /// along `r_in`. Only accounts for the non-specular lobes.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Dir) -> Col {
+        Col::zero()
+    }
+
+    /// Density per unit solid angle with which `scatter` picks `direction` from its non-specular
+    /// lobes.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Dir) -> Float {
+        0.
+    }
+
+    /// Identifies the material for the material ID pass, if it was made by one of the functions
+    /// below.
+    fn id(&self) -> Option<usize> {
+        None
+    }
 }
 
 pub type MaterialBox = Box<Material + Send + Sync>;
 
+/// A material numbered for the material ID pass.
+#[derive(Debug)]
+struct Numbered<M> {
+    material: M,
+    id: usize,
+}
+
+fn numbered<M: Material + Send + Sync + 'static>(material: M) -> MaterialBox {
+    Box::new(Numbered {
+        material,
+        id: crate::id::material(),
+    })
+}
+
+impl<M: Material> Material for Numbered<M> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        self.material.scatter(r_in, rec, sampler)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Col {
+        self.material.emitted(r_in, rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Dir) -> Col {
+        self.material.eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Dir) -> Float {
+        self.material.pdf(r_in, rec, direction)
+    }
+
+    fn id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+}
+
+pub fn coated(ri: Float, base: MaterialBox) -> MaterialBox {
+    numbered(coated::Coated::new(ri, base))
+}
+
 pub fn dielectric(ri: Float) -> MaterialBox {
-    Box::new(dielectric::Dielectric::new(ri))
+    numbered(dielectric::Dielectric::new(ri))
+}
+
+pub fn diffuse_light(emit: TextureBox) -> MaterialBox {
+    numbered(diffuse_light::DiffuseLight::new(emit))
+}
+
+pub fn dispersive_dielectric(ior: Ior) -> MaterialBox {
+    numbered(dielectric::Dielectric::dispersive(ior))
+}
+
+pub fn absorbing_dielectric(ri: Float, colour: Col, density: Float) -> MaterialBox {
+    numbered(dielectric::Dielectric::absorbing(ri, colour, density))
+}
+
+pub fn isotropic(albedo: TextureBox) -> MaterialBox {
+    numbered(isotropic::Isotropic::new(albedo))
 }
 
 pub fn lambertian(albedo: TextureBox) -> MaterialBox {
-    Box::new(lambertian::Lambertian::new(albedo))
+    numbered(lambertian::Lambertian::new(albedo))
 }
 
 pub fn metal(albedo: Col, fuzz: Float) -> MaterialBox {
-    Box::new(metal::Metal::new(albedo, fuzz))
+    numbered(metal::Metal::new(albedo, fuzz))
 }
 
-fn random_in_unit_sphere() -> Dir {
-    let mut rng = thread_rng();
-    loop {
-        let p = 2. * dir(rng.gen(), rng.gen(), rng.gen()) - dir(1., 1., 1.);
-        if p.squared_length() < 1. {
-            return p;
-        }
-    }
+pub fn mix(a: MaterialBox, b: MaterialBox, mask: TextureBox) -> MaterialBox {
+    numbered(mix::Mix::new(a, b, mask))
+}
+
+/// A uniformly distributed direction, from two dimensions of `sampler`.
+fn random_unit_vector(sampler: &mut dyn Sampler) -> Dir {
+    let (u, v) = sampler.next_2d();
+    let z = 1. - 2. * u;
+    let r = (1. - z * z).max(0.).sqrt();
+    let phi = 2. * PI * v;
+    dir(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// A uniformly distributed point in the unit ball, from three dimensions of `sampler`.
+fn random_in_unit_sphere(sampler: &mut dyn Sampler) -> Dir {
+    let d = random_unit_vector(sampler);
+    sampler.next_1d().cbrt() * d
 }
 
 fn reflect(v: Dir, n: Dir) -> Dir {
     v - 2. * v.dot(n) * n
 }
+
+fn refract(v: Dir, n: Dir, ni_over_nt: Float) -> Option<Dir> {
+    let uv = v.unit_vector();
+    let dt = uv.dot(n);
+    let discriminant = 1. - ni_over_nt * ni_over_nt * (1. - dt * dt);
+    if discriminant > 0. {
+        Some(ni_over_nt * (uv - dt * n) - discriminant.sqrt() * n)
+    } else {
+        None
+    }
+}
+
+fn schlick(cosine: Float, ref_idx: Float) -> Float {
+    let r0 = (1. - ref_idx) / (1. + ref_idx);
+    let r0 = r0 * r0;
+    r0 + (1. - r0) * (1. - cosine).powi(5)
+}
@@ -0,0 +1,185 @@
+use super::{reflect, refract, schlick, MaterialBox};
+use crate::prelude::*;
+
+/// A thin dielectric clear-coat over an arbitrary base material (car paint, varnish, plastic).
+///
+/// At each hit the coat reflects specularly with the Fresnel probability given by Schlick's
+/// approximation, otherwise light is refracted into the coat, scatters off the base and is
+/// refracted out again, losing the Fresnel reflection on the way out too. Light the base sends
+/// back beyond the critical angle is reflected inside the coat for good, and counts as absorbed.
+#[derive(Debug)]
+pub struct Coated {
+    ref_idx: Float,
+    base: MaterialBox,
+}
+
+impl Coated {
+    pub fn new(ri: Float, base: MaterialBox) -> Self {
+        Self { ref_idx: ri, base }
+    }
+}
+
+impl Coated {
+    /// Cosine of the angle between `d` and the normal.
+    #[inline]
+    fn cosine(d: Dir, normal: Dir) -> Float {
+        d.dot(normal) / d.length()
+    }
+
+    /// Fraction of the light which goes through the coat, at an angle to the normal outside it
+    /// with cosine `cosine`.
+    #[inline]
+    fn transmitted(&self, cosine: Float) -> Float {
+        1. - schlick(cosine, self.ref_idx)
+    }
+
+    /// `d`, pointing away from the surface outside the coat, as it points inside it.
+    #[inline]
+    fn inside(&self, d: Dir, normal: Dir) -> Dir {
+        // Going into a denser coat, light always gets through
+        -refract(-d, normal, 1. / self.ref_idx).unwrap_or(-d)
+    }
+
+    /// `r_in` as the base sees it, bent by the coat.
+    #[inline]
+    fn refracted(&self, r_in: &Ray, rec: &HitRecord) -> Ray {
+        let d = -self.inside(-r_in.direction(), rec.normal);
+        r_in.spawn(rec.p - d, d)
+    }
+
+    /// For light arriving from `direction` and leaving back along `r_in`: the ray the base sees,
+    /// where the light comes from inside the coat, and the density of `direction` for each unit
+    /// of density of that inside direction, times the chance of `r_in` getting through the coat.
+    /// `None` when the light comes from under the surface.
+    fn through(&self, r_in: &Ray, rec: &HitRecord, direction: Dir) -> Option<(Ray, Dir, Float)> {
+        let cosine = Self::cosine(direction, rec.normal);
+        if cosine <= 0. {
+            return None;
+        }
+        let inside = self.inside(direction, rec.normal);
+        // Refraction squeezes the whole hemisphere outside into a cone inside
+        let jacobian = cosine / (self.ref_idx * self.ref_idx * inside.dot(rec.normal));
+        let entering = self.transmitted(-Self::cosine(r_in.direction(), rec.normal));
+        Some((self.refracted(r_in, rec), inside, entering * jacobian))
+    }
+
+    /// Whether `r_in` reaches the surface from the side with the coat.
+    #[inline]
+    fn coated_side(r_in: &Ray, rec: &HitRecord) -> bool {
+        Self::cosine(r_in.direction(), rec.normal) < 0.
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        if !Self::coated_side(r_in, rec) {
+            return self.base.scatter(r_in, rec, sampler);
+        }
+        let cosine = -Self::cosine(r_in.direction(), rec.normal);
+        if sampler.next_1d() >= self.transmitted(cosine) {
+            let reflected = reflect(r_in.direction(), rec.normal);
+            return Some(Scatter {
+                attenuation: col(1., 1., 1.),
+                scattered: r_in.spawn(rec.p, reflected),
+                specular: true,
+            });
+        }
+
+        let Scatter {
+            attenuation,
+            scattered,
+            specular,
+        } = self
+            .base
+            .scatter(&self.refracted(r_in, rec), rec, sampler)?;
+        let out = refract(scattered.direction(), -rec.normal, self.ref_idx)?;
+        Some(Scatter {
+            attenuation: self.transmitted(Self::cosine(out, rec.normal)) * attenuation,
+            scattered: r_in.spawn(rec.p, out),
+            specular,
+        })
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Col {
+        self.base.emitted(r_in, rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Dir) -> Col {
+        if !Self::coated_side(r_in, rec) {
+            return self.base.eval(r_in, rec, direction);
+        }
+        match self.through(r_in, rec, direction) {
+            Some((refracted, inside, weight)) => {
+                let leaving = self.transmitted(Self::cosine(direction, rec.normal));
+                leaving * weight * self.base.eval(&refracted, rec, inside)
+            }
+            None => Col::zero(),
+        }
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Dir) -> Float {
+        if !Self::coated_side(r_in, rec) {
+            return self.base.pdf(r_in, rec, direction);
+        }
+        match self.through(r_in, rec, direction) {
+            Some((refracted, inside, weight)) => weight * self.base.pdf(&refracted, rec, inside),
+            None => 0.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{lambertian, metal},
+        sampler::Independent,
+        texture::constant_texture,
+    };
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_index_matched_coat_is_invisible_at_normal_incidence() {
+        let mat = Coated::new(1., metal(col(0.4, 0.5, 0.6), 0.));
+        let r_in = Ray::new(Pos::zero(), dir(0., -1., 0.), 0.);
+        let rec = HitRecord {
+            t: 1.,
+            p: pos(0., -1., 0.),
+            normal: dir(0., 1., 0.),
+            mat: &mat,
+            object: 0,
+        };
+
+        let scatter = mat.scatter(&r_in, &rec, &mut Independent::new()).unwrap();
+        assert_eq!(col(0.4, 0.5, 0.6), scatter.attenuation);
+    }
+
+    /// Of the light a diffuse base sends back, only the cone of directions which can leave the
+    /// coat does, a fraction `1 / n²` of it.
+    #[test]
+    fn test_coat_lets_out_the_cone_within_the_critical_angle() {
+        let mat = Coated::new(1.5, lambertian(constant_texture(col(1., 1., 1.))));
+        let r_in = Ray::new(pos(0., 1., 0.), dir(0., -1., 0.), 0.);
+        let rec = HitRecord {
+            t: 1.,
+            p: Pos::zero(),
+            normal: dir(0., 1., 0.),
+            mat: &mat,
+            object: 0,
+        };
+
+        // Midpoint rule over the hemisphere, in the cosine to the normal and the azimuth
+        let n = 400;
+        let mut total = 0.;
+        for i in 0..n {
+            let z = (i as Float + 0.5) / n as Float;
+            for j in 0..n {
+                let phi = 2. * PI * (j as Float + 0.5) / n as Float;
+                let r = (1. - z * z).sqrt();
+                total += mat.pdf(&r_in, &rec, dir(r * phi.cos(), z, r * phi.sin()));
+            }
+        }
+        let total = total * 2. * PI / (n * n) as Float;
+        assert_approx_eq!(mat.transmitted(1.) / (1.5 * 1.5), total, 1e-3);
+    }
+}
@@ -1,65 +1,123 @@
-use super::reflect;
-use crate::prelude::*;
-use rand::prelude::*;
+use super::{reflect, refract, schlick};
+use crate::{prelude::*, spectrum};
 
-#[derive(Debug)]
-pub struct Dielectric {
-    ref_idx: Float,
+/// Wavelength the constant index of a dispersive material is taken at when rendering in RGB: the
+/// sodium d-line, at which catalogues quote refractive indices.
+const D_LINE: Float = 587.6;
+
+/// Index of refraction, possibly varying with wavelength.
+#[derive(Clone, Debug)]
+pub enum Ior {
+    Constant(Float),
+    /// Cauchy's equation `n = a + b / λ²`, with `λ` in micrometres.
+    Cauchy {
+        a: Float,
+        b: Float,
+    },
+    /// The Sellmeier equation `n² = 1 + Σ bᵢ λ² / (λ² - cᵢ)`, with `λ` in micrometres.
+    Sellmeier {
+        b: [Float; 3],
+        c: [Float; 3],
+    },
 }
 
-fn refract(v: Dir, n: Dir, ni_over_nt: Float) -> Option<Dir> {
-    let uv = v.unit_vector();
-    let dt = uv.dot(n);
-    let discriminant = 1. - ni_over_nt * ni_over_nt * (1. - dt * dt);
-    if discriminant > 0. {
-        Some(ni_over_nt * (uv - dt * n) - discriminant.sqrt() * n)
-    } else {
-        None
+impl Ior {
+    /// Index of refraction at a wavelength in nanometres, or at the d-line when the ray isn't
+    /// spectral.
+    pub fn at(&self, wavelength: Option<Float>) -> Float {
+        let l = wavelength.unwrap_or(D_LINE) / 1000.;
+        let l2 = l * l;
+        match self {
+            Ior::Constant(n) => *n,
+            Ior::Cauchy { a, b } => a + b / l2,
+            Ior::Sellmeier { b, c } => {
+                (1. + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<Float>()).sqrt()
+            }
+        }
     }
 }
 
-fn schlick(cosine: Float, ref_idx: Float) -> Float {
-    let r0 = (1. - ref_idx) / (1. + ref_idx);
-    let r0 = r0 * r0;
-    r0 + (1. - r0) * (1. - cosine).powi(5)
+#[derive(Debug)]
+pub struct Dielectric {
+    ior: Ior,
+    absorbance: Col,
 }
 
 impl Dielectric {
     pub fn new(ri: Float) -> Dielectric {
-        Dielectric { ref_idx: ri }
+        Dielectric::dispersive(Ior::Constant(ri))
+    }
+
+    pub fn dispersive(ior: Ior) -> Dielectric {
+        Dielectric {
+            ior,
+            absorbance: Col::zero(),
+        }
+    }
+
+    /// Coloured glass following the Beer–Lambert law: light travelling a distance `d` inside the
+    /// medium is attenuated by `colour` raised to the power `density * d`, so `colour` is the
+    /// tint seen through one unit of glass at density 1. Black channels absorb everything at
+    /// any positive density, and nothing at density 0.
+    pub fn absorbing(ri: Float, colour: Col, density: Float) -> Dielectric {
+        let absorbance = |c: Float| {
+            if density == 0. {
+                0.
+            } else {
+                -density * c.max(Float::MIN_POSITIVE).ln()
+            }
+        };
+        Dielectric {
+            ior: Ior::Constant(ri),
+            absorbance: col(
+                absorbance(colour.r()),
+                absorbance(colour.g()),
+                absorbance(colour.b()),
+            ),
+        }
     }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<Scatter> {
-        let attenuation = col(1., 1., 1.);
-        let (outward_normal, ni_over_nt, cosine) = if r_in.direction().dot(rec.normal) > 0. {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let ref_idx = self.ior.at(r_in.wavelength());
+        let inside = r_in.direction().dot(rec.normal) > 0.;
+        // Refracted into the glass, the ray travels through it up to whatever it hits next, which
+        // may be another object inside, and the light is absorbed on the way there.
+        let medium = if inside {
+            Col::zero()
+        } else {
+            spectrum::project(self.absorbance, r_in.wavelength())
+        };
+        let (outward_normal, ni_over_nt, cosine) = if inside {
             (
                 -rec.normal,
-                self.ref_idx,
-                self.ref_idx * r_in.direction().dot(rec.normal) / r_in.direction().length(),
+                ref_idx,
+                ref_idx * r_in.direction().dot(rec.normal) / r_in.direction().length(),
             )
         } else {
             (
                 rec.normal,
-                1. / self.ref_idx,
+                1. / ref_idx,
                 -r_in.direction().dot(rec.normal) / r_in.direction().length(),
             )
         };
         if let Some(refracted) = refract(r_in.direction(), outward_normal, ni_over_nt) {
-            let reflect_prob = schlick(cosine, self.ref_idx);
+            let reflect_prob = schlick(cosine, ref_idx);
 
-            if thread_rng().gen::<Float>() > reflect_prob {
+            if sampler.next_1d() > reflect_prob {
                 return Some(Scatter {
-                    attenuation,
-                    scattered: Ray::new(rec.p, refracted, r_in.time()),
+                    attenuation: col(1., 1., 1.),
+                    scattered: r_in.spawn(rec.p, refracted).through(medium),
+                    specular: true,
                 });
             }
         }
         let reflected = reflect(r_in.direction(), rec.normal);
         Some(Scatter {
-            attenuation,
-            scattered: Ray::new(rec.p, reflected, r_in.time()),
+            attenuation: col(1., 1., 1.),
+            scattered: r_in.spawn(rec.p, reflected),
+            specular: true,
         })
     }
 }
@@ -69,6 +127,17 @@ mod tests {
     use super::*;
     use assert_approx_eq::assert_approx_eq;
 
+    /// Always draws the same number, to pick refraction over reflection.
+    struct Constant(Float);
+
+    impl Sampler for Constant {
+        fn start_pixel_sample(&mut self, _x: usize, _y: usize, _index: usize) {}
+
+        fn next_1d(&mut self) -> Float {
+            self.0
+        }
+    }
+
     #[test]
     fn test_refract_no_change() {
         let v1 = dir(1., 1., 0.);
@@ -105,9 +174,10 @@ mod tests {
             p: pos(1., 1., 0.),
             normal: dir(0., -1., 0.),
             mat: &mat,
+            object: 0,
         };
 
-        let scatter = mat.scatter(&r_in, &rec);
+        let scatter = mat.scatter(&r_in, &rec, &mut Constant(0.99));
         assert!(scatter.is_some());
         let scattered = scatter.unwrap().scattered.direction();
 
@@ -126,9 +196,10 @@ mod tests {
             p: pos(0.5, 3f32.sqrt() / 2., 0.),
             normal: dir(0., 1., 0.),
             mat: &mat,
+            object: 0,
         };
 
-        let scatter = mat.scatter(&r_in, &rec);
+        let scatter = mat.scatter(&r_in, &rec, &mut Constant(0.99));
         assert!(scatter.is_some());
         let scattered = scatter.unwrap().scattered.direction();
 
@@ -137,4 +208,71 @@ mod tests {
         assert_approx_eq!(v2.y(), scattered.y());
         assert_approx_eq!(v2.z(), scattered.z());
     }
+
+    #[test]
+    fn test_dispersion_bends_blue_more_than_red() {
+        let bk7 = Ior::Sellmeier {
+            b: [1.039_612, 0.231_792_34, 1.010_469_5],
+            c: [0.006_000_699, 0.020_017_914, 103.560_65],
+        };
+        assert_approx_eq!(1.5168, bk7.at(None), 1e-4);
+        assert!(bk7.at(Some(450.)) > bk7.at(Some(650.)));
+
+        let cauchy = Ior::Cauchy {
+            a: 1.5046,
+            b: 0.0042,
+        };
+        assert!(cauchy.at(Some(450.)) > cauchy.at(Some(650.)));
+    }
+
+    #[test]
+    fn test_absorbing_dielectric_attenuates_by_distance_inside() {
+        let mat = Dielectric::absorbing(1., col(0.5, 0.8, 1.), 1.);
+        let normal = dir(0., 1., 0.);
+
+        let entering = Ray::new(pos(0., 1., 0.), dir(0., -1., 0.), 0.);
+        let rec = HitRecord {
+            t: 1.,
+            p: Pos::zero(),
+            normal,
+            mat: &mat,
+            object: 0,
+        };
+        let Scatter {
+            attenuation,
+            scattered,
+            ..
+        } = mat.scatter(&entering, &rec, &mut Constant(0.99)).unwrap();
+        assert_eq!(col(1., 1., 1.), attenuation);
+        assert_eq!(col(1., 1., 1.), entering.transmittance(1.));
+        // Whatever it reaches two units on, in the glass
+        let transmittance = scattered.transmittance(2.);
+        assert_approx_eq!(0.25, transmittance.r());
+        assert_approx_eq!(0.64, transmittance.g());
+        assert_approx_eq!(1., transmittance.b());
+
+        let exiting = scattered.spawn(pos(0., -2., 0.), dir(0., 1., 0.));
+        let rec = HitRecord {
+            t: 2.,
+            p: Pos::zero(),
+            normal,
+            mat: &mat,
+            object: 0,
+        };
+        let scattered = mat
+            .scatter(&exiting, &rec, &mut Constant(0.99))
+            .unwrap()
+            .scattered;
+        assert_eq!(col(1., 1., 1.), scattered.transmittance(2.));
+    }
+
+    #[test]
+    fn test_black_glass_is_opaque_but_clear_at_density_zero() {
+        let black = col(0., 0., 0.);
+        let r = Ray::new(Pos::zero(), dir(0., 1., 0.), 0.);
+        let clear = r.through(Dielectric::absorbing(1.5, black, 0.).absorbance);
+        assert_eq!(col(1., 1., 1.), clear.transmittance(1.));
+        let opaque = r.through(Dielectric::absorbing(1.5, black, 1.).absorbance);
+        assert!(opaque.transmittance(1.).r() < 1e-30);
+    }
 }
@@ -0,0 +1,28 @@
+use crate::prelude::*;
+
+/// An emitter which shines equally in all directions and doesn't reflect anything.
+#[derive(Debug)]
+pub struct DiffuseLight {
+    emit: TextureBox,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: TextureBox) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<Scatter> {
+        None
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Col {
+        self.emit.spectral_value(0., 0., rec.p, r_in.wavelength())
+    }
+}
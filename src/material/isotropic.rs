@@ -0,0 +1,38 @@
+use super::random_unit_vector;
+use crate::prelude::*;
+
+/// Phase function of participating media: scatters uniformly in all directions.
+#[derive(Debug)]
+pub struct Isotropic {
+    albedo: TextureBox,
+}
+
+impl Isotropic {
+    pub fn new(albedo: TextureBox) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        &HitRecord { p, .. }: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Scatter> {
+        Some(Scatter {
+            scattered: r_in.spawn(p, random_unit_vector(sampler)),
+            attenuation: self.albedo.spectral_value(0., 0., p, r_in.wavelength()),
+            specular: false,
+        })
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Dir) -> Col {
+        self.albedo.spectral_value(0., 0., rec.p, r_in.wavelength())
+            * self.pdf(r_in, rec, direction)
+    }
+
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Dir) -> Float {
+        1. / (4. * PI)
+    }
+}
@@ -1,4 +1,4 @@
-use super::random_in_unit_sphere;
+use super::random_unit_vector;
 use crate::prelude::*;
 
 #[derive(Debug)]
@@ -13,13 +13,31 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, r_in: &Ray, &HitRecord { p, normal, .. }: &HitRecord) -> Option<Scatter> {
-        let target = p + normal + random_in_unit_sphere();
-        let scattered = Ray::new(p, target - p, r_in.time());
-        let attenuation = self.albedo.value(0., 0., p);
+    /// Offsetting the normal by a random unit vector gives directions distributed according to
+    /// the cosine to the normal, so the attenuation is just the albedo.
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        &HitRecord { p, normal, .. }: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Scatter> {
+        let target = p + normal + random_unit_vector(sampler);
+        let scattered = r_in.spawn(p, target - p);
+        let attenuation = self.albedo.spectral_value(0., 0., p, r_in.wavelength());
         Some(Scatter {
             scattered,
             attenuation,
+            specular: false,
         })
     }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Dir) -> Col {
+        self.albedo.spectral_value(0., 0., rec.p, r_in.wavelength())
+            * self.pdf(r_in, rec, direction)
+    }
+
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Dir) -> Float {
+        let cosine = rec.normal.dot(direction.unit_vector());
+        cosine.max(0.) / PI
+    }
 }
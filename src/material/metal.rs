@@ -1,5 +1,5 @@
 use super::{random_in_unit_sphere, reflect};
-use crate::prelude::*;
+use crate::{prelude::*, spectrum};
 
 #[derive(Debug)]
 pub struct Metal {
@@ -14,20 +14,22 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, &HitRecord { p, normal, .. }: &HitRecord) -> Option<Scatter> {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        &HitRecord { p, normal, .. }: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Scatter> {
         let reflected = reflect(r_in.direction().unit_vector(), normal);
 
-        let scattered = Ray::new(
-            p,
-            reflected + self.fuzz * random_in_unit_sphere(),
-            r_in.time(),
-        );
-        let attenuation = self.albedo;
+        let scattered = r_in.spawn(p, reflected + self.fuzz * random_in_unit_sphere(sampler));
+        let attenuation = spectrum::project(self.albedo, r_in.wavelength());
 
         if scattered.direction().dot(normal) > 0. {
             Some(Scatter {
                 scattered,
                 attenuation,
+                specular: true,
             })
         } else {
             None
@@ -0,0 +1,84 @@
+use super::MaterialBox;
+use crate::prelude::*;
+
+/// Blends two materials according to a mask: where the mask is black the surface behaves like
+/// `a`, where it is white like `b`. Each scattering event picks one of the two stochastically,
+/// so the average over many samples is the weighted blend.
+#[derive(Debug)]
+pub struct Mix {
+    a: MaterialBox,
+    b: MaterialBox,
+    mask: TextureBox,
+}
+
+impl Mix {
+    pub fn new(a: MaterialBox, b: MaterialBox, mask: TextureBox) -> Self {
+        Self { a, b, mask }
+    }
+
+    #[inline]
+    fn weight(&self, p: Pos) -> Float {
+        let m = self.mask.value(0., 0., p);
+        (m.r() + m.g() + m.b()) / 3.
+    }
+}
+
+impl Material for Mix {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        if sampler.next_1d() < self.weight(rec.p) {
+            self.b.scatter(r_in, rec, sampler)
+        } else {
+            self.a.scatter(r_in, rec, sampler)
+        }
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Col {
+        let m = self.weight(rec.p);
+        (1. - m) * self.a.emitted(r_in, rec) + m * self.b.emitted(r_in, rec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Dir) -> Col {
+        let m = self.weight(rec.p);
+        (1. - m) * self.a.eval(r_in, rec, direction) + m * self.b.eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Dir) -> Float {
+        let m = self.weight(rec.p);
+        (1. - m) * self.a.pdf(r_in, rec, direction) + m * self.b.pdf(r_in, rec, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::metal, sampler::Independent, texture::constant_texture};
+
+    fn scatter_with_mask(mask: Col) -> Col {
+        let mat = Mix::new(
+            metal(col(0.1, 0.2, 0.3), 0.),
+            metal(col(0.4, 0.5, 0.6), 0.),
+            constant_texture(mask),
+        );
+        let r_in = Ray::new(Pos::zero(), dir(1., -1., 0.), 0.);
+        let rec = HitRecord {
+            t: 1.,
+            p: pos(1., -1., 0.),
+            normal: dir(0., 1., 0.),
+            mat: &mat,
+            object: 0,
+        };
+        mat.scatter(&r_in, &rec, &mut Independent::new())
+            .unwrap()
+            .attenuation
+    }
+
+    #[test]
+    fn test_mix_black_mask_picks_first() {
+        assert_eq!(col(0.1, 0.2, 0.3), scatter_with_mask(col(0., 0., 0.)));
+    }
+
+    #[test]
+    fn test_mix_white_mask_picks_second() {
+        assert_eq!(col(0.4, 0.5, 0.6), scatter_with_mask(col(1., 1., 1.)));
+    }
+}
@@ -1,11 +1,82 @@
-use crate::prelude::*;
+use crate::{
+    aov::{self, Aovs, Channel},
+    denoise::{self, Denoise, Features, Guide},
+    filter::Filter,
+    prelude::*,
+    spectrum,
+};
 use image::{ImageBuffer, Rgb};
-use rayon::prelude::*;
+use std::{ops::Range, time::Duration};
 
+/// Running sums for one pixel: of the samples spread onto it by the filter, to reconstruct the
+/// image, and of the samples taken within it, to tell how noisy it is.
+#[derive(Clone, Copy, Debug)]
+struct Pixel {
+    /// Sum of the nearby samples, weighted by the filter.
+    weighted: Col,
+    weight: Float,
+    sum: Col,
+    /// Sum of the squared luminances, to estimate the variance.
+    sum_sq: Float,
+    samples: usize,
+    /// Time spent tracing the samples taken within the pixel.
+    time: Duration,
+}
+
+impl Pixel {
+    fn new() -> Pixel {
+        Pixel {
+            weighted: Col::zero(),
+            weight: 0.,
+            sum: Col::zero(),
+            sum_sq: 0.,
+            samples: 0,
+            time: Duration::default(),
+        }
+    }
+
+    fn mean(&self) -> Col {
+        if self.weight == 0. {
+            Col::zero()
+        } else {
+            self.weighted / self.weight
+        }
+    }
+
+    /// Variance of the estimate of the mean luminance.
+    fn variance(&self) -> Float {
+        if self.samples < 2 {
+            return Float::MAX;
+        }
+        let n = self.samples as Float;
+        let mean = self.sum.luminance() / n;
+        ((self.sum_sq - mean * mean * n) / (n - 1.)).max(0.) / n
+    }
+
+    /// Half the width of the 95% confidence interval of the luminance, as displayed.
+    fn noise(&self) -> Float {
+        if self.samples < 2 {
+            return Float::MAX;
+        }
+        let interval = 1.96 * self.variance().sqrt();
+        if interval == 0. {
+            return 0.;
+        }
+        // The image is displayed with a gamma of 2, which stretches differences in the shadows
+        let mean = self.sum.luminance() / self.samples as Float;
+        interval / (2. * mean.max(1e-4).sqrt())
+    }
+}
+
+/// Accumulates the samples taken for each pixel of some rows of an image.
 pub struct Pixbuf {
     w: usize,
     h: usize,
-    pixels: Vec<Col>,
+    /// The rows held, of the `h` in the whole image.
+    rows: Range<usize>,
+    pixels: Vec<Pixel>,
+    /// Sums of the output variables of the samples of each pixel, when recorded.
+    aovs: Option<Vec<Aovs>>,
 }
 
 fn as_u8(f: Float) -> u8 {
@@ -16,57 +87,517 @@ fn as_u8(f: Float) -> u8 {
     }
 }
 
+/// Converts radiance to a displayed pixel, with a gamma of 2.
+fn display(c: Col) -> Rgb<u8> {
+    // Filters with negative lobes can ring below zero around bright edges
+    let c = col(
+        c.r().max(0.).sqrt(),
+        c.g().max(0.).sqrt(),
+        c.b().max(0.).sqrt(),
+    );
+    Rgb([as_u8(c.r()), as_u8(c.g()), as_u8(c.b())])
+}
+
+/// Colour ramp from black through purple, red and yellow to white, for `t` in [0, 1].
+pub fn heat(t: Float) -> Col {
+    const STOPS: [(Float, Float, Float); 5] = [
+        (0., 0., 0.),
+        (0.4, 0., 0.6),
+        (0.9, 0.1, 0.),
+        (1., 0.8, 0.),
+        (1., 1., 1.),
+    ];
+    let x = t.clamp(0., 1.) * (STOPS.len() - 1) as Float;
+    let i = (x as usize).min(STOPS.len() - 2);
+    let f = x - i as Float;
+    let (a, b) = (STOPS[i], STOPS[i + 1]);
+    col(
+        a.0 + f * (b.0 - a.0),
+        a.1 + f * (b.1 - a.1),
+        a.2 + f * (b.2 - a.2),
+    )
+}
+
 impl Pixbuf {
+    /// The whole of an image `w` by `h` pixels.
     pub fn new(w: usize, h: usize) -> Pixbuf {
+        Pixbuf::rows(w, h, 0..h, 0.)
+    }
+
+    /// Just enough of an image `w` by `h` pixels for samples taken in `rows`: those rows, and
+    /// the ones a filter of radius `filter_radius` spreads the samples onto.
+    pub fn rows(w: usize, h: usize, rows: Range<usize>, filter_radius: Float) -> Pixbuf {
+        let margin = filter_radius.ceil() as usize;
+        let rows = rows.start.saturating_sub(margin)..(rows.end + margin).min(h);
         Pixbuf {
             w,
             h,
-            pixels: vec![Col::zero(); w * h],
+            pixels: vec![Pixel::new(); w * rows.len()],
+            rows,
+            aovs: None,
         }
     }
 
-    pub fn put(&mut self, x: usize, y: usize, c: Col) {
-        debug_assert!(x < self.w);
-        debug_assert!(y < self.h);
-        self.pixels[x + self.w * y] = c;
+    /// The same pixbuf, also recording the output variables of the samples.
+    pub fn with_aovs(self) -> Pixbuf {
+        Pixbuf {
+            aovs: Some(vec![Aovs::zero(); self.pixels.len()]),
+            ..self
+        }
+    }
+
+    /// Index in `pixels` of the pixel (`i`, `j`) of the image.
+    #[inline]
+    fn index(&self, i: usize, j: usize) -> usize {
+        debug_assert!(i < self.w);
+        debug_assert!(self.rows.contains(&j));
+        i + self.w * (j - self.rows.start)
+    }
+
+    /// The pixel of the image (`x`, `y`) is in.
+    fn index_at(&self, x: Float, y: Float) -> usize {
+        debug_assert!(x >= 0. && x <= self.w as Float);
+        debug_assert!(y >= 0. && y <= self.h as Float);
+        // A sample near the far edge of the last pixel can round onto the edge of the image
+        let i = (x as usize).min(self.w - 1);
+        let j = (y as usize).min(self.h - 1);
+        self.index(i, j)
+    }
+
+    /// The output variables recorded for each pixel.
+    fn aovs(&self) -> &[Aovs] {
+        self.aovs
+            .as_ref()
+            .expect("The output variables of the samples weren't recorded")
+    }
+
+    /// Records a sample taken at (`x`, `y`) in pixel units, with the origin at the top left corner
+    /// of the image, and spreads it over the pixels whose centres are within the radius of
+    /// `filter`.
+    pub fn add_sample(&mut self, filter: &dyn Filter, x: Float, y: Float, c: Col) {
+        let index = self.index_at(x, y);
+        let pixel = &mut self.pixels[index];
+        let l = c.luminance();
+        pixel.sum += c;
+        pixel.sum_sq += l * l;
+        pixel.samples += 1;
+
+        // Pixels with centres in (x - radius, x + radius], so that with a box filter of radius
+        // one half each sample lands in exactly one pixel
+        let radius = filter.radius();
+        let range = |p: Float, n: usize| {
+            let lo = ((p - radius - 0.5).floor() + 1.).max(0.) as usize;
+            let hi = (p + radius - 0.5).floor().min(n as Float - 1.);
+            let hi = if hi < 0. { 0 } else { hi as usize + 1 };
+            lo..hi
+        };
+        for j in range(y, self.h) {
+            for i in range(x, self.w) {
+                let weight = filter.eval(x - (i as Float + 0.5), y - (j as Float + 0.5));
+                let index = self.index(i, j);
+                let pixel = &mut self.pixels[index];
+                pixel.weighted += weight * c;
+                pixel.weight += weight;
+            }
+        }
+    }
+
+    /// Records a sample taken at (`x`, `y`) with its output variables. In spectral renders, the
+    /// sample was traced at a single `wavelength`, and its lighting is turned into RGB here, so
+    /// that the image and the lighting output variables see the same colours. The albedo stays
+    /// the grey reflectance at the wavelength: as a guide for the denoiser, it is much better
+    /// than the far noisier colour estimated from a single wavelength.
+    pub fn add(
+        &mut self,
+        filter: &dyn Filter,
+        x: Float,
+        y: Float,
+        mut sample: Aovs,
+        wavelength: Option<Float>,
+    ) {
+        if let Some(wavelength) = wavelength {
+            let rgb = |c: Col| spectrum::to_rgb(wavelength, c.r());
+            sample.lighting.direct = rgb(sample.lighting.direct);
+            sample.lighting.indirect = rgb(sample.lighting.indirect);
+        }
+        self.add_sample(filter, x, y, sample.lighting.total());
+        self.add_aovs(x, y, sample);
+    }
+
+    /// Records the output variables of a sample taken at (`x`, `y`), after the sample itself,
+    /// if the pixbuf records them.
+    pub fn add_aovs(&mut self, x: Float, y: Float, aovs: Aovs) {
+        let index = self.index_at(x, y);
+        if let Some(sums) = &mut self.aovs {
+            sums[index] += aovs;
+        }
+    }
+
+    /// A copy of the image smoothed by a joint bilateral filter, guided by the features recorded
+    /// for each pixel.
+    pub fn denoised(&self, params: &Denoise) -> Pixbuf {
+        let guides: Vec<Guide> = self
+            .pixels
+            .iter()
+            .zip(self.aovs())
+            .map(|(p, aovs)| Guide {
+                colour: p.mean(),
+                variance: p.variance().min(1e6),
+                features: Features {
+                    albedo: aovs.albedo / p.samples.max(1) as Float,
+                    normal: aovs.normal / aovs.hits.max(1) as Float,
+                    depth: aovs.depth / aovs.hits.max(1) as Float,
+                },
+            })
+            .collect();
+        let colours = denoise::joint_bilateral(self.w, self.h, &guides, params);
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(colours)
+            .map(|(p, c)| Pixel {
+                weighted: c,
+                weight: 1.,
+                ..*p
+            })
+            .collect();
+        Pixbuf {
+            w: self.w,
+            h: self.h,
+            rows: self.rows.clone(),
+            pixels,
+            aovs: self.aovs.clone(),
+        }
     }
 
     fn get(&self, x: usize, y: usize) -> Col {
-        debug_assert!(x < self.w);
-        debug_assert!(y < self.h);
-        self.pixels[x + self.w * y]
+        self.pixels[self.index(x, y)].mean()
+    }
+
+    /// For each pixel, whether it has had at least `min_samples` samples and its noise is below
+    /// `target_noise`, measured as half the 95% confidence interval of its displayed brightness.
+    pub fn converged(&self, target_noise: Float, min_samples: usize) -> Vec<bool> {
+        self.pixels
+            .iter()
+            .map(|p| p.samples >= min_samples && p.noise() <= target_noise)
+            .collect()
     }
 
     pub fn as_image(&self) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
         let mut img = ImageBuffer::new(self.w as u32, self.h as u32);
         for (i, j, p) in img.enumerate_pixels_mut() {
-            let c = self.get(i as usize, j as usize);
-            let col = col(c.r().sqrt(), c.g().sqrt(), c.b().sqrt());
+            *p = display(self.get(i as usize, j as usize));
+        }
+        img
+    }
 
-            let ir = as_u8(col.r());
-            let ig = as_u8(col.g());
-            let ib = as_u8(col.b());
-            *p = Rgb([ir, ig, ib]);
+    /// Image of one of the output variables recorded with the samples.
+    pub fn aov_image(&self, channel: Channel) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let all = self.aovs();
+        let hit = |a: &Aovs| a.hits.max(1) as Float;
+        let hits = || all.iter().filter(|a| a.hits > 0);
+        let max_depth = hits().map(|a| a.depth / hit(a)).fold(0., Float::max);
+        let far = Float::MAX;
+        let (mut min, mut max) = (pos(far, far, far), pos(-far, -far, -far));
+        for a in hits() {
+            let position = Pos::zero() + (a.position - Pos::zero()) / hit(a);
+            for axis in 0..3 {
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        }
+
+        let mut img = ImageBuffer::new(self.w as u32, self.h as u32);
+        for (i, j, p) in img.enumerate_pixels_mut() {
+            let index = self.index(i as usize, j as usize);
+            let aovs = &all[index];
+            let samples = self.pixels[index].samples.max(1) as Float;
+            let c = match channel {
+                Channel::Depth if aovs.hits == 0 => Col::zero(),
+                Channel::Depth => {
+                    let grey = 1. - aovs.depth / hit(aovs) / max_depth.max(1e-6);
+                    col(grey, grey, grey)
+                }
+                Channel::Position if aovs.hits == 0 => Col::zero(),
+                Channel::Position => {
+                    let position = (aovs.position - Pos::zero()) / hit(aovs);
+                    let t = |axis: usize| {
+                        (position[axis] - min[axis]) / (max[axis] - min[axis]).max(1e-6)
+                    };
+                    col(t(0), t(1), t(2))
+                }
+                Channel::Normal if aovs.hits == 0 => Col::zero(),
+                Channel::Normal => {
+                    let n = aovs.normal / hit(aovs);
+                    0.5 * col(n.x() + 1., n.y() + 1., n.z() + 1.)
+                }
+                Channel::Albedo => aovs.albedo / samples,
+                Channel::MaterialId => aovs.material.map_or(Col::zero(), aov::id_colour),
+                Channel::ObjectId => aovs.object.map_or(Col::zero(), aov::id_colour),
+                Channel::Direct => {
+                    *p = display(aovs.lighting.direct / samples);
+                    continue;
+                }
+                Channel::Indirect => {
+                    *p = display(aovs.lighting.indirect / samples);
+                    continue;
+                }
+            };
+            *p = Rgb([as_u8(c.r()), as_u8(c.g()), as_u8(c.b())]);
+        }
+        img
+    }
+
+    /// One of the output variables which hold measurements, as a Portable Float Map: greyscale
+    /// for depth, colour for positions. Pixels where no camera ray hit anything have an infinite
+    /// depth and a zero position.
+    pub fn aov_floats(&self, channel: Channel) -> Vec<u8> {
+        assert!(
+            channel.floats(),
+            "The {} channel isn't saved as floats",
+            channel.name()
+        );
+        let header = if channel == Channel::Depth {
+            "Pf"
+        } else {
+            "PF"
+        };
+        // The negative scale says the floats are little-endian
+        let mut pfm = format!("{}\n{} {}\n-1.0\n", header, self.w, self.h).into_bytes();
+        let mut put = |v: f32| pfm.extend_from_slice(&v.to_le_bytes());
+        // Rows go from the bottom up
+        for row in self.aovs().chunks(self.w).rev() {
+            for aovs in row {
+                let hits = aovs.hits as Float;
+                match channel {
+                    Channel::Depth if aovs.hits == 0 => put(Float::INFINITY),
+                    Channel::Depth => put(aovs.depth / hits),
+                    _ => {
+                        let position = (aovs.position - Pos::zero()) / hits.max(1.);
+                        put(position.x());
+                        put(position.y());
+                        put(position.z());
+                    }
+                }
+            }
+        }
+        pfm
+    }
+
+    /// Records `time` spent tracing a sample in the pixel (`i`, `j`).
+    pub fn add_time(&mut self, i: usize, j: usize, time: Duration) {
+        let index = self.index(i, j);
+        self.pixels[index].time += time;
+    }
+
+    /// Time spent tracing the samples of each pixel, row by row from the top.
+    pub fn times(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.pixels.iter().map(|p| p.time)
+    }
+
+    /// Heat map of the time spent on each pixel, relative to the most any took.
+    pub fn time_image(&self) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let max = self.times().max().unwrap_or_default().as_secs_f64();
+        let mut img = ImageBuffer::new(self.w as u32, self.h as u32);
+        for (i, j, p) in img.enumerate_pixels_mut() {
+            let time = self.pixels[self.index(i as usize, j as usize)].time;
+            let t = if max > 0. {
+                (time.as_secs_f64() / max) as Float
+            } else {
+                0.
+            };
+            let c = heat(t);
+            *p = Rgb([as_u8(c.r()), as_u8(c.g()), as_u8(c.b())]);
+        }
+        img
+    }
+
+    /// Heat map of the number of samples spent on each pixel, relative to the most any got.
+    pub fn samples_image(&self) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let max = self
+            .pixels
+            .iter()
+            .map(|p| p.samples)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let mut img = ImageBuffer::new(self.w as u32, self.h as u32);
+        for (i, j, p) in img.enumerate_pixels_mut() {
+            let samples = self.pixels[self.index(i as usize, j as usize)].samples;
+            let c = heat(samples as Float / max as Float);
+            *p = Rgb([as_u8(c.r()), as_u8(c.g()), as_u8(c.b())]);
         }
         img
     }
 }
 
 impl std::ops::AddAssign for Pixbuf {
-    /// A bit of a wonky add_assign, since it only works for pixbufs with the same dimension,
-    /// which constitutes a strengthening of the preconditions. Out of the window goes Barbara
-    /// Liskov. Sorry Barb.
+    /// A bit of a wonky add_assign, since it only works for pixbufs of the same image, holding
+    /// at least the rows of the one added, which constitutes a strengthening of the
+    /// preconditions. Out of the window goes Barbara Liskov. Sorry Barb.
     fn add_assign(&mut self, i: Self) {
         assert_eq!(self.w, i.w);
         assert_eq!(self.h, i.h);
-        for (d, s) in self.pixels.iter_mut().zip(i.pixels.iter()) {
-            *d += *s;
+        if i.pixels.is_empty() {
+            return;
+        }
+        assert!(self.rows.start <= i.rows.start && i.rows.end <= self.rows.end);
+        let offset = self.index(0, i.rows.start);
+        let end = offset + i.pixels.len();
+        for (d, s) in self.pixels[offset..end].iter_mut().zip(i.pixels.iter()) {
+            d.weighted += s.weighted;
+            d.weight += s.weight;
+            d.sum += s.sum;
+            d.sum_sq += s.sum_sq;
+            d.samples += s.samples;
+            d.time += s.time;
+        }
+        if let Some(aovs) = i.aovs {
+            let len = self.pixels.len();
+            let sums = self.aovs.get_or_insert_with(|| vec![Aovs::zero(); len]);
+            for (d, s) in sums[offset..end].iter_mut().zip(aovs) {
+                *d += s;
+            }
         }
     }
 }
 
-impl std::ops::DivAssign<usize> for Pixbuf {
-    fn div_assign(&mut self, i: usize) {
-        self.pixels.par_iter_mut().for_each(|c| *c /= i as Float);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        filter::{self, BoxFilter, FilterKind},
+        integrator::Lighting,
+    };
+    use assert_approx_eq::assert_approx_eq;
+
+    const BOX: BoxFilter = BoxFilter { radius: 0.5 };
+
+    #[test]
+    fn test_mean_of_samples() {
+        let mut pixbuf = Pixbuf::new(2, 1);
+        pixbuf.add_sample(&BOX, 1., 0., col(1., 0., 0.));
+        pixbuf.add_sample(&BOX, 1.9, 0.5, col(0., 0., 1.));
+        let mut other = Pixbuf::new(2, 1);
+        other.add_sample(&BOX, 1.5, 0.9, col(0., 1., 0.));
+        pixbuf += other;
+
+        assert_eq!(Col::zero(), pixbuf.get(0, 0));
+        let c = pixbuf.get(1, 0);
+        assert_approx_eq!(1. / 3., c.r());
+        assert_approx_eq!(1. / 3., c.g());
+        assert_approx_eq!(1. / 3., c.b());
+    }
+
+    #[test]
+    fn test_bands_add_into_their_rows() {
+        let mut pixbuf = Pixbuf::new(2, 4).with_aovs();
+        let mut band = Pixbuf::rows(2, 4, 2..3, 0.5);
+        assert_eq!(1..4, band.rows);
+        band.add_sample(&BOX, 1.5, 2.5, col(1., 1., 1.));
+        pixbuf += band;
+
+        let mut band = Pixbuf::rows(2, 4, 0..1, 0.5).with_aovs();
+        let hit = Aovs {
+            hits: 1,
+            depth: 2.,
+            ..Aovs::zero()
+        };
+        band.add_aovs(0.5, 0.5, hit);
+        pixbuf += band;
+
+        for j in 0..4 {
+            for i in 0..2 {
+                let expected = if (i, j) == (1, 2) { 1. } else { 0. };
+                assert_eq!(expected, pixbuf.get(i, j).r());
+            }
+        }
+        assert_eq!(2., pixbuf.aovs()[0].depth);
+        assert_eq!(0, pixbuf.aovs()[5].hits);
+    }
+
+    #[test]
+    fn test_spectral_samples_are_added_as_rgb() {
+        let mut pixbuf = Pixbuf::new(1, 1).with_aovs();
+        let flat = col(1., 1., 1.);
+        let sample = Aovs {
+            albedo: flat,
+            lighting: Lighting {
+                direct: flat,
+                indirect: Col::zero(),
+            },
+            ..Aovs::zero()
+        };
+        pixbuf.add(&BOX, 0.5, 0.5, sample, Some(450.));
+
+        let rgb = spectrum::to_rgb(450., 1.);
+        assert_eq!(rgb, pixbuf.get(0, 0));
+        assert_eq!(flat, pixbuf.aovs()[0].albedo);
+        assert_eq!(rgb, pixbuf.aovs()[0].lighting.direct);
+    }
+
+    #[test]
+    fn test_depth_saved_as_floats() {
+        let mut pixbuf = Pixbuf::new(2, 2).with_aovs();
+        let depth = |depth| Aovs {
+            hits: 1,
+            depth,
+            ..Aovs::zero()
+        };
+        pixbuf.add_aovs(0.5, 0.5, depth(1.));
+        pixbuf.add_aovs(0.5, 0.5, depth(2.));
+        pixbuf.add_aovs(1.5, 1.5, depth(0.25));
+
+        let pfm = pixbuf.aov_floats(Channel::Depth);
+        let header = b"Pf\n2 2\n-1.0\n";
+        assert_eq!(&header[..], &pfm[..header.len()]);
+        let floats: Vec<f32> = pfm[header.len()..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        // From the bottom row up
+        assert_eq!(vec![f32::INFINITY, 0.25, 1.5, f32::INFINITY], floats);
+    }
+
+    #[test]
+    fn test_constant_pixels_converge_and_noisy_ones_do_not() {
+        let mut pixbuf = Pixbuf::new(2, 1);
+        for i in 0..16 {
+            pixbuf.add_sample(&BOX, 0.5, 0.5, col(0.5, 0.5, 0.5));
+            let v = if i % 2 == 0 { 0. } else { 1. };
+            pixbuf.add_sample(&BOX, 1.5, 0.5, col(v, v, v));
+        }
+        assert_eq!(vec![true, false], pixbuf.converged(0.01, 16));
+        assert_eq!(vec![false, false], pixbuf.converged(0.01, 17));
+    }
+
+    #[test]
+    fn test_wide_filters_spread_samples_but_keep_flat_images_flat() {
+        let mut settings = crate::settings::low();
+        settings.filter = FilterKind::Mitchell {
+            b: 1. / 3.,
+            c: 1. / 3.,
+        };
+        settings.filter_radius = 2.;
+        let filter = filter::filter(&settings);
+
+        let mut pixbuf = Pixbuf::new(4, 4);
+        pixbuf.add_sample(&*filter, 1.2, 1.7, col(1., 1., 1.));
+        assert!(pixbuf.get(0, 1).r() > 0.);
+        assert!(pixbuf.get(2, 2).r() > 0.);
+        assert_eq!(Col::zero(), pixbuf.get(3, 3));
+
+        let mut pixbuf = Pixbuf::new(4, 4);
+        for j in 0..16 {
+            for i in 0..16 {
+                let (x, y) = (i as Float / 4. + 0.1, j as Float / 4. + 0.1);
+                pixbuf.add_sample(&*filter, x, y, col(0.5, 0.5, 0.5));
+            }
+        }
+        for j in 0..4 {
+            for i in 0..4 {
+                assert_approx_eq!(0.5, pixbuf.get(i, j).r());
+            }
+        }
     }
 }
@@ -2,12 +2,14 @@ pub use crate::{
     hitable::{HitRecord, Hitable},
     material::{Material, Scatter},
     ray::Ray,
+    sampler::Sampler,
     texture::{Texture, TextureBox},
     vec3::{
         col::{col, Col},
         dir::{dir, Dir},
+        onb::Onb,
         pos::{pos, Pos},
         Float, Vector,
     },
 };
-pub use std::f32::{consts::*, MAX};
+pub use std::f32::consts::*;
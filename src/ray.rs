@@ -1,15 +1,48 @@
 use crate::prelude::*;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Ray {
     a: Pos,
     b: Dir,
     time: Float,
+    wavelength: Option<Float>,
+    /// Absorption coefficients of the medium the ray travels through, per unit of distance.
+    absorbance: Col,
 }
 
 impl Ray {
     pub fn new(a: Pos, b: Dir, ti: Float) -> Self {
-        Ray { a, b, time: ti }
+        Ray {
+            a,
+            b,
+            time: ti,
+            wavelength: None,
+            absorbance: Col::zero(),
+        }
+    }
+
+    /// Tags the ray with a single wavelength in nanometres, for spectral rendering.
+    pub fn with_wavelength(self, wavelength: Float) -> Self {
+        Ray {
+            wavelength: Some(wavelength),
+            ..self
+        }
+    }
+
+    /// The same ray, at another time.
+    pub fn with_time(self, time: Float) -> Self {
+        Ray { time, ..self }
+    }
+
+    /// The same ray, travelling through a medium which absorbs `absorbance` per unit of distance.
+    pub fn through(self, absorbance: Col) -> Self {
+        Ray { absorbance, ..self }
+    }
+
+    /// A new ray leaving `a` in direction `b` at the same time and wavelength as this one, and
+    /// through the same medium.
+    pub fn spawn(&self, a: Pos, b: Dir) -> Self {
+        Ray { a, b, ..*self }
     }
 
     pub fn origin(&self) -> Pos {
@@ -24,6 +57,25 @@ impl Ray {
         self.time
     }
 
+    pub fn wavelength(&self) -> Option<Float> {
+        self.wavelength
+    }
+
+    /// Fraction of the light surviving the way from the origin of the ray to `point_at(t)`,
+    /// following the Beer–Lambert law.
+    pub fn transmittance(&self, t: Float) -> Col {
+        if self.absorbance == Col::zero() {
+            return col(1., 1., 1.);
+        }
+        let distance = t * self.b.length();
+        let a = self.absorbance;
+        col(
+            (-a.r() * distance).exp(),
+            (-a.g() * distance).exp(),
+            (-a.b() * distance).exp(),
+        )
+    }
+
     pub fn point_at(&self, t: Float) -> Pos {
         self.a + t * self.b
     }
@@ -0,0 +1,204 @@
+//! Statistics about a render: where the time went and how much tracing it took, written as JSON
+//! so that performance can be followed from one version to the next.
+
+use crate::{
+    pixbuf::Pixbuf,
+    stats::{self, Stats, RAY_KINDS},
+};
+use std::{fmt::Write, time::Duration};
+
+/// Wall-clock time of each phase of a render.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timings {
+    /// Making the scene, but for its bounding hierarchies.
+    pub scene: Duration,
+    pub bvh: Duration,
+    /// Focusing, preprocessing and taking all the samples.
+    pub render: Duration,
+    /// Denoising and saving the images.
+    pub export: Duration,
+}
+
+/// Ratio of two counts, or zero if there was nothing to count.
+fn per(count: usize, of: usize) -> f64 {
+    if of == 0 {
+        0.
+    } else {
+        count as f64 / of as f64
+    }
+}
+
+/// The statistics, timings and time spent on each pixel as a JSON object. Times are in seconds.
+/// Rays and tests are left out unless the build counts them.
+pub fn json(stats: &Stats, timings: &Timings, pixbuf: &Pixbuf) -> String {
+    report(stats, timings, pixbuf, stats::ENABLED)
+}
+
+/// The JSON object, with the counts in `stats` if `counted`.
+fn report(stats: &Stats, timings: &Timings, pixbuf: &Pixbuf, counted: bool) -> String {
+    let mut out = String::new();
+    // Writing to a string can't fail
+    let mut field =
+        |indent: usize, text: String| writeln!(out, "{:1$}{2}", "", indent, text).unwrap();
+
+    field(0, "{".into());
+    field(2, "\"timings\": {".into());
+    let mut phases = vec![
+        ("scene", timings.scene),
+        ("bvh", timings.bvh),
+        ("render", timings.render),
+        ("export", timings.export),
+    ];
+    if !counted {
+        // Building the hierarchies is only timed along with the counts, so the scene includes it
+        phases.remove(1);
+    }
+    for (i, (name, time)) in phases.iter().enumerate() {
+        let comma = if i + 1 < phases.len() { "," } else { "" };
+        field(4, format!("\"{}\": {}{}", name, time.as_secs_f64(), comma));
+    }
+    field(2, "},".into());
+
+    if counted {
+        let rays = stats.total_rays();
+        let render = timings.render.as_secs_f64();
+        let per_second = if render > 0. {
+            rays as f64 / render
+        } else {
+            0.
+        };
+        field(2, "\"rays\": {".into());
+        for &kind in &RAY_KINDS {
+            field(4, format!("\"{}\": {},", kind.name(), stats.rays(kind)));
+        }
+        field(4, format!("\"total\": {},", rays));
+        field(4, format!("\"per_second\": {}", per_second));
+        field(2, "},".into());
+
+        let lengths = stats.path_lengths();
+        field(2, "\"paths\": {".into());
+        field(4, format!("\"count\": {},", lengths.iter().sum::<usize>()));
+        field(
+            4,
+            format!("\"average_length\": {},", stats.average_path_length()),
+        );
+        let lengths: Vec<String> = lengths.iter().map(|n| n.to_string()).collect();
+        field(4, format!("\"by_length\": [{}]", lengths.join(", ")));
+        field(2, "},".into());
+
+        let box_tests = stats.box_tests();
+        let primitive_tests = stats.primitive_tests();
+        field(2, "\"bvh\": {".into());
+        // Each node visited tests the ray against its box, so these also count the nodes visited
+        field(4, format!("\"box_tests\": {},", box_tests));
+        field(
+            4,
+            format!("\"box_tests_per_ray\": {},", per(box_tests, rays)),
+        );
+        field(4, format!("\"primitive_tests\": {},", primitive_tests));
+        field(
+            4,
+            format!(
+                "\"primitive_tests_per_ray\": {}",
+                per(primitive_tests, rays)
+            ),
+        );
+        field(2, "},".into());
+
+        let records: Vec<_> = stats.records().collect();
+        field(2, "\"categories\": {".into());
+        for (i, (category, record)) in records.iter().enumerate() {
+            let comma = if i + 1 < records.len() { "," } else { "" };
+            field(
+                4,
+                format!(
+                    "\"{}\": {{ \"hits\": {}, \"misses\": {} }}{}",
+                    category.name(),
+                    record.hits,
+                    record.misses,
+                    comma
+                ),
+            );
+        }
+        field(2, "},".into());
+    }
+
+    let times: Vec<f64> = pixbuf.times().map(|t| t.as_secs_f64()).collect();
+    // An empty image has no times to sum up
+    let (min, mean, max) = if times.is_empty() {
+        ("null".to_string(), "null".to_string(), "null".to_string())
+    } else {
+        let min = times.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = times.iter().cloned().fold(0., f64::max);
+        let mean = times.iter().sum::<f64>() / times.len() as f64;
+        (min.to_string(), mean.to_string(), max.to_string())
+    };
+    field(2, "\"pixel_time\": {".into());
+    field(4, format!("\"min\": {},", min));
+    field(4, format!("\"mean\": {},", mean));
+    field(4, format!("\"max\": {}", max));
+    field(2, "}".into());
+    field(0, "}".into());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::RayKind;
+
+    #[test]
+    fn test_json() {
+        let mut stats = Stats::new();
+        for &kind in &[RayKind::Primary, RayKind::Primary, RayKind::Shadow] {
+            stats.ray(kind);
+        }
+        stats.path_length(0);
+        stats.path_length(3);
+        let mut pixbuf = Pixbuf::new(2, 1);
+        pixbuf.add_time(1, 0, Duration::from_millis(500));
+        let timings = Timings {
+            render: Duration::from_secs(2),
+            ..Timings::default()
+        };
+
+        let json = report(&stats, &timings, &pixbuf, true);
+        for expected in &[
+            "\"render\": 2,",
+            "\"primary\": 2,",
+            "\"secondary\": 0,",
+            "\"total\": 3,",
+            "\"per_second\": 1.5",
+            "\"average_length\": 1.5,",
+            "\"by_length\": [1, 0, 0, 1]",
+            "\"box_tests\": 0,",
+            "\"min\": 0,",
+            "\"mean\": 0.25,",
+            "\"max\": 0.5",
+        ] {
+            assert!(json.contains(expected), "{} not in {}", expected, json);
+        }
+        assert!(json.trim_end().ends_with('}'));
+    }
+
+    #[test]
+    fn test_json_without_pixels() {
+        let json = report(&Stats::new(), &Timings::default(), &Pixbuf::new(0, 0), true);
+        assert!(json.contains("\"mean\": null,"), "{}", json);
+        assert!(!json.contains("inf") && !json.contains("NaN"), "{}", json);
+    }
+
+    #[test]
+    fn test_json_without_counts() {
+        let mut stats = Stats::new();
+        stats.ray(RayKind::Primary);
+        let json = report(&stats, &Timings::default(), &Pixbuf::new(1, 1), false);
+        assert!(json.contains("\"timings\": {"), "{}", json);
+        assert!(json.contains("\"pixel_time\": {"), "{}", json);
+        assert!(
+            !json.contains("\"rays\"") && !json.contains("\"bvh\""),
+            "{}",
+            json
+        );
+    }
+}
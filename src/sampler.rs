@@ -0,0 +1,224 @@
+//! Sources for the random numbers a camera sample needs: the position in the pixel, the lens,
+//! the time, the wavelength and then every scattering event along the path. Samplers hand these
+//! out one dimension at a time, and the better ones spread each dimension evenly over the
+//! samples of a pixel instead of drawing them independently.
+//!
+//! Free-flight distances in participating media are left out. They are drawn while intersecting
+//! the world, where the bounding hierarchy may test several media along one ray in an order that
+//! depends on the traversal, and delta tracking needs as many numbers as there are null
+//! collisions. Neither fits a fixed dimension of the sample, and taking them from the sampler
+//! would shift every later dimension of the path by a varying amount, so media draw them from
+//! the thread's generator instead.
+
+mod blue_noise;
+mod halton;
+mod independent;
+mod sobol;
+mod stratified;
+
+use crate::{prelude::*, Settings};
+
+pub use independent::Independent;
+
+pub trait Sampler {
+    /// Starts the `index`th sample of the pixel at (`x`, `y`), back at the first dimension.
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize);
+
+    /// The next dimension of the current sample, in [0, 1).
+    fn next_1d(&mut self) -> Float;
+
+    /// The next two dimensions of the current sample, for samplers which stratify pairs of them
+    /// together, such as a point on a disk or a direction.
+    fn next_2d(&mut self) -> (Float, Float) {
+        let u = self.next_1d();
+        (u, self.next_1d())
+    }
+
+    /// Picks one of `n` items.
+    fn next_index(&mut self, n: usize) -> usize {
+        ((self.next_1d() * n as Float) as usize).min(n - 1)
+    }
+}
+
+pub type SamplerBox = Box<dyn Sampler>;
+
+#[derive(Clone, Copy, Debug)]
+pub enum SamplerKind {
+    /// Independent uniform random numbers.
+    Independent,
+    /// Jittered strata, shuffled differently for each pixel and dimension, with pairs of
+    /// dimensions stratified together by correlated multi-jittering.
+    Stratified,
+    /// The Halton sequence, randomly shifted for each pixel.
+    Halton,
+    /// The Sobol (0,2)-sequence for each pair of dimensions, randomly scrambled for each pixel.
+    Sobol,
+    /// The Halton sequence shifted by a blue-noise mask, so that the remaining error looks like
+    /// fine-grained blue noise rather than blotches.
+    BlueNoise,
+}
+
+pub fn sampler(settings: &Settings) -> SamplerBox {
+    match settings.sampler {
+        SamplerKind::Independent => Box::new(Independent::new()),
+        SamplerKind::Stratified => Box::new(stratified::Stratified::new(settings.samples)),
+        SamplerKind::Halton => Box::new(halton::Halton::new()),
+        SamplerKind::Sobol => Box::new(sobol::Sobol::new(settings.samples)),
+        SamplerKind::BlueNoise => Box::new(blue_noise::BlueNoise::new()),
+    }
+}
+
+/// The first primes, used as bases for the dimensions of the Halton sequence. Samplers built on
+/// it fall back to independent random numbers past the last one.
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+/// Mirrors the digits of `i` in `base` around the radix point.
+fn radical_inverse(base: u32, mut i: u64) -> Float {
+    let inv_base = 1. / base as f64;
+    let mut inv = 1.;
+    let mut reversed = 0.;
+    while i > 0 {
+        let digit = i % base as u64;
+        inv *= inv_base;
+        reversed += digit as f64 * inv;
+        i /= base as u64;
+    }
+    (reversed as Float).min(ONE_MINUS_EPSILON)
+}
+
+/// The largest float below one.
+const ONE_MINUS_EPSILON: Float = 1. - Float::EPSILON / 2.;
+
+/// Turns the 32 bits of `bits` into a float in [0, 1).
+fn to_unit(bits: u32) -> Float {
+    (bits >> 8) as Float / (1 << 24) as Float
+}
+
+/// Adds `shift` to `u`, wrapping around to stay in [0, 1).
+fn toroidal_shift(u: Float, shift: Float) -> Float {
+    let v = u + shift;
+    let v = if v >= 1. { v - 1. } else { v };
+    v.min(ONE_MINUS_EPSILON)
+}
+
+/// Mixes its input thoroughly (the SplitMix64 finaliser).
+fn mix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// A well-distributed hash of a few values, used to give each pixel and dimension its own
+/// randomisation without storing anything.
+pub fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x9e37_79b9_7f4a_7c15, |h, &v| mix(h ^ mix(v)))
+}
+
+/// A random permutation of [0, `l`) chosen by `p`, evaluated at `i`, from Kensler's
+/// "Correlated Multi-Jittered Sampling" (2013).
+fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % l
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_radical_inverse() {
+        assert_approx_eq!(0.5, radical_inverse(2, 1));
+        assert_approx_eq!(0.25, radical_inverse(2, 2));
+        assert_approx_eq!(0.75, radical_inverse(2, 3));
+        assert_approx_eq!(1. / 9., radical_inverse(3, 3));
+    }
+
+    #[test]
+    fn test_permute_is_a_permutation() {
+        for &l in &[1, 7, 16, 100] {
+            for p in 0..10 {
+                let mut seen = vec![false; l as usize];
+                for i in 0..l {
+                    seen[permute(i, l, p) as usize] = true;
+                }
+                assert!(seen.iter().all(|&s| s));
+            }
+        }
+    }
+
+    /// Every sampler should cover each dimension evenly over the samples of a pixel: with n
+    /// samples, each of n / 4 equal intervals gets about four of them. (Not one in each of n,
+    /// since the Halton sequence only stratifies by powers of the base of each dimension.)
+    #[test]
+    fn test_samplers_spread_samples_over_each_dimension() {
+        let n = 64;
+        let kinds = [
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+            SamplerKind::BlueNoise,
+        ];
+        for &kind in &kinds {
+            let mut settings = crate::settings::low();
+            settings.samples = n;
+            settings.sampler = kind;
+            let mut sampler = sampler(&settings);
+            for dimension in 0..6 {
+                let bins = n / 4;
+                let mut counts = vec![0; bins];
+                for index in 0..n {
+                    sampler.start_pixel_sample(3, 5, index);
+                    for _ in 0..dimension {
+                        sampler.next_1d();
+                    }
+                    let u = sampler.next_1d();
+                    assert!((0. ..1.).contains(&u));
+                    counts[(u * bins as Float) as usize] += 1;
+                }
+                assert!(
+                    counts.iter().all(|c| (1..=8).contains(c)),
+                    "{:?} dimension {}: {:?}",
+                    kind,
+                    dimension,
+                    counts
+                );
+            }
+        }
+    }
+}
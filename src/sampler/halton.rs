@@ -0,0 +1,45 @@
+use super::{hash, radical_inverse, to_unit, toroidal_shift, Sampler, PRIMES};
+use crate::prelude::*;
+use rand::prelude::*;
+
+/// The Halton sequence, with the next prime as base for each dimension. Every pixel uses the
+/// same points, shifted by a random offset of its own in each dimension (a Cranley-Patterson
+/// rotation) so that neighbouring pixels don't make the same mistakes.
+pub struct Halton {
+    x: usize,
+    y: usize,
+    index: u64,
+    dimension: usize,
+    rng: ThreadRng,
+}
+
+impl Halton {
+    pub fn new() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            index: 0,
+            dimension: 0,
+            rng: thread_rng(),
+        }
+    }
+}
+
+impl Sampler for Halton {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.x = x;
+        self.y = y;
+        self.index = index as u64;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> Float {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        if dimension >= PRIMES.len() {
+            return self.rng.gen();
+        }
+        let shift = to_unit(hash(&[self.x as u64, self.y as u64, dimension as u64]) as u32);
+        toroidal_shift(radical_inverse(PRIMES[dimension], self.index), shift)
+    }
+}
@@ -0,0 +1,22 @@
+use super::Sampler;
+use crate::prelude::*;
+use rand::prelude::*;
+
+/// Independent uniform random numbers, which know nothing of the other samples of the pixel.
+pub struct Independent {
+    rng: ThreadRng,
+}
+
+impl Independent {
+    pub fn new() -> Self {
+        Self { rng: thread_rng() }
+    }
+}
+
+impl Sampler for Independent {
+    fn start_pixel_sample(&mut self, _x: usize, _y: usize, _index: usize) {}
+
+    fn next_1d(&mut self) -> Float {
+        self.rng.gen()
+    }
+}
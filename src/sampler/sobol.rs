@@ -0,0 +1,101 @@
+use super::{hash, mix, to_unit, Sampler};
+use crate::prelude::*;
+
+/// The first two dimensions of the Sobol sequence, which form a (0,2)-sequence: any power of two
+/// consecutive points are stratified in every way a power of two can split the square. Further
+/// pairs of dimensions reuse them with the sample order shuffled, and every pixel and dimension
+/// gets its own random digit scrambling, which keeps the stratification.
+pub struct Sobol {
+    /// Samples per pixel, rounded up to a power of two.
+    samples: u32,
+    x: usize,
+    y: usize,
+    index: u32,
+    dimension: usize,
+}
+
+/// The second dimension of the Sobol sequence, as bits after the radix point.
+fn sobol_2(mut i: u32) -> u32 {
+    let mut v = 1 << 31;
+    let mut bits = 0;
+    while i != 0 {
+        if i & 1 != 0 {
+            bits ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+    bits
+}
+
+impl Sobol {
+    pub fn new(samples: usize) -> Self {
+        Self {
+            samples: samples.max(1).next_power_of_two() as u32,
+            x: 0,
+            y: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// The shuffled sample index and the scrambling bits for the next `dimensions` dimensions.
+    fn next(&mut self, dimensions: usize) -> (u32, u64) {
+        let seed = hash(&[self.x as u64, self.y as u64, self.dimension as u64]);
+        self.dimension += dimensions;
+        let index = if self.index < self.samples {
+            // XORing the low bits permutes the indices below the power of two
+            self.index ^ (seed as u32 & (self.samples - 1))
+        } else {
+            self.index
+        };
+        (index, mix(seed))
+    }
+}
+
+impl Sampler for Sobol {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.x = x;
+        self.y = y;
+        self.index = index as u32;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> Float {
+        let (index, scramble) = self.next(1);
+        to_unit(index.reverse_bits() ^ scramble as u32)
+    }
+
+    fn next_2d(&mut self) -> (Float, Float) {
+        let (index, scramble) = self.next(2);
+        (
+            to_unit(index.reverse_bits() ^ scramble as u32),
+            to_unit(sobol_2(index) ^ (scramble >> 32) as u32),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each quarter of the square in any of the ways to split it in four gets one of four points.
+    #[test]
+    fn test_first_points_are_a_0_2_net() {
+        let points: Vec<(u32, u32)> = (0..4)
+            .map(|i: u32| (i.reverse_bits(), sobol_2(i)))
+            .collect();
+        let mut cells = vec![];
+        for &(x, y) in &points {
+            cells.push((x >> 30, 0));
+            cells.push((x >> 31, y >> 31));
+            cells.push((0, y >> 30));
+        }
+        for split in 0..3 {
+            let mut seen: Vec<_> = cells.iter().skip(split).step_by(3).collect();
+            seen.sort();
+            seen.dedup();
+            assert_eq!(4, seen.len());
+        }
+    }
+}
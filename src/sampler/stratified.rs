@@ -0,0 +1,68 @@
+use super::{hash, permute, Sampler, ONE_MINUS_EPSILON};
+use crate::prelude::*;
+use rand::prelude::*;
+
+/// Splits each dimension into as many strata as there are samples per pixel, and jitters one
+/// sample in each. The strata are visited in a different random order for each pixel and
+/// dimension, so that dimensions don't correlate. Pairs of dimensions are stratified together by
+/// correlated multi-jittering (Kensler, 2013), which keeps each of them stratified on its own.
+pub struct Stratified {
+    samples: u32,
+    x: usize,
+    y: usize,
+    index: u32,
+    dimension: usize,
+    rng: ThreadRng,
+}
+
+impl Stratified {
+    pub fn new(samples: usize) -> Self {
+        Self {
+            samples: samples.max(1) as u32,
+            x: 0,
+            y: 0,
+            index: 0,
+            dimension: 0,
+            rng: thread_rng(),
+        }
+    }
+
+    fn seed(&mut self, dimensions: usize) -> u32 {
+        let seed = hash(&[self.x as u64, self.y as u64, self.dimension as u64]) as u32;
+        self.dimension += dimensions;
+        seed
+    }
+}
+
+impl Sampler for Stratified {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.x = x;
+        self.y = y;
+        self.index = index as u32 % self.samples;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> Float {
+        let p = self.seed(1);
+        let n = self.samples;
+        let stratum = permute(self.index, n, p);
+        ((stratum as Float + self.rng.gen::<Float>()) / n as Float).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (Float, Float) {
+        let p = self.seed(2);
+        let count = self.samples;
+        // The samples are laid out on a grid of `columns` by `rows` cells
+        let columns = (count as Float).sqrt() as u32;
+        let rows = count.div_ceil(columns);
+        let s = permute(self.index, count, p.wrapping_mul(0x5163_3e2d));
+        let (column, row) = (s % columns, s / columns);
+        let sx = permute(column, columns, p.wrapping_mul(0x68bc_21eb));
+        let sy = permute(row, rows, p.wrapping_mul(0x02e5_be93));
+        let (jx, jy) = (self.rng.gen::<Float>(), self.rng.gen::<Float>());
+        let (width, height) = (columns as Float, rows as Float);
+        let u = (column as Float + (sy as Float + jx) / height) / width;
+        let v = (row as Float + (sx as Float + jy) / width) / height;
+        (u.min(ONE_MINUS_EPSILON), v.min(ONE_MINUS_EPSILON))
+    }
+}
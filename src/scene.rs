@@ -1,27 +1,147 @@
+pub mod animated;
 pub mod book_1;
 pub mod book_2;
 mod prelude;
+pub mod showcase;
 
+use crate::stats::{self, RayKind};
 use prelude::*;
 
 fn camera(
-    look_from: Pos,
-    look_at: Pos,
+    placement: Placement,
+    vfov: Float,
+    aperture: Float,
+    focus_dist: Float,
+    settings: &Settings,
+    shutter: (Float, Float),
+) -> CameraBox {
+    let mut aspect = settings.width as Float / (settings.height as Float);
+    if settings.stereo.is_some() {
+        // Each eye gets half the image
+        aspect *= 2.;
+    }
+    let shutter = settings.shutter.unwrap_or(shutter);
+    let (right, _, _) = placement.frame();
+    let eye = |offset: Float| -> CameraBox {
+        // Eyes are side by side, looking in parallel
+        let moved = Placement {
+            look_from: placement.look_from + offset * right,
+            look_at: placement.look_at + offset * right,
+            ..placement
+        };
+        match settings.projection {
+            Projection::Perspective => {
+                let camera = match settings.lens {
+                    Some(lens) => {
+                        let lens = Physical {
+                            shutter: settings.shutter.unwrap_or(lens.shutter),
+                            ..lens
+                        };
+                        Perspective::physical(moved, aspect, &lens, focus_dist)
+                    }
+                    None => Perspective::new(moved, vfov, aspect, aperture, focus_dist, shutter),
+                };
+                Box::new(camera.with_bokeh(&settings.bokeh))
+            }
+            Projection::Orthographic { height } => {
+                Box::new(Orthographic::new(moved, height, aspect, shutter))
+            }
+            Projection::Fisheye { fov, mapping } => {
+                Box::new(Fisheye::new(moved, fov, mapping, aspect, shutter))
+            }
+            Projection::Realistic { path, sensor, unit } => {
+                let lens = Lens::load(path).expect("Couldn't load the lens prescription");
+                let camera = Realistic::new(moved, lens, sensor, aspect, focus_dist, unit, shutter)
+                    .with_bokeh(&settings.bokeh);
+                match settings.lens {
+                    Some(lens) => Box::new(camera.with_physical(&Physical {
+                        shutter: settings.shutter.unwrap_or(lens.shutter),
+                        ..lens
+                    })),
+                    None => Box::new(camera),
+                }
+            }
+            // except in panoramas, where they turn around with the direction they look in
+            Projection::Equirectangular => {
+                Box::new(Equirectangular::new(placement, offset, shutter))
+            }
+        }
+    };
+    let camera = match settings.stereo {
+        Some(separation) => Box::new(Stereo::new(eye(-separation / 2.), eye(separation / 2.))),
+        None => eye(0.),
+    };
+    let (t0, t1) = shutter;
+    match settings.rolling_shutter {
+        Some(readout) => Box::new(RollingShutter::new(camera, t0, t1, readout)),
+        None => camera,
+    }
+}
+
+/// A camera following `path` while the shutter is open, over `shutter` unless overridden by
+/// the settings.
+fn moving_camera(
+    path: &CameraPath,
     up: Dir,
     vfov: Float,
     aperture: Float,
     focus_dist: Float,
     settings: &Settings,
-    t0: Float,
-    t1: Float,
-) -> Camera {
-    let aspect = settings.width as Float / (settings.height as Float);
-    Camera::new(
-        look_from, look_at, up, vfov, aspect, aperture, focus_dist, t0, t1,
-    )
+    shutter: (Float, Float),
+) -> CameraBox {
+    let shutter = settings.shutter.unwrap_or(shutter);
+    let (t0, t1) = shutter;
+    let placement = Placement {
+        look_from: path.look_from.at(t0),
+        look_at: path.look_at.at(t0),
+        up,
+    };
+    let camera = camera(placement, vfov, aperture, focus_dist, settings, shutter);
+    Box::new(Moving::along(camera, path, up, t0, t1))
+}
+
+pub struct Scene {
+    pub camera: CameraBox,
+    pub world: HitableBox,
+    /// Emitters to sample directly at each diffuse hit. They should also be part of `world`.
+    pub lights: Vec<HitableBox>,
+    /// Radiance coming from rays which escape the world.
+    pub background: fn(&Ray) -> Col,
+}
+
+impl Scene {
+    /// The first hit of `r` in the world, counting it as a ray of `kind`.
+    pub fn hit(&self, kind: RayKind, r: &Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        stats::ray(kind);
+        self.world.hit(r, t_min, t_max)
+    }
+
+    /// Traces a shadow ray: the first surface it hits, if any, and the fraction of light which
+    /// gets through the participating media on the way there.
+    pub fn shadow(&self, r: &Ray, t_min: Float, t_max: Float) -> (Option<HitRecord<'_>>, Float) {
+        stats::ray(RayKind::Shadow);
+        let rec = self.world.hit_surface(r, t_min, t_max);
+        let t_max = rec.as_ref().map_or(t_max, |rec| rec.t);
+        (rec, self.world.transmittance(r, t_min, t_max))
+    }
+
+    /// Focuses the camera on whatever is seen through (`u`, `v`).
+    pub fn autofocus(&mut self, u: Float, v: Float) {
+        let world = &self.world;
+        self.camera.autofocus(
+            &mut |r| world.hit(r, 0.001, Float::MAX).map(|rec| rec.t),
+            u,
+            v,
+        );
+    }
+}
+
+pub fn sky(r: &Ray) -> Col {
+    let unit_direction = r.direction().unit_vector();
+    let t = 0.5 * (unit_direction.y() + 1.);
+    (1. - t) * col(1., 1., 1.) + t * col(0.5, 0.7, 1.0)
 }
 
-pub struct Scene<C> {
-    pub camera: Camera,
-    pub world: HitableBox<C>,
+pub fn black(_r: &Ray) -> Col {
+    Col::zero()
 }
@@ -0,0 +1,82 @@
+use crate::scene::prelude::*;
+
+/// Two seconds circling the book's three spheres, while a checkered ball rolls between them.
+/// Render it with `settings.animation` set; stills show its start.
+fn path() -> CameraPath {
+    let around = |degrees: Float, height: Float| {
+        let theta = degrees * PI / 180.;
+        pos(13. * theta.cos(), height, 13. * theta.sin())
+    };
+    CameraPath {
+        look_from: Keyframes::new(
+            Interpolation::CatmullRom,
+            vec![
+                (0., around(10., 2.)),
+                (0.5, around(40., 3.)),
+                (1., around(70., 4.)),
+                (1.5, around(100., 3.)),
+                (2., around(130., 2.)),
+            ],
+        ),
+        look_at: Keyframes::new(
+            Interpolation::Linear,
+            vec![(0., pos(0., 0.5, 0.)), (2., pos(0., 1., 0.))],
+        ),
+    }
+}
+
+fn ball() -> Keyframes<Transform> {
+    // Rolling without slipping turns it by its travel over its radius
+    let rolled = |x: Float| Transform {
+        translation: dir(x, 0.5, 2.),
+        rotation: Rotation::around(dir(0., 0., -1.), (x + 4.) / 0.5 * 180. / PI),
+        scale: 0.5,
+    };
+    // Rotations are blended the short way round, so the keys are 0.8 radians apart, and even
+    // the four a Catmull-Rom stretch blends are within half a turn
+    let keys = (0..=20)
+        .map(|i| {
+            let t = i as Float / 10.;
+            (t, rolled(4. * t - 4.))
+        })
+        .collect();
+    Keyframes::new(Interpolation::CatmullRom, keys)
+}
+
+fn camera(settings: &Settings) -> CameraBox {
+    crate::scene::moving_camera(&path(), dir(0., 1., 0.), 20., 0., 10., settings, (0., 0.))
+}
+
+fn world(t0: Float, t1: Float) -> HitableBox {
+    let checker = checker(
+        constant_texture(col(0.2, 0.3, 0.1)),
+        constant_texture(col(0.9, 0.9, 0.9)),
+    );
+    let list = vec![
+        sphere(
+            pos(0., -1000., 0.),
+            1000.,
+            lambertian(constant_texture(col(0.5, 0.5, 0.5))),
+        ),
+        sphere(pos(0., 1., 0.), 1., dielectric(1.5)),
+        sphere(
+            pos(-4., 1., 0.),
+            1.,
+            lambertian(constant_texture(col(0.4, 0.2, 0.1))),
+        ),
+        sphere(pos(4., 1., 0.), 1., metal(col(0.7, 0.6, 0.5), 0.)),
+        // Blurred as it rolls while the shutter is open
+        animated(sphere(Pos::zero(), 1., lambertian(checker)), ball()),
+    ];
+    bounding_hierarchy(list, t0, t1)
+}
+
+pub fn scene(settings: &Settings) -> Scene {
+    let (t0, t1) = settings.shutter.unwrap_or((0., 0.));
+    Scene {
+        camera: camera(settings),
+        world: world(t0, t1),
+        lights: vec![],
+        background: sky,
+    }
+}
@@ -1,28 +1,31 @@
 use crate::scene::prelude::*;
 
-fn camera(settings: &Settings) -> Camera {
+fn camera(settings: &Settings) -> CameraBox {
     let look_from = pos(0., 0., 0.);
     let look_at = pos(0., 0., -1.);
     crate::scene::camera(
-        look_from,
-        look_at,
-        dir(0., 1., 0.),
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
         90.,
         0.,
         1.,
         settings,
-        0.,
-        0.,
+        (0., 0.),
     )
 }
 
-fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
-    factory.hitable_list(vec![])
+fn world() -> HitableBox {
+    hitable_list(vec![])
 }
 
-pub fn scene<C, H: HitableFactory<C>>(factory: &H, settings: &Settings) -> Scene<C> {
+pub fn scene(settings: &Settings) -> Scene {
     Scene {
         camera: camera(settings),
-        world: world(factory),
+        world: world(),
+        lights: vec![],
+        background: sky,
     }
 }
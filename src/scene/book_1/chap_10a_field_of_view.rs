@@ -1,30 +1,31 @@
 use crate::scene::prelude::*;
 
-fn camera(settings: &Settings) -> Camera {
+fn camera(settings: &Settings) -> CameraBox {
     let look_from = pos(0., 0., 0.);
     let look_at = pos(0., 0., -1.);
     crate::scene::camera(
-        look_from,
-        look_at,
-        dir(0., 1., 0.),
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
         90.,
         0.,
         1.,
         settings,
-        0.,
-        0.,
+        (0., 0.),
     )
 }
 
-fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
+fn world() -> HitableBox {
     let r = (PI / 4.).cos();
-    factory.hitable_list(vec![
-        factory.sphere(
+    hitable_list(vec![
+        sphere(
             pos(-r, 0., -1.),
             r,
             lambertian(constant_texture(col(0., 0., 1.))),
         ),
-        factory.sphere(
+        sphere(
             pos(r, 0., -1.),
             r,
             lambertian(constant_texture(col(1., 0., 0.))),
@@ -32,9 +33,11 @@ fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
     ])
 }
 
-pub fn scene<C, H: HitableFactory<C>>(factory: &H, settings: &Settings) -> Scene<C> {
+pub fn scene(settings: &Settings) -> Scene {
     Scene {
         camera: camera(settings),
-        world: world(factory),
+        world: world(),
+        lights: vec![],
+        background: sky,
     }
 }
@@ -1,25 +1,26 @@
 use crate::scene::prelude::*;
 
-fn camera(settings: &Settings) -> Camera {
+fn camera(settings: &Settings) -> CameraBox {
     let look_from = pos(13., 2., 3.);
     let look_at = pos(0., 0., 0.);
     crate::scene::camera(
-        look_from,
-        look_at,
-        dir(0., 1., 0.),
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
         20.,
         0.1,
         10.,
         settings,
-        0.,
-        0.,
+        (0., 0.),
     )
 }
 
-fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
+fn world() -> HitableBox {
     let mut rng = thread_rng();
     let mut list = vec![];
-    list.push(factory.sphere(
+    list.push(sphere(
         pos(0., -1000., 0.),
         1000.,
         lambertian(constant_texture(col(0.5, 0.5, 0.5))),
@@ -56,24 +57,26 @@ fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
                 b as Float + 0.9 * rng.gen::<Float>(),
             );
             if (center - pos(4., 0.2, 0.)).length() > 0.9 {
-                list.push(factory.sphere(center, 0.2, random_mat()))
+                list.push(sphere(center, 0.2, random_mat()))
             }
         }
     }
 
-    list.push(factory.sphere(pos(0., 1., 0.), 1., dielectric(1.5)));
-    list.push(factory.sphere(
+    list.push(sphere(pos(0., 1., 0.), 1., dielectric(1.5)));
+    list.push(sphere(
         pos(-4., 1., 0.),
         1.,
         lambertian(constant_texture(col(0.4, 0.2, 0.1))),
     ));
-    list.push(factory.sphere(pos(4., 1., 0.), 1., metal(col(0.7, 0.6, 0.5), 0.)));
-    factory.hitable_list(list)
+    list.push(sphere(pos(4., 1., 0.), 1., metal(col(0.7, 0.6, 0.5), 0.)));
+    hitable_list(list)
 }
 
-pub fn scene<C, H: HitableFactory<C>>(factory: &H, settings: &Settings) -> Scene<C> {
+pub fn scene(settings: &Settings) -> Scene {
     Scene {
         camera: camera(settings),
-        world: world(factory),
+        world: world(),
+        lights: vec![],
+        background: sky,
     }
 }
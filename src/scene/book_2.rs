@@ -4,3 +4,6 @@ pub mod chap_03a_checker_floor;
 pub mod chap_03b_checker_spheres;
 pub mod chap_04a_perlin_spheres;
 pub mod chap_04b_scaled_perlin_spheres;
+pub mod chap_06_simple_light;
+pub mod chap_08a_volumes;
+pub mod chap_08b_heterogeneous_volumes;
@@ -1,35 +1,36 @@
 use crate::scene::prelude::*;
 
-fn camera(settings: &Settings) -> Camera {
+fn camera(settings: &Settings) -> CameraBox {
     let look_from = pos(13., 2., 3.);
     let look_at = pos(0., 0., 0.);
     crate::scene::camera(
-        look_from,
-        look_at,
-        dir(0., 1., 0.),
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
         20.,
         0.1,
         10.,
         settings,
-        0.,
-        1.,
+        (0., 1.),
     )
 }
 
-fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
+fn world() -> HitableBox {
     let mut rng = thread_rng();
     let mut list = vec![];
-    list.push(factory.sphere(
+    list.push(sphere(
         pos(0., -1000., 0.),
         1000.,
         lambertian(constant_texture(col(0.5, 0.5, 0.5))),
     ));
 
-    fn random_sphere<C>(factory: &HitableFactory<C>, center: Pos) -> HitableBox<C> {
+    fn random_sphere(center: Pos) -> HitableBox {
         let mut rng = thread_rng();
         let choose_mat = rng.gen::<Float>();
         if choose_mat < 0.8 {
-            factory.moving_sphere(
+            moving_sphere(
                 center,
                 center + dir(0., 0.5 * rng.gen::<Float>(), 0.),
                 0.,
@@ -42,7 +43,7 @@ fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
                 ))),
             )
         } else if choose_mat < 0.95 {
-            factory.sphere(
+            sphere(
                 center,
                 0.2,
                 metal(
@@ -55,7 +56,7 @@ fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
                 ),
             )
         } else {
-            factory.sphere(center, 0.2, dielectric(1.5))
+            sphere(center, 0.2, dielectric(1.5))
         }
     }
 
@@ -67,24 +68,26 @@ fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
                 b as Float + 0.9 * rng.gen::<Float>(),
             );
             if (center - pos(4., 0.2, 0.)).length() > 0.9 {
-                list.push(random_sphere(factory, center))
+                list.push(random_sphere(center))
             }
         }
     }
 
-    list.push(factory.sphere(pos(0., 1., 0.), 1., dielectric(1.5)));
-    list.push(factory.sphere(
+    list.push(sphere(pos(0., 1., 0.), 1., dielectric(1.5)));
+    list.push(sphere(
         pos(-4., 1., 0.),
         1.,
         lambertian(constant_texture(col(0.4, 0.2, 0.1))),
     ));
-    list.push(factory.sphere(pos(4., 1., 0.), 1., metal(col(0.7, 0.6, 0.5), 0.)));
-    factory.hitable_list(list)
+    list.push(sphere(pos(4., 1., 0.), 1., metal(col(0.7, 0.6, 0.5), 0.)));
+    hitable_list(list)
 }
 
-pub fn scene<C, H: HitableFactory<C>>(factory: &H, settings: &Settings) -> Scene<C> {
+pub fn scene(settings: &Settings) -> Scene {
     Scene {
         camera: camera(settings),
-        world: world(factory),
+        world: world(),
+        lights: vec![],
+        background: sky,
     }
 }
@@ -1,30 +1,40 @@
 use crate::scene::prelude::*;
 
-fn camera(settings: &Settings, t0: Float, t1: Float) -> Camera {
+fn camera(settings: &Settings, t0: Float, t1: Float) -> CameraBox {
     let look_from = pos(13., 2., 3.);
     let look_at = pos(0., 0., 0.);
     crate::scene::camera(
-        look_from,
-        look_at,
-        dir(0., 1., 0.),
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
         20.,
         0.1,
         10.,
         settings,
-        t0,
-        t1,
+        (t0, t1),
     )
 }
 
-fn world<C>(factory: &HitableFactory<C>, t0: Float, t1: Float) -> HitableBox<C> {
+/// A small, bright light whose light the glass spheres focus onto the floor.
+fn light() -> HitableBox {
+    sphere(
+        pos(-1.5, 3.5, -2.5),
+        0.5,
+        diffuse_light(constant_texture(col(100., 100., 100.))),
+    )
+}
+
+fn world(light: HitableBox, t0: Float, t1: Float) -> HitableBox {
     let mut rng = thread_rng();
     let mut list = vec![];
 
-    fn random_sphere<C>(factory: &HitableFactory<C>, center: Pos) -> HitableBox<C> {
+    fn random_sphere(center: Pos) -> HitableBox {
         let mut rng = thread_rng();
         let choose_mat = rng.gen::<Float>();
         if choose_mat < 0.8 {
-            factory.moving_sphere(
+            moving_sphere(
                 center,
                 center + dir(0., 0.5 * rng.gen::<Float>(), 0.),
                 0.,
@@ -37,7 +47,7 @@ fn world<C>(factory: &HitableFactory<C>, t0: Float, t1: Float) -> HitableBox<C>
                 ))),
             )
         } else if choose_mat < 0.95 {
-            factory.sphere(
+            sphere(
                 center,
                 0.2,
                 metal(
@@ -50,7 +60,7 @@ fn world<C>(factory: &HitableFactory<C>, t0: Float, t1: Float) -> HitableBox<C>
                 ),
             )
         } else {
-            factory.sphere(center, 0.2, dielectric(1.5))
+            sphere(center, 0.2, dielectric(1.5))
         }
     }
 
@@ -62,36 +72,41 @@ fn world<C>(factory: &HitableFactory<C>, t0: Float, t1: Float) -> HitableBox<C>
                 b as Float + 0.9 * rng.gen::<Float>(),
             );
             if (center - pos(4., 0.2, 0.)).length() > 0.9 {
-                list.push(random_sphere(factory, center))
+                list.push(random_sphere(center))
             }
         }
     }
 
-    list.push(factory.sphere(pos(0., 1., 0.), 1., dielectric(1.5)));
-    list.push(factory.sphere(
+    list.push(sphere(pos(0., 1., 0.), 1., dielectric(1.5)));
+    list.push(sphere(
         pos(-4., 1., 0.),
         1.,
         lambertian(constant_texture(col(0.4, 0.2, 0.1))),
     ));
-    list.push(factory.sphere(pos(4., 1., 0.), 1., metal(col(0.7, 0.6, 0.5), 0.)));
-    let objs = factory.bounding_hierarchy(list, t0, t1);
+    list.push(sphere(pos(4., 1., 0.), 1., metal(col(0.7, 0.6, 0.5), 0.)));
+    let objs = bounding_hierarchy(list, t0, t1);
 
     let mut list = vec![];
     let checker = checker(
         constant_texture(col(0.2, 0.3, 0.1)),
         constant_texture(col(0.9, 0.9, 0.9)),
     );
-    list.push(factory.sphere(pos(0., -1000., 0.), 1000., lambertian(checker)));
+    list.push(sphere(pos(0., -1000., 0.), 1000., lambertian(checker)));
     list.push(objs);
+    list.push(light);
 
-    factory.bounding_hierarchy(list, t0, t1)
+    bounding_hierarchy(list, t0, t1)
 }
 
-pub fn scene<C, H: HitableFactory<C>>(factory: &H, settings: &Settings) -> Scene<C> {
+/// The caustics under the glass spheres are best rendered with `IntegratorKind::PhotonMapping`.
+pub fn scene(settings: &Settings) -> Scene {
     let t0 = 0.;
     let t1 = 1.;
+    let light = Arc::new(light());
     Scene {
         camera: camera(settings, t0, t1),
-        world: world(factory, t0, t1),
+        world: world(shared(&light), t0, t1),
+        lights: vec![shared(&light)],
+        background: sky,
     }
 }
@@ -1,31 +1,38 @@
 use crate::scene::prelude::*;
 
-fn camera(settings: &Settings) -> Camera {
+fn camera(settings: &Settings) -> CameraBox {
     let look_from = pos(13., 2., 3.);
     let look_at = pos(0., 0., 0.);
     crate::scene::camera(
-        look_from,
-        look_at,
-        dir(0., 1., 0.),
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
         20.,
         0.,
         10.,
         settings,
-        0.,
-        0.,
+        (0., 0.),
     )
 }
 
-fn world<C>(factory: &HitableFactory<C>) -> HitableBox<C> {
+fn world() -> HitableBox {
     let mut list = vec![];
-    list.push(factory.sphere(pos(0., -1000., 0.), 1000., lambertian(noise_texture(1.))));
-    list.push(factory.sphere(pos(0., 2., 0.), 2., lambertian(noise_texture(1.))));
-    factory.bounding_hierarchy(list, 0., 0.)
+    list.push(sphere(
+        pos(0., -1000., 0.),
+        1000.,
+        lambertian(noise_texture(1.)),
+    ));
+    list.push(sphere(pos(0., 2., 0.), 2., lambertian(noise_texture(1.))));
+    bounding_hierarchy(list, 0., 0.)
 }
 
-pub fn scene<C, H: HitableFactory<C>>(factory: &H, settings: &Settings) -> Scene<C> {
+pub fn scene(settings: &Settings) -> Scene {
     Scene {
         camera: camera(settings),
-        world: world(factory),
+        world: world(),
+        lights: vec![],
+        background: sky,
     }
 }
@@ -0,0 +1,45 @@
+use crate::scene::prelude::*;
+
+fn camera(settings: &Settings) -> CameraBox {
+    let look_from = pos(26., 3., 6.);
+    let look_at = pos(0., 2., 0.);
+    crate::scene::camera(
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
+        20.,
+        0.,
+        10.,
+        settings,
+        (0., 0.),
+    )
+}
+
+fn light() -> HitableBox {
+    sphere(
+        pos(0., 7., 0.),
+        1.,
+        diffuse_light(constant_texture(col(8., 8., 8.))),
+    )
+}
+
+fn world(light: HitableBox) -> HitableBox {
+    let list = vec![
+        sphere(pos(0., -1000., 0.), 1000., lambertian(noise_texture(4.))),
+        sphere(pos(0., 2., 0.), 2., lambertian(noise_texture(4.))),
+        light,
+    ];
+    bounding_hierarchy(list, 0., 0.)
+}
+
+pub fn scene(settings: &Settings) -> Scene {
+    let light = Arc::new(light());
+    Scene {
+        camera: camera(settings),
+        world: world(shared(&light)),
+        lights: vec![shared(&light)],
+        background: black,
+    }
+}
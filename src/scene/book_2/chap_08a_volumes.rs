@@ -0,0 +1,66 @@
+use crate::scene::prelude::*;
+
+fn camera(settings: &Settings) -> CameraBox {
+    let look_from = pos(13., 2., 3.);
+    let look_at = pos(0., 1., 0.);
+    crate::scene::camera(
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
+        30.,
+        0.,
+        10.,
+        settings,
+        (0., 0.),
+    )
+}
+
+fn world() -> HitableBox {
+    let list = vec![
+        sphere(
+            pos(0., -1000., 0.),
+            1000.,
+            lambertian(constant_texture(col(0.48, 0.83, 0.53))),
+        ),
+        // Smoke and fog
+        constant_medium(
+            sphere(pos(-3., 1., -1.), 1., dielectric(1.5)),
+            1.,
+            constant_texture(col(0., 0., 0.)),
+        ),
+        constant_medium(
+            sphere(pos(0., 1., 2.), 1., dielectric(1.5)),
+            1.,
+            constant_texture(col(1., 1., 1.)),
+        ),
+        // Subsurface-looking glass ball: a dense blue medium inside a dielectric shell
+        sphere(pos(2., 1., -1.), 1., dielectric(1.5)),
+        constant_medium(
+            sphere(pos(2., 1., -1.), 1., dielectric(1.5)),
+            0.2,
+            constant_texture(col(0.2, 0.4, 0.9)),
+        ),
+    ];
+
+    let objects = bounding_hierarchy(list, 0., 0.);
+
+    // Thin mist over the whole scene
+    let mist = constant_medium(
+        sphere(pos(0., 0., 0.), 5000., dielectric(1.5)),
+        0.0001,
+        constant_texture(col(1., 1., 1.)),
+    );
+
+    hitable_list(vec![objects, mist])
+}
+
+pub fn scene(settings: &Settings) -> Scene {
+    Scene {
+        camera: camera(settings),
+        world: world(),
+        lights: vec![],
+        background: sky,
+    }
+}
@@ -0,0 +1,45 @@
+use crate::scene::prelude::*;
+
+fn camera(settings: &Settings) -> CameraBox {
+    let look_from = pos(13., 2., 3.);
+    let look_at = pos(0., 1., 0.);
+    crate::scene::camera(
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
+        30.,
+        0.,
+        10.,
+        settings,
+        (0., 0.),
+    )
+}
+
+fn world() -> HitableBox {
+    let list = vec![
+        sphere(
+            pos(0., -1000., 0.),
+            1000.,
+            lambertian(constant_texture(col(0.48, 0.83, 0.53))),
+        ),
+        // A cloud whose density follows Perlin noise
+        heterogeneous_medium(
+            sphere(pos(0., 2., 0.), 2., dielectric(1.5)),
+            noise_density(2., 4.),
+            constant_texture(col(1., 1., 1.)),
+        ),
+    ];
+
+    bounding_hierarchy(list, 0., 0.)
+}
+
+pub fn scene(settings: &Settings) -> Scene {
+    Scene {
+        camera: camera(settings),
+        world: world(),
+        lights: vec![],
+        background: sky,
+    }
+}
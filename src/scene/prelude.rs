@@ -1,10 +1,22 @@
 pub use crate::{
-    camera::Camera,
-    hitable::{HitableBox, HitableFactory, Stats},
-    material::{dielectric, lambertian, metal, MaterialBox},
+    animation::{CameraPath, Interpolation, Keyframes},
+    camera::{
+        CameraBox, Equirectangular, Fisheye, Lens, Moving, Orthographic, Perspective, Physical,
+        Placement, Projection, Realistic, RollingShutter, Stereo,
+    },
+    hitable::{
+        animated, bounding_hierarchy, constant_medium, heterogeneous_medium, hitable_list,
+        moving_sphere, shared, sphere, HitableBox,
+    },
+    material::{
+        absorbing_dielectric, coated, dielectric, diffuse_light, dispersive_dielectric, lambertian,
+        metal, mix, Ior, MaterialBox,
+    },
     prelude::*,
-    scene::Scene,
-    texture::{checker, constant_texture, noise_texture},
+    scene::{black, sky, Scene},
+    texture::{checker, constant_texture, noise_density, noise_texture, voxel_density},
+    transform::{Rotation, Transform},
     Settings,
 };
 pub use rand::prelude::*;
+pub use std::sync::Arc;
@@ -0,0 +1,4 @@
+pub mod coloured_glass;
+pub mod dispersion;
+pub mod layered;
+pub mod voxel_smoke;
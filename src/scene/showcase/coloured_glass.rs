@@ -0,0 +1,58 @@
+use crate::scene::prelude::*;
+
+fn camera(settings: &Settings) -> CameraBox {
+    let look_from = pos(13., 2., 3.);
+    let look_at = pos(0., 1., 0.);
+    crate::scene::camera(
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
+        20.,
+        0.,
+        10.,
+        settings,
+        (0., 0.),
+    )
+}
+
+fn world() -> HitableBox {
+    let checker = checker(
+        constant_texture(col(0.2, 0.3, 0.1)),
+        constant_texture(col(0.9, 0.9, 0.9)),
+    );
+    let list = vec![
+        sphere(pos(0., -1000., 0.), 1000., lambertian(checker)),
+        // Thick bottle glass
+        sphere(
+            pos(0., 1., 0.),
+            1.,
+            absorbing_dielectric(1.5, col(0.4, 0.8, 0.5), 1.),
+        ),
+        // A ball in tinted water, which is absorbed on the way to it and back
+        sphere(
+            pos(-4., 1., 0.),
+            1.,
+            absorbing_dielectric(1.33, col(0.6, 0.8, 0.95), 1.),
+        ),
+        sphere(
+            pos(-4., 1., 0.),
+            0.5,
+            lambertian(constant_texture(col(0.9, 0.5, 0.1))),
+        ),
+        // Clear glass, for comparison
+        sphere(pos(-2., 1., -3.), 1., dielectric(1.5)),
+    ];
+    bounding_hierarchy(list, 0., 0.)
+}
+
+/// The big spheres of the checker floor scene, in coloured glass.
+pub fn scene(settings: &Settings) -> Scene {
+    Scene {
+        camera: camera(settings),
+        world: world(),
+        lights: vec![],
+        background: sky,
+    }
+}
@@ -0,0 +1,52 @@
+use crate::scene::prelude::*;
+
+fn camera(settings: &Settings) -> CameraBox {
+    let look_from = pos(13., 2., 3.);
+    let look_at = pos(0., 1., 0.);
+    crate::scene::camera(
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
+        20.,
+        0.,
+        10.,
+        settings,
+        (0., 0.),
+    )
+}
+
+fn world() -> HitableBox {
+    let checker = checker(
+        constant_texture(col(0.05, 0.05, 0.05)),
+        constant_texture(col(0.9, 0.9, 0.9)),
+    );
+    // Schott SF11 dense flint
+    let flint = Ior::Sellmeier {
+        b: [1.737_597, 0.313_747_35, 1.878_781],
+        c: [0.013_188_707, 0.062_306_814, 155.236_3],
+    };
+    // Far more dispersive than any real glass, to show it plainly
+    let exaggerated = Ior::Cauchy { a: 1.5, b: 0.05 };
+    let list = vec![
+        sphere(pos(0., -1000., 0.), 1000., lambertian(checker)),
+        sphere(pos(0., 1., 0.), 1., dispersive_dielectric(flint)),
+        sphere(pos(-4., 1., 0.), 1., dispersive_dielectric(exaggerated)),
+        // Crown glass, which disperses little, for comparison
+        sphere(pos(-2., 1., -3.), 1., dielectric(1.5)),
+    ];
+    bounding_hierarchy(list, 0., 0.)
+}
+
+/// The big spheres of the checker floor scene in dispersive glass, over a black and white floor
+/// whose edges come out in colour through them. Render it with `settings.spectral` set: in RGB
+/// every glass refracts all colours alike.
+pub fn scene(settings: &Settings) -> Scene {
+    Scene {
+        camera: camera(settings),
+        world: world(),
+        lights: vec![],
+        background: sky,
+    }
+}
@@ -0,0 +1,61 @@
+use crate::scene::prelude::*;
+
+fn camera(settings: &Settings) -> CameraBox {
+    let look_from = pos(13., 2., 3.);
+    let look_at = pos(0., 1., 0.);
+    crate::scene::camera(
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
+        20.,
+        0.,
+        10.,
+        settings,
+        (0., 0.),
+    )
+}
+
+fn world() -> HitableBox {
+    let checker = checker(
+        constant_texture(col(0.2, 0.3, 0.1)),
+        constant_texture(col(0.9, 0.9, 0.9)),
+    );
+    let list = vec![
+        sphere(pos(0., -1000., 0.), 1000., lambertian(checker)),
+        // Red car paint
+        sphere(
+            pos(0., 1., 0.),
+            1.,
+            coated(1.5, lambertian(constant_texture(col(0.6, 0.05, 0.05)))),
+        ),
+        // Varnished brass
+        sphere(
+            pos(-2., 1., -3.),
+            1.,
+            coated(1.5, metal(col(0.8, 0.6, 0.2), 0.3)),
+        ),
+        // Polished metal worn through to a white undercoat in places
+        sphere(
+            pos(-4., 1., 0.),
+            1.,
+            mix(
+                metal(col(0.7, 0.6, 0.5), 0.),
+                lambertian(constant_texture(col(0.8, 0.8, 0.8))),
+                noise_texture(4.),
+            ),
+        ),
+    ];
+    bounding_hierarchy(list, 0., 0.)
+}
+
+/// The big spheres of the checker floor scene, in layered and blended materials.
+pub fn scene(settings: &Settings) -> Scene {
+    Scene {
+        camera: camera(settings),
+        world: world(),
+        lights: vec![],
+        background: sky,
+    }
+}
@@ -0,0 +1,60 @@
+use crate::scene::prelude::*;
+
+fn camera(settings: &Settings) -> CameraBox {
+    let look_from = pos(13., 3., 5.);
+    let look_at = pos(0., 1.5, 0.);
+    crate::scene::camera(
+        Placement {
+            look_from,
+            look_at,
+            up: dir(0., 1., 0.),
+        },
+        25.,
+        0.,
+        10.,
+        settings,
+        (0., 0.),
+    )
+}
+
+fn light() -> HitableBox {
+    sphere(
+        pos(-3., 7., 2.),
+        1.,
+        diffuse_light(constant_texture(col(12., 12., 12.))),
+    )
+}
+
+fn world(light: HitableBox) -> HitableBox {
+    let min = pos(-1.5, 0., -1.5);
+    let max = pos(1.5, 3., 1.5);
+    let density =
+        voxel_density("volumes/puff.vol", min, max, 6.).expect("Couldn't load the voxel grid");
+
+    let list = vec![
+        sphere(
+            pos(0., -1000., 0.),
+            1000.,
+            lambertian(constant_texture(col(0.5, 0.5, 0.5))),
+        ),
+        // The grid is zero outside its box, so any boundary around the box will do
+        heterogeneous_medium(
+            sphere(pos(0., 1.5, 0.), 2.6, dielectric(1.5)),
+            density,
+            constant_texture(col(0.9, 0.9, 0.9)),
+        ),
+        light,
+    ];
+    bounding_hierarchy(list, 0., 0.)
+}
+
+/// A puff of smoke loaded from a voxel grid, lit from above so that it casts a soft shadow.
+pub fn scene(settings: &Settings) -> Scene {
+    let light = Arc::new(light());
+    Scene {
+        camera: camera(settings),
+        world: world(shared(&light)),
+        lights: vec![shared(&light)],
+        background: black,
+    }
+}
@@ -1,11 +1,38 @@
 use super::Settings;
+use crate::{
+    camera::{Aperture, ApertureMask, Bokeh, Projection},
+    denoise::Denoise,
+    filter::FilterKind,
+    integrator::IntegratorKind,
+    sampler::SamplerKind,
+};
+use std::sync::Arc;
 
 pub fn low() -> Settings {
     Settings {
         width: 200,
         height: 100,
         samples: 100,
+        target_noise: None,
         depth: 50,
+        roulette_depth: 5,
+        spectral: false,
+        projection: Projection::Perspective,
+        stereo: None,
+        lens: None,
+        bokeh: Bokeh::default(),
+        shutter: None,
+        rolling_shutter: None,
+        animation: None,
+        autofocus: None,
+        sampler: SamplerKind::Independent,
+        filter: FilterKind::Box,
+        filter_radius: 0.5,
+        denoise: None,
+        aovs: false,
+        stats: false,
+        heat_maps: false,
+        integrator: IntegratorKind::Iterative,
     }
 }
 
@@ -14,6 +41,47 @@ pub fn high() -> Settings {
         width: 1280,
         height: 720,
         samples: 100,
+        target_noise: None,
         depth: 50,
+        roulette_depth: 5,
+        spectral: false,
+        projection: Projection::Perspective,
+        stereo: None,
+        lens: None,
+        bokeh: Bokeh::default(),
+        shutter: None,
+        rolling_shutter: None,
+        animation: None,
+        autofocus: None,
+        sampler: SamplerKind::Independent,
+        filter: FilterKind::Box,
+        filter_radius: 0.5,
+        denoise: None,
+        aovs: false,
+        stats: false,
+        heat_maps: false,
+        integrator: IntegratorKind::Iterative,
+    }
+}
+
+/// Quick, denoised renders for checking a scene.
+pub fn preview() -> Settings {
+    Settings {
+        samples: 16,
+        denoise: Some(Denoise::default()),
+        ..high()
+    }
+}
+
+/// Out of focus highlights in the shape of the star in `apertures/star.png`, for scenes whose
+/// camera has an open aperture.
+pub fn star_bokeh() -> Settings {
+    let mask = ApertureMask::open("apertures/star.png").expect("Couldn't load the aperture mask");
+    Settings {
+        bokeh: Bokeh {
+            aperture: Aperture::Mask(Arc::new(mask)),
+            ..Bokeh::default()
+        },
+        ..high()
     }
 }
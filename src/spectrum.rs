@@ -0,0 +1,138 @@
+//! Helpers for spectral rendering: each camera sample traces a single wavelength, the RGB colours
+//! of textures and materials are turned into spectral values where they are looked up, and the
+//! film turns the result back into RGB through the CIE 1931 colour matching functions.
+
+use crate::prelude::*;
+use lazy_static::lazy_static;
+
+pub const LAMBDA_MIN: Float = 380.;
+pub const LAMBDA_MAX: Float = 780.;
+
+/// Maps a uniform random number to a wavelength in nanometres, uniformly over the visible range.
+pub fn sample_wavelength(u: Float) -> Float {
+    LAMBDA_MIN + u * (LAMBDA_MAX - LAMBDA_MIN)
+}
+
+fn smoothstep(edge0: Float, edge1: Float, x: Float) -> Float {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+/// Value at `wavelength` of a smooth spectrum whose colour is approximately `c`.
+///
+/// This uses three smoothed box spectra for blue, green and red which add up to one everywhere,
+/// so white stays perfectly flat. It is not an exact inverse of the film response, but it is
+/// cheap and keeps reflectances within [0, 1].
+pub fn from_rgb(c: Col, wavelength: Float) -> Float {
+    let blue_to_green = smoothstep(490., 510., wavelength);
+    let green_to_red = smoothstep(580., 600., wavelength);
+    let blue = 1. - blue_to_green;
+    let red = green_to_red;
+    let green = blue_to_green - green_to_red;
+    c.r() * red + c.g() * green + c.b() * blue
+}
+
+/// Reduces an RGB attenuation or radiance to the ray's wavelength, or leaves it alone when the
+/// ray is not spectral.
+#[inline]
+pub fn project(c: Col, wavelength: Option<Float>) -> Col {
+    match wavelength {
+        Some(l) => {
+            let s = from_rgb(c, l);
+            col(s, s, s)
+        }
+        None => c,
+    }
+}
+
+fn lobe(x: Float, mu: Float, sigma1: Float, sigma2: Float) -> Float {
+    let t = (x - mu) / if x < mu { sigma1 } else { sigma2 };
+    (-0.5 * t * t).exp()
+}
+
+/// CIE 1931 2° colour matching functions, using the multi-lobe fit from Wyman, Sloan and Shirley,
+/// "Simple Analytic Approximations to the CIE XYZ Color Matching Functions" (2013).
+pub fn xyz(wavelength: Float) -> [Float; 3] {
+    let l = wavelength;
+    [
+        1.056 * lobe(l, 599.8, 37.9, 31.0) + 0.362 * lobe(l, 442.0, 16.0, 26.7)
+            - 0.065 * lobe(l, 501.1, 20.4, 26.2),
+        0.821 * lobe(l, 568.8, 46.9, 40.5) + 0.286 * lobe(l, 530.9, 16.3, 31.1),
+        1.217 * lobe(l, 437.0, 11.8, 36.0) + 0.681 * lobe(l, 459.0, 26.0, 13.8),
+    ]
+}
+
+fn xyz_to_linear_srgb([x, y, z]: [Float; 3]) -> Col {
+    col(
+        3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+        -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z,
+        0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+    )
+}
+
+lazy_static! {
+    /// Response of the film to a flat spectrum of unit radiance, used to white balance so that
+    /// white surfaces under a white sky come out white.
+    static ref WHITE: Col = {
+        let steps = (LAMBDA_MAX - LAMBDA_MIN) as usize;
+        (0..steps)
+            .map(|i| xyz_to_linear_srgb(xyz(LAMBDA_MIN + i as Float + 0.5)))
+            .sum::<Col>()
+    };
+}
+
+/// Linear RGB estimate for a sample of `radiance` at a wavelength drawn by `sample_wavelength`.
+pub fn to_rgb(wavelength: Float, radiance: Float) -> Col {
+    let c = xyz_to_linear_srgb(xyz(wavelength)) * (radiance * (LAMBDA_MAX - LAMBDA_MIN));
+    c / *WHITE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::constant_texture;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_white_is_flat() {
+        let white = col(1., 1., 1.);
+        for l in (380..780).step_by(10) {
+            assert_approx_eq!(1., from_rgb(white, l as Float));
+        }
+    }
+
+    #[test]
+    fn test_flat_spectrum_comes_out_white() {
+        let n = 400;
+        let c = (0..n)
+            .map(|i| to_rgb(sample_wavelength((i as Float + 0.5) / n as Float), 1.))
+            .sum::<Col>()
+            / n as Float;
+        assert_approx_eq!(1., c.r(), 1e-3);
+        assert_approx_eq!(1., c.g(), 1e-3);
+        assert_approx_eq!(1., c.b(), 1e-3);
+    }
+
+    #[test]
+    fn test_textures_are_looked_up_at_the_wavelength() {
+        let red = constant_texture(col(1., 0., 0.));
+        let p = Pos::zero();
+        assert_eq!(col(1., 0., 0.), red.spectral_value(0., 0., p, None));
+        assert_eq!(col(1., 1., 1.), red.spectral_value(0., 0., p, Some(650.)));
+        assert_eq!(Col::zero(), red.spectral_value(0., 0., p, Some(450.)));
+    }
+
+    #[test]
+    fn test_red_stays_reddish() {
+        let n = 400;
+        let c = (0..n)
+            .map(|i| {
+                let l = sample_wavelength((i as Float + 0.5) / n as Float);
+                to_rgb(l, from_rgb(col(1., 0., 0.), l))
+            })
+            .sum::<Col>()
+            / n as Float;
+        assert!(c.r() > c.g());
+        assert!(c.r() > c.b());
+    }
+}
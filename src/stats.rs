@@ -0,0 +1,268 @@
+//! Counts of what rendering does: rays traced by kind, intersection tests by kind of hitable,
+//! lengths of paths and time spent building bounding hierarchies.
+//!
+//! Nothing is counted unless the crate is built with the `stats` feature, and without it the
+//! counting functions are empty, so they cost nothing. Each thread counts into its own `Stats`,
+//! without locks, which `take` hands over to be added up with those of the other threads.
+
+use crate::prelude::*;
+use std::{ops::AddAssign, time::Duration};
+
+/// Whether this build counts anything.
+pub const ENABLED: bool = cfg!(feature = "stats");
+
+/// What kind of hitable a ray was tested against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    /// A node of a bounding hierarchy, tested once for each visit.
+    BoundingBox,
+    Sphere,
+    MovingSphere,
+    List,
+    Transformed,
+    ConstantMedium,
+    HeterogeneousMedium,
+}
+
+pub const CATEGORIES: [Category; 7] = [
+    Category::BoundingBox,
+    Category::Sphere,
+    Category::MovingSphere,
+    Category::List,
+    Category::Transformed,
+    Category::ConstantMedium,
+    Category::HeterogeneousMedium,
+];
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::BoundingBox => "bounding_box",
+            Category::Sphere => "sphere",
+            Category::MovingSphere => "moving_sphere",
+            Category::List => "list",
+            Category::Transformed => "transformed",
+            Category::ConstantMedium => "constant_medium",
+            Category::HeterogeneousMedium => "heterogeneous_medium",
+        }
+    }
+
+    /// Whether testing a ray against the hitable tests it against a shape, rather than against
+    /// other hitables.
+    pub fn is_primitive(self) -> bool {
+        matches!(self, Category::Sphere | Category::MovingSphere)
+    }
+}
+
+/// What a ray is traced through the world for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RayKind {
+    /// From the camera, or the first hit seen through it.
+    Primary,
+    /// Scattered along a path, or followed from a light.
+    Secondary,
+    /// Towards a point, only to tell whether it can be seen.
+    Shadow,
+}
+
+pub const RAY_KINDS: [RayKind; 3] = [RayKind::Primary, RayKind::Secondary, RayKind::Shadow];
+
+impl RayKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            RayKind::Primary => "primary",
+            RayKind::Secondary => "secondary",
+            RayKind::Shadow => "shadow",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Record {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl Record {
+    /// Number of times the hitable was tested against a ray.
+    pub fn tests(&self) -> usize {
+        self.hits + self.misses
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    by_category: [Record; CATEGORIES.len()],
+    /// Number of rays traced, by kind.
+    rays: [usize; RAY_KINDS.len()],
+    /// Number of paths by number of bounces.
+    path_lengths: Vec<usize>,
+    /// Time spent building the outermost bounding hierarchies.
+    bvh_time: Duration,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn test(&mut self, category: Category, hit: bool) {
+        let record = &mut self.by_category[category as usize];
+        if hit {
+            record.hits += 1;
+        } else {
+            record.misses += 1;
+        }
+    }
+
+    pub fn ray(&mut self, kind: RayKind) {
+        self.rays[kind as usize] += 1;
+    }
+
+    pub fn path_length(&mut self, bounces: usize) {
+        if self.path_lengths.len() <= bounces {
+            self.path_lengths.resize(bounces + 1, 0);
+        }
+        self.path_lengths[bounces] += 1;
+    }
+
+    /// Hits and misses of each category of hitable which was tested at all.
+    pub fn records(&self) -> impl Iterator<Item = (Category, &Record)> {
+        CATEGORIES
+            .iter()
+            .map(move |&c| (c, self.record(c)))
+            .filter(|(_, r)| r.tests() > 0)
+    }
+
+    pub fn record(&self, category: Category) -> &Record {
+        &self.by_category[category as usize]
+    }
+
+    pub fn rays(&self, kind: RayKind) -> usize {
+        self.rays[kind as usize]
+    }
+
+    pub fn total_rays(&self) -> usize {
+        self.rays.iter().sum()
+    }
+
+    /// Number of times rays were tested against the box of a node of a bounding hierarchy, which
+    /// is once for each node visited.
+    pub fn box_tests(&self) -> usize {
+        self.record(Category::BoundingBox).tests()
+    }
+
+    /// Number of times rays were tested against the shape of a primitive.
+    pub fn primitive_tests(&self) -> usize {
+        CATEGORIES
+            .iter()
+            .filter(|c| c.is_primitive())
+            .map(|&c| self.record(c).tests())
+            .sum()
+    }
+
+    pub fn path_lengths(&self) -> &[usize] {
+        &self.path_lengths
+    }
+
+    /// Mean number of bounces of the paths, or zero if there were none.
+    pub fn average_path_length(&self) -> Float {
+        let paths: usize = self.path_lengths.iter().sum();
+        if paths == 0 {
+            return 0.;
+        }
+        let bounces: usize = self
+            .path_lengths
+            .iter()
+            .enumerate()
+            .map(|(b, n)| b * n)
+            .sum();
+        bounces as Float / paths as Float
+    }
+
+    pub fn bvh_time(&self) -> Duration {
+        self.bvh_time
+    }
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, rhs: Self) {
+        for (d, s) in self.by_category.iter_mut().zip(&rhs.by_category) {
+            d.hits += s.hits;
+            d.misses += s.misses;
+        }
+        for (d, s) in self.rays.iter_mut().zip(&rhs.rays) {
+            *d += s;
+        }
+        if self.path_lengths.len() < rhs.path_lengths.len() {
+            self.path_lengths.resize(rhs.path_lengths.len(), 0);
+        }
+        for (d, s) in self.path_lengths.iter_mut().zip(rhs.path_lengths) {
+            *d += s;
+        }
+        self.bvh_time += rhs.bvh_time;
+    }
+}
+
+#[cfg(feature = "stats")]
+thread_local! {
+    static STATS: std::cell::RefCell<Stats> = Default::default();
+}
+
+/// Applies `f` to the counts of this thread.
+#[cfg(feature = "stats")]
+#[inline]
+fn record<F: FnOnce(&mut Stats)>(f: F) {
+    STATS.with(|stats| f(&mut stats.borrow_mut()))
+}
+
+#[cfg(not(feature = "stats"))]
+#[inline(always)]
+fn record<F: FnOnce(&mut Stats)>(_f: F) {}
+
+/// Counts a test of a ray against a hitable of `category`.
+#[inline]
+pub fn test(category: Category, hit: bool) {
+    record(|stats| stats.test(category, hit))
+}
+
+#[inline]
+pub fn ray(kind: RayKind) {
+    record(|stats| stats.ray(kind))
+}
+
+#[inline]
+pub fn path_length(bounces: usize) {
+    record(|stats| stats.path_length(bounces))
+}
+
+pub fn bvh_built(time: Duration) {
+    record(|stats| stats.bvh_time += time)
+}
+
+/// The counts of this thread since they were last taken.
+pub fn take() -> Stats {
+    let mut taken = Stats::new();
+    record(|stats| taken = std::mem::replace(stats, Stats::new()));
+    taken
+}
+
+#[cfg(all(test, feature = "stats"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_are_taken_per_thread() {
+        take();
+        ray(RayKind::Primary);
+        test(Category::Sphere, true);
+        test(Category::BoundingBox, false);
+        std::thread::spawn(|| ray(RayKind::Shadow)).join().unwrap();
+
+        let stats = take();
+        assert_eq!(1, stats.total_rays());
+        assert_eq!(1, stats.primitive_tests());
+        assert_eq!(1, stats.box_tests());
+        assert_eq!(0, take().total_rays());
+    }
+}
@@ -1,10 +1,20 @@
+mod density;
 mod perlin;
 
-use crate::prelude::*;
-use std::fmt::Debug;
+use crate::{prelude::*, spectrum};
+use std::{fmt::Debug, path::Path};
+
+pub use density::{DensityBox, LoadVoxelGridError};
 
 pub trait Texture: Debug + TextureClone {
     fn value(&self, u: Float, v: Float, p: Pos) -> Col;
+
+    /// The colour at `p` as a spectral value at `wavelength`, for rays which carry one, or as
+    /// RGB for those which don't.
+    #[inline]
+    fn spectral_value(&self, u: Float, v: Float, p: Pos, wavelength: Option<Float>) -> Col {
+        spectrum::project(self.value(u, v, p), wavelength)
+    }
 }
 
 pub type TextureBox = Box<dyn Texture + Send + Sync>;
@@ -67,3 +77,18 @@ pub fn noise_texture(scale: Float) -> TextureBox {
     use perlin::NoiseTexture;
     Box::new(NoiseTexture::new(scale))
 }
+
+pub fn noise_density(scale: Float, density: Float) -> DensityBox {
+    Box::new(density::NoiseDensity::new(scale, density))
+}
+
+/// Loads a voxel grid from `path` (see `VoxelGrid::parse` for the format), stretched over the
+/// box from `min` to `max`, with every voxel multiplied by `scale`.
+pub fn voxel_density<P: AsRef<Path>>(
+    path: P,
+    min: Pos,
+    max: Pos,
+    scale: Float,
+) -> Result<DensityBox, LoadVoxelGridError> {
+    Ok(Box::new(density::VoxelGrid::load(path, min, max, scale)?))
+}
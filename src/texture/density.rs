@@ -0,0 +1,299 @@
+use super::perlin::noise;
+use crate::prelude::*;
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display},
+    fs,
+    num::{ParseFloatError, ParseIntError},
+    path::Path,
+};
+
+/// A scalar field giving the density of a heterogeneous participating medium.
+pub trait Density: Debug {
+    fn density(&self, p: Pos) -> Float;
+
+    /// An upper bound of `density` over the whole volume, used as the majorant for delta and ratio
+    /// tracking. The tighter it is, the fewer null collisions.
+    fn max_density(&self) -> Float;
+}
+
+pub type DensityBox = Box<dyn Density + Send + Sync>;
+
+#[derive(Clone, Debug)]
+pub struct NoiseDensity {
+    scale: Float,
+    density: Float,
+}
+
+impl NoiseDensity {
+    pub fn new(scale: Float, density: Float) -> Self {
+        Self { scale, density }
+    }
+}
+
+impl Density for NoiseDensity {
+    #[inline]
+    fn density(&self, p: Pos) -> Float {
+        let n = 0.5 * (1. + noise(self.scale * p));
+        self.density * n.clamp(0., 1.)
+    }
+
+    fn max_density(&self) -> Float {
+        self.density
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadVoxelGridError {
+    Io(std::io::Error),
+    MissingDimensions,
+    ParseIntError(ParseIntError),
+    ParseFloatError(ParseFloatError),
+    TooManyVoxels([usize; 3]),
+    WrongVoxelCount { expected: usize, found: usize },
+    BadDensity { index: usize, density: Float },
+}
+
+impl Display for LoadVoxelGridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadVoxelGridError::Io(e) => write!(f, "couldn't read the voxel grid: {}", e),
+            LoadVoxelGridError::MissingDimensions => write!(f, "the voxel grid has no dimensions"),
+            LoadVoxelGridError::ParseIntError(e) => write!(f, "bad voxel grid dimension: {}", e),
+            LoadVoxelGridError::ParseFloatError(e) => write!(f, "bad voxel density: {}", e),
+            LoadVoxelGridError::TooManyVoxels([nx, ny, nz]) => write!(
+                f,
+                "a {}x{}x{} voxel grid is too big to hold in memory",
+                nx, ny, nz
+            ),
+            LoadVoxelGridError::WrongVoxelCount { expected, found } => write!(
+                f,
+                "expected {} voxels from the dimensions, found {}",
+                expected, found
+            ),
+            LoadVoxelGridError::BadDensity { index, density } => write!(
+                f,
+                "voxel {} has a density {}, which isn't finite and non-negative",
+                index, density
+            ),
+        }
+    }
+}
+
+impl Error for LoadVoxelGridError {}
+
+impl From<std::io::Error> for LoadVoxelGridError {
+    fn from(e: std::io::Error) -> LoadVoxelGridError {
+        LoadVoxelGridError::Io(e)
+    }
+}
+
+impl From<ParseIntError> for LoadVoxelGridError {
+    fn from(e: ParseIntError) -> LoadVoxelGridError {
+        LoadVoxelGridError::ParseIntError(e)
+    }
+}
+
+impl From<ParseFloatError> for LoadVoxelGridError {
+    fn from(e: ParseFloatError) -> LoadVoxelGridError {
+        LoadVoxelGridError::ParseFloatError(e)
+    }
+}
+
+/// Densities sampled on a regular grid spanning the box from `min` to `max`, trilinearly
+/// interpolated in between and zero outside.
+#[derive(Clone, Debug)]
+pub struct VoxelGrid {
+    dims: [usize; 3],
+    voxels: Vec<Float>,
+    min: Pos,
+    max: Pos,
+    max_density: Float,
+}
+
+impl VoxelGrid {
+    /// Parses a grid from text: the three dimensions `nx ny nz`, followed by `nx * ny * nz`
+    /// densities with x varying fastest, then y, then z. Everything is whitespace separated, and
+    /// lines starting with `#` are comments. Densities must be finite and non-negative.
+    pub fn parse(s: &str, min: Pos, max: Pos, scale: Float) -> Result<Self, LoadVoxelGridError> {
+        let mut tokens = s
+            .lines()
+            .filter(|l| !l.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace);
+
+        let mut dims = [0; 3];
+        for d in dims.iter_mut() {
+            *d = tokens
+                .next()
+                .ok_or(LoadVoxelGridError::MissingDimensions)?
+                .parse()?;
+        }
+
+        let expected = dims
+            .iter()
+            .try_fold(1usize, |n, &d| n.checked_mul(d))
+            .ok_or(LoadVoxelGridError::TooManyVoxels(dims))?;
+
+        let voxels = tokens
+            .map(|t| t.parse::<Float>().map(|d| scale * d))
+            .collect::<Result<Vec<_>, _>>()?;
+        if voxels.len() != expected || expected == 0 {
+            return Err(LoadVoxelGridError::WrongVoxelCount {
+                expected,
+                found: voxels.len(),
+            });
+        }
+
+        if let Some((index, &density)) = voxels
+            .iter()
+            .enumerate()
+            .find(|(_, &d)| !d.is_finite() || d < 0.)
+        {
+            return Err(LoadVoxelGridError::BadDensity { index, density });
+        }
+
+        let max_density = voxels.iter().cloned().fold(0., Float::max);
+        Ok(Self {
+            dims,
+            voxels,
+            min,
+            max,
+            max_density,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        min: Pos,
+        max: Pos,
+        scale: Float,
+    ) -> Result<Self, LoadVoxelGridError> {
+        Self::parse(&fs::read_to_string(path)?, min, max, scale)
+    }
+
+    #[inline]
+    fn voxel(&self, i: usize, j: usize, k: usize) -> Float {
+        let [nx, ny, _] = self.dims;
+        self.voxels[i + nx * (j + ny * k)]
+    }
+}
+
+impl Density for VoxelGrid {
+    fn density(&self, p: Pos) -> Float {
+        let mut cell = [0; 3];
+        let mut frac = [0.; 3];
+        for axis in 0..3 {
+            let n = self.dims[axis];
+            let x = (p[axis] - self.min[axis]) / (self.max[axis] - self.min[axis]);
+            if !(0. ..=1.).contains(&x) {
+                return 0.;
+            }
+            // Voxel centres sit at the middle of each of the n cells along the axis
+            let x = (x * n as Float - 0.5).max(0.).min((n - 1) as Float);
+            let i = (x.floor() as usize).min(n.saturating_sub(2));
+            cell[axis] = i;
+            frac[axis] = x - i as Float;
+        }
+
+        let [i, j, k] = cell;
+        let [u, v, w] = frac;
+        let [nx, ny, nz] = self.dims;
+        let mut accum = 0.;
+        for di in 0..nx.min(2) {
+            for dj in 0..ny.min(2) {
+                for dk in 0..nz.min(2) {
+                    let weight = if di == 0 { 1. - u } else { u }
+                        * if dj == 0 { 1. - v } else { v }
+                        * if dk == 0 { 1. - w } else { w };
+                    accum += weight * self.voxel(i + di, j + dj, k + dk);
+                }
+            }
+        }
+        accum
+    }
+
+    fn max_density(&self) -> Float {
+        self.max_density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn grid() -> VoxelGrid {
+        let s = "# a 2x2x2 grid\n2 2 2\n0 1\n0 1\n0 1\n0 1\n";
+        VoxelGrid::parse(s, pos(0., 0., 0.), pos(2., 2., 2.), 2.).unwrap()
+    }
+
+    #[test]
+    fn test_parse_voxel_grid() {
+        let g = grid();
+        assert_eq!([2, 2, 2], g.dims);
+        assert_eq!(2., g.max_density());
+    }
+
+    #[test]
+    fn test_parse_voxel_grid_with_wrong_count() {
+        let res = VoxelGrid::parse("2 2 2\n1 2 3", Pos::zero(), pos(1., 1., 1.), 1.);
+        match res {
+            Err(LoadVoxelGridError::WrongVoxelCount {
+                expected: 8,
+                found: 3,
+            }) => (),
+            _ => panic!("expected a voxel count error"),
+        }
+    }
+
+    #[test]
+    fn test_parse_voxel_grid_with_negative_density() {
+        let res = VoxelGrid::parse("2 1 1\n0.5 -1", Pos::zero(), pos(1., 1., 1.), 1.);
+        match res {
+            Err(LoadVoxelGridError::BadDensity { index: 1, .. }) => (),
+            _ => panic!("expected a bad density error"),
+        }
+    }
+
+    #[test]
+    fn test_parse_voxel_grid_with_infinite_density() {
+        for bad in &["inf", "NaN"] {
+            let s = format!("2 1 1\n{} 0.5", bad);
+            match VoxelGrid::parse(&s, Pos::zero(), pos(1., 1., 1.), 1.) {
+                Err(LoadVoxelGridError::BadDensity { index: 0, .. }) => (),
+                _ => panic!("expected a bad density error for {}", bad),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_voxel_grid_with_huge_dimensions() {
+        let s = format!("{} {} 2\n1", usize::MAX, usize::MAX);
+        match VoxelGrid::parse(&s, Pos::zero(), pos(1., 1., 1.), 1.) {
+            Err(LoadVoxelGridError::TooManyVoxels(_)) => (),
+            _ => panic!("expected a too many voxels error"),
+        }
+    }
+
+    #[test]
+    fn test_sample_voxel_grid() {
+        let g = VoxelGrid::parse(
+            include_str!("../../volumes/puff.vol"),
+            Pos::zero(),
+            pos(1., 1., 1.),
+            1.,
+        )
+        .unwrap();
+        assert_eq!([16, 16, 16], g.dims);
+        assert!(g.max_density() <= 1.);
+    }
+
+    #[test]
+    fn test_voxel_grid_interpolation() {
+        let g = grid();
+        assert_approx_eq!(0., g.density(pos(0.5, 1., 1.)));
+        assert_approx_eq!(1., g.density(pos(1., 1., 1.)));
+        assert_approx_eq!(2., g.density(pos(1.5, 0.2, 1.8)));
+        assert_approx_eq!(0., g.density(pos(3., 1., 1.)));
+    }
+}
@@ -0,0 +1,216 @@
+//! Rigid motions with uniform scaling, to place hitables in the scene.
+
+use crate::prelude::*;
+
+/// A rotation, as a unit quaternion.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rotation {
+    w: Float,
+    v: Dir,
+}
+
+impl Rotation {
+    pub fn identity() -> Self {
+        Self {
+            w: 1.,
+            v: Dir::zero(),
+        }
+    }
+
+    /// Turning by `degrees` anticlockwise around `axis`, when looking down the axis.
+    pub fn around(axis: Dir, degrees: Float) -> Self {
+        let half = degrees * PI / 180. / 2.;
+        Self {
+            w: half.cos(),
+            v: half.sin() * axis.unit_vector(),
+        }
+    }
+
+    /// The rotation turning the x, y and z axes onto the orthonormal, right-handed `u`, `v`
+    /// and `w`.
+    pub fn from_basis(u: Dir, v: Dir, w: Dir) -> Self {
+        let (m00, m01, m02) = (u.x(), v.x(), w.x());
+        let (m10, m11, m12) = (u.y(), v.y(), w.y());
+        let (m20, m21, m22) = (u.z(), v.z(), w.z());
+        let trace = m00 + m11 + m22;
+        // Divide by the largest of the four components, for accuracy
+        let (w, x, y, z) = if trace > 0. {
+            let s = 2. * (trace + 1.).sqrt();
+            (s / 4., (m21 - m12) / s, (m02 - m20) / s, (m10 - m01) / s)
+        } else if m00 > m11 && m00 > m22 {
+            let s = 2. * (1. + m00 - m11 - m22).sqrt();
+            ((m21 - m12) / s, s / 4., (m01 + m10) / s, (m02 + m20) / s)
+        } else if m11 > m22 {
+            let s = 2. * (1. + m11 - m00 - m22).sqrt();
+            ((m02 - m20) / s, (m01 + m10) / s, s / 4., (m12 + m21) / s)
+        } else {
+            let s = 2. * (1. + m22 - m00 - m11).sqrt();
+            ((m10 - m01) / s, (m02 + m20) / s, (m12 + m21) / s, s / 4.)
+        };
+        Self { w, v: dir(x, y, z) }
+    }
+
+    pub fn inverse(self) -> Self {
+        Self {
+            w: self.w,
+            v: -self.v,
+        }
+    }
+
+    pub fn rotate(self, d: Dir) -> Dir {
+        let t = 2. * self.v.cross(d);
+        d + self.w * t + self.v.cross(t)
+    }
+
+    fn dot(self, o: Self) -> Float {
+        self.w * o.w + self.v.dot(o.v)
+    }
+}
+
+impl std::ops::Mul for Rotation {
+    type Output = Self;
+
+    /// Rotating by `o`, then by `self`.
+    fn mul(self, o: Self) -> Self {
+        Self {
+            w: self.w * o.w - self.v.dot(o.v),
+            v: self.w * o.v + o.w * self.v + self.v.cross(o.v),
+        }
+    }
+}
+
+/// Scaling by `scale`, then rotating by `rotation`, then moving by `translation`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Dir,
+    pub rotation: Rotation,
+    pub scale: Float,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            translation: Dir::zero(),
+            rotation: Rotation::identity(),
+            scale: 1.,
+        }
+    }
+
+    pub fn translate(translation: Dir) -> Self {
+        Self {
+            translation,
+            ..Self::identity()
+        }
+    }
+
+    pub fn rotate(axis: Dir, degrees: Float) -> Self {
+        Self {
+            rotation: Rotation::around(axis, degrees),
+            ..Self::identity()
+        }
+    }
+
+    pub fn point(&self, p: Pos) -> Pos {
+        Pos::zero() + self.vector(p - Pos::zero()) + self.translation
+    }
+
+    pub fn vector(&self, d: Dir) -> Dir {
+        self.scale * self.rotation.rotate(d)
+    }
+
+    /// Scaling is uniform, so normals just turn.
+    pub fn normal(&self, n: Dir) -> Dir {
+        self.rotation.rotate(n)
+    }
+
+    pub fn inverse_point(&self, p: Pos) -> Pos {
+        Pos::zero() + self.inverse_vector(p - Pos::zero() - self.translation)
+    }
+
+    pub fn inverse_vector(&self, d: Dir) -> Dir {
+        self.rotation.inverse().rotate(d) / self.scale
+    }
+
+    /// The weighted average of `transforms`, whose weights add up to one. Rotations are averaged
+    /// as quaternions on the same side as the first, then normalised, which is close to
+    /// interpolating along the arcs between them when they are close together.
+    pub fn blend(transforms: &[(Float, Transform)]) -> Self {
+        let first = transforms[0].1.rotation;
+        let mut translation = Dir::zero();
+        let mut scale = 0.;
+        let (mut w, mut v) = (0., Dir::zero());
+        for &(weight, t) in transforms {
+            translation += weight * t.translation;
+            scale += weight * t.scale;
+            let sign = if t.rotation.dot(first) < 0. { -1. } else { 1. };
+            w += sign * weight * t.rotation.w;
+            v += sign * weight * t.rotation.v;
+        }
+        let norm = (w * w + v.squared_length()).sqrt();
+        Self {
+            translation,
+            rotation: Rotation {
+                w: w / norm,
+                v: v / norm,
+            },
+            scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn assert_pos_eq(expected: Pos, actual: Pos) {
+        assert_approx_eq!(expected.x(), actual.x());
+        assert_approx_eq!(expected.y(), actual.y());
+        assert_approx_eq!(expected.z(), actual.z());
+    }
+
+    #[test]
+    fn test_transform_and_inverse() {
+        let t = Transform {
+            translation: dir(1., 2., 3.),
+            rotation: Rotation::around(dir(0., 1., 0.), 90.),
+            scale: 2.,
+        };
+        let p = t.point(pos(1., 0., 0.));
+        assert_pos_eq(pos(1., 2., 1.), p);
+        assert_pos_eq(pos(1., 0., 0.), t.inverse_point(p));
+    }
+
+    #[test]
+    fn test_rotation_from_basis() {
+        for &(axis, degrees) in &[
+            (dir(0., 1., 0.), 30.),
+            (dir(1., 2., 3.), 170.),
+            (dir(-1., 0., 1.), -120.),
+        ] {
+            let r = Rotation::around(axis, degrees);
+            let (u, v, w) = (
+                r.rotate(dir(1., 0., 0.)),
+                r.rotate(dir(0., 1., 0.)),
+                r.rotate(dir(0., 0., 1.)),
+            );
+            let from_basis = Rotation::from_basis(u, v, w);
+            let p = pos(0.3, -0.5, 0.7);
+            let t = |rotation| Transform {
+                rotation,
+                ..Transform::identity()
+            };
+            assert_pos_eq(t(r).point(p), t(from_basis).point(p));
+            assert_pos_eq(p, t(from_basis.inverse() * r).point(p));
+        }
+    }
+
+    #[test]
+    fn test_blend_halfway_between_rotations() {
+        let a = Transform::rotate(dir(0., 0., 1.), 0.);
+        let b = Transform::rotate(dir(0., 0., 1.), 90.);
+        let halfway = Transform::blend(&[(0.5, a), (0.5, b)]);
+        let s = FRAC_PI_4.cos();
+        assert_pos_eq(pos(s, s, 0.), halfway.point(pos(1., 0., 0.)));
+    }
+}
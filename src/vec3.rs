@@ -1,5 +1,6 @@
 pub mod col;
 pub mod dir;
+pub mod onb;
 pub mod pos;
 
 #[cfg(test)]
@@ -20,6 +20,11 @@ impl Col {
     pub fn b(self) -> Float {
         self.0[2]
     }
+
+    /// Relative luminance, as the eye sees it, with the Rec. 709 weights.
+    pub fn luminance(self) -> Float {
+        0.2126 * self.r() + 0.7152 * self.g() + 0.0722 * self.b()
+    }
 }
 
 pub fn col(r: Float, g: Float, b: Float) -> Col {
@@ -0,0 +1,32 @@
+use crate::prelude::*;
+
+/// An orthonormal basis whose `w` axis is aligned with a given direction, for turning
+/// directions sampled around the z axis into world space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Onb {
+    u: Dir,
+    v: Dir,
+    w: Dir,
+}
+
+impl Onb {
+    pub fn from_w(n: Dir) -> Self {
+        let w = n.unit_vector();
+        let a = if w.x().abs() > 0.9 {
+            dir(0., 1., 0.)
+        } else {
+            dir(1., 0., 0.)
+        };
+        let v = w.cross(a).unit_vector();
+        let u = w.cross(v);
+        Self { u, v, w }
+    }
+
+    pub fn w(&self) -> Dir {
+        self.w
+    }
+
+    pub fn local(&self, a: Float, b: Float, c: Float) -> Dir {
+        a * self.u + b * self.v + c * self.w
+    }
+}